@@ -0,0 +1,73 @@
+//! Compares writing many small payloads with a new coroutine per
+//! payload, against a single coroutine reused across payloads.
+//!
+//! Writes are partial, so that each payload needs requests for its
+//! remaining bytes. Run it with `cargo bench --bench write`.
+
+use std::time::{Duration, Instant};
+
+use io_stream::{
+    coroutines::write::{WriteStream, WriteStreamResult},
+    io::{StreamIo, StreamOutput},
+};
+
+const PAYLOADS: u32 = 100_000;
+const PAYLOAD: &[u8] = b"PING :irc.example.org\r\n";
+const MAX_WRITE: usize = 8;
+
+fn main() {
+    report("new coroutine per payload", new_per_payload());
+    report("reused coroutine", reused());
+}
+
+fn report(name: &str, (elapsed, written): (Duration, usize)) {
+    let per_payload = elapsed / PAYLOADS;
+    println!("{name}: {written} bytes in {elapsed:?} ({per_payload:?}/payload)");
+}
+
+fn new_per_payload() -> (Duration, usize) {
+    let mut written = 0;
+    let start = Instant::now();
+
+    for _ in 0..PAYLOADS {
+        let mut write = WriteStream::new(PAYLOAD.to_vec());
+        written += write_all(&mut write).bytes_count;
+    }
+
+    (start.elapsed(), written)
+}
+
+fn reused() -> (Duration, usize) {
+    let mut write = WriteStream::new(Vec::new());
+    let mut written = 0;
+    let start = Instant::now();
+
+    for _ in 0..PAYLOADS {
+        write.replace(PAYLOAD.iter().copied());
+        let output = write_all(&mut write);
+        written += output.bytes_count;
+        write.replace_buffer(output.buffer);
+    }
+
+    (start.elapsed(), written)
+}
+
+/// Drives the given coroutine, simulating partial writes of
+/// [`MAX_WRITE`] bytes max.
+fn write_all(write: &mut WriteStream) -> StreamOutput {
+    let mut arg = None;
+
+    loop {
+        match write.resume(arg.take()) {
+            WriteStreamResult::Ok(output) => break output,
+            WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                let output = StreamOutput {
+                    bytes_count: buffer.len().min(MAX_WRITE),
+                    buffer,
+                };
+                arg = Some(StreamIo::Write(Ok(output)))
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -9,6 +9,7 @@ use std::{
 
 use io_stream::{
     coroutines::{
+        flush::{FlushStream, FlushStreamResult},
         read::{ReadStream, ReadStreamResult},
         write::{WriteStream, WriteStreamResult},
     },
@@ -46,10 +47,25 @@ fn main() {
             WriteStreamResult::Ok(_) => break,
             WriteStreamResult::Err(err) => panic!("{err}"),
             WriteStreamResult::Eof => panic!("reached unexpected EOF"),
+            // the sink is full, resuming without argument retries the
+            // remaining bytes
+            WriteStreamResult::Backpressure(_) => arg = None,
             WriteStreamResult::Io(io) => arg = Some(handle(&mut stream, io).unwrap()),
         }
     }
 
+    // sends the bytes buffered by rustls
+    let mut arg = None;
+    let mut flush = FlushStream::new();
+
+    loop {
+        match flush.resume(arg) {
+            FlushStreamResult::Ok(()) => break,
+            FlushStreamResult::Err(err) => panic!("{err}"),
+            FlushStreamResult::Io(io) => arg = Some(handle(&mut stream, io).unwrap()),
+        }
+    }
+
     let mut response = Vec::new();
 
     loop {
@@ -67,7 +83,7 @@ fn main() {
 
         let bytes = output.bytes();
 
-        match memmem::find(bytes, &[b'\r', b'\n', b'\r', b'\n']) {
+        match memmem::find(bytes, b"\r\n\r\n") {
             None => {
                 response.extend(bytes);
                 continue;
@@ -35,7 +35,7 @@ async fn main() {
 
     let mut tcp = TcpStream::connect((host.as_str(), port)).await.unwrap();
 
-    stdout.write(b"\nReceived greeting:\n").await.unwrap();
+    stdout.write_all(b"\nReceived greeting:\n").await.unwrap();
 
     let mut arg = None;
     let mut read = ReadStream::new();
@@ -52,13 +52,13 @@ async fn main() {
     let mut lines = greeting.bytes().lines();
     while let Ok(Some(line)) = lines.next_line().await {
         stdout
-            .write(format!("S: {line}\n").as_bytes())
+            .write_all(format!("S: {line}\n").as_bytes())
             .await
             .unwrap();
     }
 
     loop {
-        stdout.write(b"\n").await.unwrap();
+        stdout.write_all(b"\n").await.unwrap();
 
         let mut data = prompt(&mut stdout, "C:").await;
         data.push_str("\r\n");
@@ -71,6 +71,9 @@ async fn main() {
                 WriteStreamResult::Ok(_) => break,
                 WriteStreamResult::Err(err) => panic!("{err}"),
                 WriteStreamResult::Eof => panic!("reached unexpected EOF"),
+                // the sink is full, resuming without argument retries the
+                // remaining bytes
+                WriteStreamResult::Backpressure(_) => arg = None,
                 WriteStreamResult::Io(io) => arg = Some(handle(&mut tcp, io).await.unwrap()),
             }
         }
@@ -90,7 +93,7 @@ async fn main() {
         let mut lines = response.bytes().lines();
         while let Ok(Some(line)) = lines.next_line().await {
             stdout
-                .write(format!("S: {line}\n").as_bytes())
+                .write_all(format!("S: {line}\n").as_bytes())
                 .await
                 .unwrap();
         }
@@ -99,7 +102,7 @@ async fn main() {
 
 async fn prompt(stdout: &mut Stdout, message: &str) -> String {
     stdout
-        .write(format!("{message} ").as_bytes())
+        .write_all(format!("{message} ").as_bytes())
         .await
         .unwrap();
 
@@ -0,0 +1,34 @@
+//! Cooperative cancellation of coroutines.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag to cooperatively cancel coroutines.
+///
+/// A cancel handle can be cloned and shared across multiple
+/// coroutines, for example to implement graceful shutdown. Once
+/// cancelled, coroutines stop emitting new I/O requests and fail with
+/// a `Cancelled` error instead. The partial state of the coroutines is
+/// preserved.
+///
+/// Cancellation cannot interrupt an I/O already being processed by a
+/// runtime: it only takes effect on the next resume.
+#[derive(Clone, Debug, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    /// Creates a new, non-cancelled handle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all the coroutines sharing this handle.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the handle has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
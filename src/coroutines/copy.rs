@@ -0,0 +1,181 @@
+//! I/O-free coroutine to copy bytes from a stream to another.
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+    write::{WriteStream, WriteStreamError, WriteStreamResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum CopyStreamError {
+    /// The destination stream unexpectedly reached the End Of File.
+    ///
+    /// Contains the amount of bytes copied so far.
+    #[error("Unexpected EOF on destination stream after copying {0} bytes")]
+    UnexpectedEof(u64),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+
+    /// Error from the [`WriteStream`] coroutine.
+    #[error(transparent)]
+    Write(#[from] WriteStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum CopyStreamResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the total amount of bytes copied.
+    Ok(u64),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(CopyStreamError),
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading a chunk from the source stream.
+    Read,
+
+    /// Writing the last read chunk to the destination stream.
+    Write(WriteStream),
+}
+
+/// I/O-free coroutine to copy bytes from a stream to another.
+///
+/// The coroutine alternates between reading a chunk from the source
+/// stream and writing it to the destination stream, until the source
+/// stream reaches the End Of File. Only one chunk is held in memory
+/// at a time.
+///
+/// Runtimes should process [`StreamIo::Read`] requests with the
+/// source stream, and [`StreamIo::Write`] requests with the
+/// destination stream.
+#[derive(Debug)]
+pub struct CopyStream {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The current state.
+    state: State,
+
+    /// The amount of bytes copied so far.
+    copied: u64,
+}
+
+impl CopyStream {
+    /// Creates a new coroutine to copy bytes using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to copy bytes using a buffer with the
+    /// given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to copy bytes (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            state: State::Read,
+            copied: 0,
+        }
+    }
+
+    /// Returns the amount of bytes copied so far.
+    pub fn copied(&self) -> u64 {
+        self.copied
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> CopyStreamResult {
+        loop {
+            match &mut self.state {
+                State::Read => {
+                    let mut output = match self.read.resume(arg.take()) {
+                        ReadStreamResult::Ok(output) => output,
+                        ReadStreamResult::Io(io) => break CopyStreamResult::Io(io),
+                        ReadStreamResult::Err(err) => break CopyStreamResult::Err(err.into()),
+                        ReadStreamResult::Eof => {
+                            debug!("copied {} bytes", self.copied);
+                            break CopyStreamResult::Ok(self.copied);
+                        }
+                    };
+
+                    // the read buffer is written as is, then given
+                    // back to the read coroutine
+                    output.buffer.truncate(output.bytes_count);
+                    let write = WriteStream::new(output.buffer);
+                    self.state = State::Write(write);
+                }
+                State::Write(write) => {
+                    let output = match write.resume(arg.take()) {
+                        WriteStreamResult::Ok(output) => output,
+                        WriteStreamResult::Io(io) => break CopyStreamResult::Io(io),
+                        WriteStreamResult::Err(err) => break CopyStreamResult::Err(err.into()),
+                        WriteStreamResult::Eof | WriteStreamResult::Backpressure(_) => {
+                            self.copied += write.written() as u64;
+                            let err = CopyStreamError::UnexpectedEof(self.copied);
+                            break CopyStreamResult::Err(err);
+                        }
+                    };
+
+                    self.copied += output.bytes_count as u64;
+                    self.read.replace(output.buffer);
+                    self.state = State::Read;
+                }
+            }
+        }
+    }
+}
+
+impl Default for CopyStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::copy::CopyStreamResult,
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::CopyStream;
+
+    impl_io_result!(CopyStreamResult);
+
+    #[test]
+    fn copy_short_writes() {
+        let _ = env_logger::try_init();
+
+        // simulates partial writes of 2 bytes max
+        let mut cursor =
+            ChunkedCursor::with_pattern("hello world".as_bytes(), [1, 3, 2]).with_max_write(2);
+
+        let mut copy = CopyStream::with_capacity(4);
+
+        let copied = match resume_until_done(&mut cursor, |arg| copy.resume(arg)) {
+            CopyStreamResult::Ok(copied) => copied,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(copied, 11);
+        assert_eq!(cursor.written(), b"hello world");
+    }
+}
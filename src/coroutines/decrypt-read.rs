@@ -0,0 +1,146 @@
+//! I/O-free coroutine to read bytes and decrypt them with a stream
+//! cipher.
+
+use alloc::vec::Vec;
+
+use cipher::StreamCipher;
+use log::{debug, trace};
+
+use crate::io::StreamIo;
+
+use super::{
+    cancel::Cancel,
+    read::{ReadBudget, ReadStream, ReadStreamResult},
+};
+
+/// I/O-free coroutine to read bytes and decrypt them with a stream
+/// cipher.
+///
+/// The keystream is applied in place to the read bytes of each chunk,
+/// the cipher state being kept across chunks. The coroutine emits
+/// the same results as [`ReadStream`], with decrypted bytes.
+#[derive(Debug)]
+pub struct DecryptReadStream<C> {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The stream cipher.
+    cipher: C,
+}
+
+impl<C: StreamCipher> DecryptReadStream<C> {
+    /// Creates a new coroutine to read and decrypt bytes with the
+    /// given cipher, using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(cipher: C) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, cipher)
+    }
+
+    /// Creates a new coroutine to read and decrypt bytes with the
+    /// given cipher, using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, cipher: C) -> Self {
+        trace!("init coroutine to read and decrypt bytes (capacity: {capacity})");
+        let read = ReadStream::with_capacity(capacity);
+        Self { read, cipher }
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.read = self.read.with_cancel(cancel);
+        self
+    }
+
+    /// Limits the amount of bytes read by the coroutine with the
+    /// given shared budget.
+    pub fn with_budget(mut self, budget: ReadBudget) -> Self {
+        self.read = self.read.with_budget(budget);
+        self
+    }
+
+    /// Returns a reference to the stream cipher.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Replaces the inner buffer with the given one.
+    pub fn replace(&mut self, buffer: Vec<u8>) {
+        self.read.replace(buffer);
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamResult {
+        let mut output = match self.read.resume(arg) {
+            ReadStreamResult::Ok(output) => output,
+            result => return result,
+        };
+
+        // only the read bytes are decrypted, so that the keystream
+        // position matches the stream position
+        self.cipher
+            .apply_keystream(&mut output.buffer[..output.bytes_count]);
+        debug!("decrypted {} bytes", output.bytes_count);
+
+        ReadStreamResult::Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use chacha20::{
+        cipher::{KeyIvInit, StreamCipher},
+        ChaCha20,
+    };
+
+    use crate::{
+        coroutines::read::ReadStreamResult,
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::ChunkedCursor,
+    };
+
+    use super::DecryptReadStream;
+
+    #[test]
+    fn decrypt_read() {
+        let _ = env_logger::try_init();
+
+        let key = [0x42; 32];
+        let nonce = [0x24; 12];
+
+        let mut encrypted = b"attack at dawn, retreat at dusk".to_vec();
+        ChaCha20::new(&key.into(), &nonce.into()).apply_keystream(&mut encrypted);
+
+        let mut reader = ChunkedCursor::with_pattern(encrypted.as_slice(), [1, 3, 2]);
+
+        let cipher = ChaCha20::new(&key.into(), &nonce.into());
+        let mut read = DecryptReadStream::with_capacity(5, cipher);
+        let mut arg = None;
+        let mut decrypted: Vec<u8> = Vec::new();
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => {
+                    decrypted.extend(output.bytes());
+                    read.replace(output.buffer);
+                }
+                ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    // reads less than the buffer capacity
+                    let len = init_read_buffer(&mut buffer).len() - 1;
+                    let bytes_count = reader.read(&mut buffer[..len]).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                ReadStreamResult::Eof => break,
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(decrypted, b"attack at dawn, retreat at dusk");
+    }
+}
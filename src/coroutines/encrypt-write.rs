@@ -0,0 +1,168 @@
+//! I/O-free coroutine to encrypt bytes with a stream cipher and write
+//! them into a stream.
+
+use alloc::vec::Vec;
+
+use cipher::StreamCipher;
+use log::{debug, trace};
+
+use crate::io::StreamIo;
+
+use super::{
+    cancel::Cancel,
+    write::{WriteStream, WriteStreamResult},
+};
+
+/// I/O-free coroutine to encrypt bytes with a stream cipher and write
+/// them into a stream.
+///
+/// Bytes are encrypted as soon as they are given to the coroutine, so
+/// that the cipher state advances exactly once per byte, whatever the
+/// amount of partial writes performed by the runtime. The coroutine
+/// emits the same results as [`WriteStream`], the output buffer
+/// containing the encrypted bytes.
+#[derive(Debug)]
+pub struct EncryptWriteStream<C> {
+    /// The inner write coroutine.
+    write: WriteStream,
+
+    /// The stream cipher.
+    cipher: C,
+}
+
+impl<C: StreamCipher> EncryptWriteStream<C> {
+    /// Creates a new coroutine to encrypt and write the given bytes
+    /// with the given cipher.
+    pub fn new(mut cipher: C, mut bytes: Vec<u8>) -> Self {
+        trace!("init coroutine to encrypt and write {} bytes", bytes.len());
+        cipher.apply_keystream(&mut bytes);
+        let write = WriteStream::new(bytes);
+        Self { write, cipher }
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.write = self.write.with_cancel(cancel);
+        self
+    }
+
+    /// Returns a reference to the stream cipher.
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Returns the amount of encrypted bytes written so far.
+    pub fn written(&self) -> usize {
+        self.write.written()
+    }
+
+    /// Encrypts the given bytes, then replaces the inner bytes with
+    /// them.
+    ///
+    /// The cipher state is kept, so that the new bytes follow the
+    /// previous ones in the keystream.
+    pub fn replace(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        let bytes = self.encrypt(bytes);
+        self.write.replace(bytes);
+    }
+
+    /// Encrypts the given bytes, then adds them to the inner buffer.
+    ///
+    /// See [`WriteStream::extend`].
+    pub fn extend(&mut self, more_bytes: impl IntoIterator<Item = u8>) {
+        let bytes = self.encrypt(more_bytes);
+        self.write.extend(bytes);
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamResult {
+        self.write.resume(arg)
+    }
+
+    /// Applies the keystream to the given bytes.
+    fn encrypt(&mut self, bytes: impl IntoIterator<Item = u8>) -> Vec<u8> {
+        let mut bytes: Vec<u8> = bytes.into_iter().collect();
+        self.cipher.apply_keystream(&mut bytes);
+        debug!("encrypted {} bytes", bytes.len());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read as _, Write as _};
+
+    use chacha20::{cipher::KeyIvInit, ChaCha20};
+
+    use crate::{
+        coroutines::{
+            decrypt_read::DecryptReadStream, read::ReadStreamResult, write::WriteStreamResult,
+        },
+        io::{init_read_buffer, StreamIo, StreamOutput},
+    };
+
+    use super::EncryptWriteStream;
+
+    const KEY: [u8; 32] = [0x42; 32];
+    const NONCE: [u8; 12] = [0x24; 12];
+
+    #[test]
+    fn encrypt_write_roundtrip() {
+        let _ = env_logger::try_init();
+
+        let cipher = ChaCha20::new(&KEY.into(), &NONCE.into());
+        let mut write = EncryptWriteStream::new(cipher, b"attack at dawn".to_vec());
+        let mut writer = Vec::new();
+        let mut arg = None;
+
+        for more in [None, Some(", retreat at dusk")] {
+            if let Some(more) = more {
+                write.extend(more.bytes());
+            }
+
+            loop {
+                match write.resume(arg.take()) {
+                    WriteStreamResult::Ok(_) => break,
+                    WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                        // simulates partial writes of 3 bytes max
+                        let bytes_count = writer.write(&buffer[..buffer.len().min(3)]).unwrap();
+                        let output = StreamOutput {
+                            buffer,
+                            bytes_count,
+                        };
+                        arg = Some(StreamIo::Write(Ok(output)))
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            }
+        }
+
+        assert_ne!(writer, b"attack at dawn, retreat at dusk");
+
+        let mut reader = writer.as_slice();
+        let cipher = ChaCha20::new(&KEY.into(), &NONCE.into());
+        let mut read = DecryptReadStream::with_capacity(5, cipher);
+        let mut decrypted: Vec<u8> = Vec::new();
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => {
+                    decrypted.extend(output.bytes());
+                    read.replace(output.buffer);
+                }
+                ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                ReadStreamResult::Eof => break,
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(decrypted, b"attack at dawn, retreat at dusk");
+    }
+}
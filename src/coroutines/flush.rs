@@ -0,0 +1,186 @@
+//! I/O-free coroutine to flush a stream.
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{Coroutine, CoroutineResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum FlushStreamError {
+    /// The coroutine received an invalid argument.
+    ///
+    /// Occurs when the coroutine receives an I/O response from
+    /// another coroutine, which should not happen if the runtime maps
+    /// correctly the arguments, or when it receives a response while
+    /// not awaiting one.
+    #[error("Invalid argument: expected {0}, got {1:?}")]
+    InvalidArgument(&'static str, StreamIo),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+pub type FlushStreamResult = CoroutineResult<(), FlushStreamError>;
+
+/// I/O-free coroutine to flush a stream.
+///
+/// Streams buffering their writes, like TLS connections or
+/// [`std::io::BufWriter`], only send bytes once flushed. Running this
+/// coroutine after a [`WriteStream`] makes sure that the written
+/// bytes actually leave the process.
+///
+/// [`WriteStream`]: super::write::WriteStream
+#[derive(Debug, Default)]
+pub struct FlushStream {
+    /// Whether a flush request has been emitted.
+    awaiting: bool,
+}
+
+impl FlushStream {
+    /// Creates a new coroutine to flush a stream.
+    pub fn new() -> Self {
+        trace!("init coroutine to flush stream");
+        Self { awaiting: false }
+    }
+
+    /// Makes the flush progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> FlushStreamResult {
+        let Some(arg) = arg else {
+            trace!("wants I/O to flush stream");
+            self.awaiting = true;
+            return FlushStreamResult::Io(StreamIo::Flush(false));
+        };
+
+        if !self.awaiting {
+            return FlushStreamResult::Err(FlushStreamError::InvalidArgument("no response", arg));
+        }
+
+        match arg {
+            StreamIo::Flush(true) => {
+                self.awaiting = false;
+                debug!("flushed stream");
+                FlushStreamResult::Ok(())
+            }
+            StreamIo::Flush(false) => FlushStreamResult::Io(StreamIo::Flush(false)),
+            arg => FlushStreamResult::Err(FlushStreamError::InvalidArgument("flush output", arg)),
+        }
+    }
+}
+
+impl Coroutine for FlushStream {
+    type Output = ();
+    type Error = FlushStreamError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> FlushStreamResult {
+        FlushStream::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use crate::{
+        coroutines::{
+            flush::{FlushStreamError, FlushStreamResult},
+            write::{WriteStream, WriteStreamResult},
+        },
+        io::{StreamIo, StreamOutput},
+    };
+
+    use super::FlushStream;
+
+    /// A writer buffering bytes until flushed.
+    #[derive(Default)]
+    struct BufferedWriter {
+        buffered: Vec<u8>,
+        flushed: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl Write for BufferedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buffered.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed.append(&mut self.buffered);
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_then_flush() {
+        let _ = env_logger::try_init();
+
+        let mut writer = BufferedWriter::default();
+
+        let mut write = WriteStream::new(b"hello".to_vec());
+        let mut arg = None;
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(_) => break,
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    let bytes_count = writer.write(&buffer).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(writer.buffered, b"hello");
+        assert_eq!(writer.flushes, 0);
+
+        let mut flush = FlushStream::new();
+
+        loop {
+            match flush.resume(arg.take()) {
+                FlushStreamResult::Ok(()) => break,
+                FlushStreamResult::Io(StreamIo::Flush(false)) => {
+                    writer.flush().unwrap();
+                    arg = Some(StreamIo::Flush(true))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert!(writer.buffered.is_empty());
+        assert_eq!(writer.flushed, b"hello");
+        assert_eq!(writer.flushes, 1);
+    }
+
+    #[test]
+    fn flush_extra_response() {
+        let _ = env_logger::try_init();
+
+        let mut flush = FlushStream::new();
+
+        match flush.resume(Some(StreamIo::Flush(true))) {
+            FlushStreamResult::Err(FlushStreamError::InvalidArgument("no response", _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match flush.resume(None) {
+            FlushStreamResult::Io(StreamIo::Flush(false)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match flush.resume(Some(StreamIo::Flush(true))) {
+            FlushStreamResult::Ok(()) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match flush.resume(Some(StreamIo::Flush(true))) {
+            FlushStreamResult::Err(FlushStreamError::InvalidArgument("no response", _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
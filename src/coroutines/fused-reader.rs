@@ -0,0 +1,182 @@
+//! Adapter to chain read coroutines over the same stream without
+//! losing bytes.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+
+use crate::io::{init_read_buffer, StreamIo, StreamOutput};
+
+/// Adapter to chain read coroutines over the same stream without
+/// losing bytes.
+///
+/// Some read coroutines read past what they need (see their
+/// `leftover` getters). When successive coroutines run over the same
+/// stream, those extra bytes belong to the next coroutine. The fused
+/// reader owns a single shared buffer: leftovers are pushed back into
+/// it with [`Self::push_back`], and read requests emitted by the next
+/// coroutines are fulfilled from it with [`Self::handle`] before
+/// reaching the stream.
+///
+/// Bytes are only served once, so no byte read from the stream is
+/// ever lost or read twice.
+#[derive(Clone, Debug, Default)]
+pub struct FusedReader {
+    /// The buffer containing pushed back bytes not yet served.
+    buffer: Vec<u8>,
+}
+
+impl FusedReader {
+    /// Creates a new fused reader with an empty buffer.
+    pub fn new() -> Self {
+        trace!("init fused reader");
+        Self::default()
+    }
+
+    /// Pushes the given bytes back in front of the buffer.
+    ///
+    /// Pushed back bytes are served before any byte previously in the
+    /// buffer, since they were read from the stream after them.
+    pub fn push_back(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        let mut buffer: Vec<u8> = bytes.into_iter().collect();
+
+        if buffer.is_empty() {
+            return;
+        }
+
+        debug!("push back {} bytes", buffer.len());
+        buffer.append(&mut self.buffer);
+        self.buffer = buffer;
+    }
+
+    /// Returns `true` if there is no byte left to serve.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns the bytes left to serve.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes left to serve.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Tries to fulfil the given I/O request from the buffer.
+    ///
+    /// Returns `Ok` with the I/O response to resume the coroutine
+    /// with if the request could be fulfilled, otherwise `Err` with
+    /// the untouched request, which should be processed by a runtime.
+    pub fn handle(&mut self, io: StreamIo) -> Result<StreamIo, StreamIo> {
+        let StreamIo::Read(Err(mut buffer)) = io else {
+            return Err(io);
+        };
+
+        if self.buffer.is_empty() || init_read_buffer(&mut buffer).is_empty() {
+            return Err(StreamIo::Read(Err(buffer)));
+        }
+
+        let bytes_count = buffer.len().min(self.buffer.len());
+        buffer[..bytes_count].copy_from_slice(&self.buffer[..bytes_count]);
+        self.buffer.drain(..bytes_count);
+        debug!("served {bytes_count} pushed back bytes");
+
+        let output = StreamOutput {
+            buffer,
+            bytes_count,
+        };
+
+        Ok(StreamIo::Read(Ok(output)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io::Read as _};
+
+    use crate::{
+        coroutines::{
+            read_exact::{ReadStreamExact, ReadStreamExactResult},
+            read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+            read_varint::{ReadStreamVarint, ReadStreamVarintResult},
+        },
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::ChunkedCursor,
+    };
+
+    use super::FusedReader;
+
+    #[test]
+    fn fused_reads() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern(&b"\x03abcdefghi"[..], []);
+        let mut fused = FusedReader::new();
+        let stream_reads = Cell::new(0);
+
+        let mut handle = |fused: &mut FusedReader, io: StreamIo| match fused.handle(io) {
+            Ok(io) => io,
+            Err(StreamIo::Read(Err(mut buffer))) => {
+                stream_reads.set(stream_reads.get() + 1);
+                let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                let output = StreamOutput {
+                    buffer,
+                    bytes_count,
+                };
+                StreamIo::Read(Ok(output))
+            }
+            Err(io) => unreachable!("Unexpected I/O: {io:?}"),
+        };
+
+        // the varint over-reads into the payload
+        let mut read = ReadStreamVarint::with_capacity(8);
+        let mut arg = None;
+
+        let len = loop {
+            match read.resume(arg.take()) {
+                ReadStreamVarintResult::Ok(len) => break len as usize,
+                ReadStreamVarintResult::Io(io) => arg = Some(handle(&mut fused, io)),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(len, 3);
+        assert_eq!(read.leftover(), b"abcdefg");
+        fused.push_back(read.take_leftover());
+
+        // the payload is entirely served from pushed back bytes
+        let mut read = ReadStreamExact::new(len);
+        let mut arg = None;
+
+        let payload = loop {
+            match read.resume(arg.take()) {
+                ReadStreamExactResult::Ok(payload) => break payload,
+                ReadStreamExactResult::Io(io) => arg = Some(handle(&mut fused, io)),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(payload, b"abc");
+        assert_eq!(fused.leftover(), b"defg");
+
+        // the rest is served from pushed back bytes then from the
+        // stream
+        let mut read = ReadStreamToEnd::with_capacity(8);
+        let mut arg = None;
+
+        let rest = loop {
+            match read.resume(arg.take()) {
+                ReadStreamToEndResult::Ok(rest) => break rest,
+                ReadStreamToEndResult::Io(io) => arg = Some(handle(&mut fused, io)),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(rest, b"defghi");
+        assert!(fused.is_empty());
+        assert_eq!(stream_reads.get(), 3);
+    }
+}
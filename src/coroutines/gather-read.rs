@@ -0,0 +1,241 @@
+//! I/O-free coroutine to read from multiple streams in sequence.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    read_to_end::{ReadStreamToEnd, ReadStreamToEndError, ReadStreamToEndResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum GatherReadStreamError {
+    /// The coroutine received an I/O response while not awaiting one.
+    #[error("Invalid argument: expected {0}, got {1:?}")]
+    InvalidArgument(&'static str, StreamIo),
+
+    /// Error from the [`ReadStreamToEnd`] coroutine reading the source
+    /// at the given index.
+    #[error("Cannot read source {0} to end: {1}")]
+    ReadToEnd(usize, ReadStreamToEndError),
+
+    /// Error from the [`ReadStreamExact`] coroutine reading the source
+    /// at the given index.
+    ///
+    /// A source reaching the End Of File before its length leads to
+    /// [`ReadStreamExactError::UnexpectedEof`].
+    #[error("Cannot read source {0}: {1}")]
+    ReadExact(usize, ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum GatherReadStreamResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the concatenated bytes of all sources, and the amount
+    /// of bytes read from each source.
+    Ok((Vec<u8>, Vec<usize>)),
+
+    /// A stream I/O needs to be performed on the source at the given
+    /// index to make the coroutine progress.
+    Io(usize, StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(GatherReadStreamError),
+}
+
+/// The inner read coroutine of the current source.
+#[derive(Debug)]
+enum Source {
+    /// Reading the source until the End Of File.
+    ToEnd(ReadStreamToEnd),
+
+    /// Reading an exact amount of bytes from the source.
+    Exact(ReadStreamExact),
+}
+
+/// I/O-free coroutine to read from multiple streams in sequence, and
+/// to concatenate their bytes in order.
+///
+/// Each source is read either until the End Of File or up to a given
+/// length, which suits reassembling striped downloads. I/O requests
+/// are emitted alongside the index of the source they target, so
+/// that runtimes route them to the right stream.
+#[derive(Debug)]
+pub struct GatherReadStream {
+    /// The read buffer capacity.
+    capacity: usize,
+
+    /// The length of each source, or `None` to read it to the end.
+    lengths: Vec<Option<usize>>,
+
+    /// The inner read coroutine of the current source.
+    source: Option<Source>,
+
+    /// The concatenated bytes read so far.
+    bytes: Vec<u8>,
+
+    /// The amount of bytes read from each completed source.
+    counts: Vec<usize>,
+}
+
+impl GatherReadStream {
+    /// Creates a new coroutine to read the given amount of sources
+    /// until the End Of File, using buffers with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    pub fn new(sources: usize) -> Self {
+        let lengths = (0..sources).map(|_| None);
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, lengths)
+    }
+
+    /// Creates a new coroutine to read exactly the given length from
+    /// each source, using buffers with [`ReadStream::DEFAULT_CAPACITY`]
+    /// capacity.
+    pub fn with_lengths(lengths: impl IntoIterator<Item = usize>) -> Self {
+        let lengths = lengths.into_iter().map(Some);
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, lengths)
+    }
+
+    /// Creates a new coroutine to read the given sources, using
+    /// buffers with the given capacity.
+    ///
+    /// Each source is read exactly up to its length, or until the End
+    /// Of File if `None`.
+    pub fn with_capacity(
+        capacity: usize,
+        lengths: impl IntoIterator<Item = Option<usize>>,
+    ) -> Self {
+        let lengths: Vec<_> = lengths.into_iter().collect();
+        let n = lengths.len();
+        trace!("init coroutine to gather {n} sources (capacity: {capacity})");
+
+        Self {
+            capacity,
+            counts: Vec::with_capacity(n),
+            lengths,
+            source: None,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> GatherReadStreamResult {
+        loop {
+            let index = self.counts.len();
+
+            let Some(length) = self.lengths.get(index) else {
+                if let Some(arg) = arg {
+                    let err = GatherReadStreamError::InvalidArgument("no response", arg);
+                    break GatherReadStreamResult::Err(err);
+                }
+
+                let bytes = mem::take(&mut self.bytes);
+                let counts = mem::take(&mut self.counts);
+                break GatherReadStreamResult::Ok((bytes, counts));
+            };
+
+            let source = self.source.get_or_insert_with(|| match length {
+                Some(len) => Source::Exact(ReadStreamExact::with_capacity(self.capacity, *len)),
+                None => Source::ToEnd(ReadStreamToEnd::with_capacity(self.capacity)),
+            });
+
+            let bytes = match source {
+                Source::ToEnd(read) => match read.resume(arg.take()) {
+                    ReadStreamToEndResult::Ok(bytes) => bytes,
+                    ReadStreamToEndResult::Io(io) => break GatherReadStreamResult::Io(index, io),
+                    ReadStreamToEndResult::Err(err) => {
+                        let err = GatherReadStreamError::ReadToEnd(index, err);
+                        break GatherReadStreamResult::Err(err);
+                    }
+                },
+                Source::Exact(read) => match read.resume(arg.take()) {
+                    ReadStreamExactResult::Ok(bytes) => bytes,
+                    ReadStreamExactResult::Io(io) => break GatherReadStreamResult::Io(index, io),
+                    ReadStreamExactResult::Err(err) => {
+                        let err = GatherReadStreamError::ReadExact(index, err);
+                        break GatherReadStreamResult::Err(err);
+                    }
+                },
+            };
+
+            debug!("gathered {} bytes from source {index}", bytes.len());
+            self.counts.push(bytes.len());
+            self.bytes.extend(bytes);
+            self.source = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::{
+        coroutines::gather_read::{GatherReadStreamError, GatherReadStreamResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+    };
+
+    use super::GatherReadStream;
+
+    fn gather(
+        mut gather: GatherReadStream,
+        sources: &mut [&[u8]],
+    ) -> Result<(Vec<u8>, Vec<usize>), GatherReadStreamError> {
+        let mut arg = None;
+
+        loop {
+            match gather.resume(arg.take()) {
+                GatherReadStreamResult::Ok(output) => break Ok(output),
+                GatherReadStreamResult::Io(i, StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = sources[i].read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                GatherReadStreamResult::Err(err) => break Err(err),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn gather_read() {
+        let _ = env_logger::try_init();
+
+        let mut sources: [&[u8]; 3] = [b"stripe 1, ", b"stripe 2, ", b"stripe 3"];
+        let gather = GatherReadStream::with_capacity(4, [None, Some(6), None]);
+        let (bytes, counts) = self::gather(gather, &mut sources).unwrap();
+
+        assert_eq!(bytes, b"stripe 1, stripestripe 3");
+        assert_eq!(counts, [10, 6, 8]);
+
+        // bytes past the length are left in the source
+        assert_eq!(sources[1], b" 2, ");
+    }
+
+    #[test]
+    fn gather_read_unexpected_eof() {
+        let _ = env_logger::try_init();
+
+        let mut sources: [&[u8]; 3] = [b"abc", b"de", b"fgh"];
+        let gather = GatherReadStream::with_lengths([3, 3, 3]);
+
+        match self::gather(gather, &mut sources) {
+            Err(GatherReadStreamError::ReadExact(1, _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // the next sources are not read
+        assert_eq!(sources[2], b"fgh");
+    }
+}
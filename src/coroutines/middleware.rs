@@ -0,0 +1,230 @@
+//! Active interception of the I/O exchanged between coroutines and
+//! runtimes.
+
+use log::trace;
+
+use crate::io::StreamIo;
+
+use super::{Coroutine, CoroutineResult};
+
+/// Middleware rewriting the I/O exchanged between a coroutine and a
+/// runtime.
+///
+/// Unlike [`OnIo`], which only inspects requests, middlewares own the
+/// I/O and give back the one to forward, for example to inject a
+/// header in written bytes or to transform read bytes. Coroutines are
+/// not aware of them, so rewrites must keep the I/O consistent: a
+/// response should still match the request the coroutine emitted.
+///
+/// Closures taking and returning a [`StreamIo`] are middlewares
+/// rewriting requests only.
+///
+/// [`OnIo`]: super::on_io::OnIo
+pub trait Middleware {
+    /// Processes an I/O request emitted by the coroutine, before it
+    /// reaches the runtime.
+    fn process(&mut self, io: StreamIo) -> StreamIo;
+
+    /// Processes an I/O response of the runtime, before it reaches the
+    /// coroutine.
+    ///
+    /// Forwards the response as is by default.
+    fn process_response(&mut self, io: StreamIo) -> StreamIo {
+        io
+    }
+
+    /// Chains the given middleware after this one, see
+    /// [`MiddlewareChain`].
+    fn chain<M: Middleware>(self, next: M) -> MiddlewareChain<Self, M>
+    where
+        Self: Sized,
+    {
+        MiddlewareChain { first: self, next }
+    }
+}
+
+impl<F: FnMut(StreamIo) -> StreamIo> Middleware for F {
+    fn process(&mut self, io: StreamIo) -> StreamIo {
+        self(io)
+    }
+}
+
+/// Chain of two middlewares.
+///
+/// Requests go through the first middleware then the next one,
+/// whereas responses go the other way around, so that each middleware
+/// wraps the ones chained after it.
+///
+/// See [`Middleware::chain`].
+#[derive(Debug)]
+pub struct MiddlewareChain<A, B> {
+    /// The middleware closest to the coroutine.
+    first: A,
+
+    /// The middleware closest to the runtime.
+    next: B,
+}
+
+impl<A: Middleware, B: Middleware> Middleware for MiddlewareChain<A, B> {
+    fn process(&mut self, io: StreamIo) -> StreamIo {
+        let io = self.first.process(io);
+        self.next.process(io)
+    }
+
+    fn process_response(&mut self, io: StreamIo) -> StreamIo {
+        let io = self.next.process_response(io);
+        self.first.process_response(io)
+    }
+}
+
+/// I/O-free coroutine wrapper applying a middleware between the inner
+/// coroutine and the runtime.
+///
+/// Responses are processed before resuming the inner coroutine, and
+/// requests right after it emits them. Results are propagated
+/// untouched.
+///
+/// See [`Coroutine::with_middleware`].
+#[derive(Debug)]
+pub struct WithMiddleware<C, M> {
+    /// The inner coroutine.
+    coroutine: C,
+
+    /// The middleware applied to the I/O.
+    middleware: M,
+}
+
+impl<C: Coroutine, M: Middleware> WithMiddleware<C, M> {
+    /// Wraps the given coroutine with the given middleware.
+    pub fn new(coroutine: C, middleware: M) -> Self {
+        trace!("init coroutine with middleware");
+        Self {
+            coroutine,
+            middleware,
+        }
+    }
+
+    /// Returns the inner coroutine and middleware.
+    pub fn into_inner(self) -> (C, M) {
+        (self.coroutine, self.middleware)
+    }
+}
+
+impl<C: Coroutine, M: Middleware> Coroutine for WithMiddleware<C, M> {
+    type Output = C::Output;
+    type Error = C::Error;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error> {
+        let arg = arg.map(|io| self.middleware.process_response(io));
+
+        match self.coroutine.resume(arg) {
+            CoroutineResult::Io(io) => CoroutineResult::Io(self.middleware.process(io)),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read as _, Write as _};
+
+    use crate::{
+        coroutines::{read_exact::ReadStreamExact, write::WriteStream, Coroutine, CoroutineResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::ChunkedCursor,
+    };
+
+    use super::Middleware;
+
+    /// Uppercases the bytes of read responses.
+    struct Uppercase;
+
+    impl Middleware for Uppercase {
+        fn process(&mut self, io: StreamIo) -> StreamIo {
+            io
+        }
+
+        fn process_response(&mut self, mut io: StreamIo) -> StreamIo {
+            if let StreamIo::Read(Ok(output)) = &mut io {
+                output.buffer[..output.bytes_count].make_ascii_uppercase();
+            }
+
+            io
+        }
+    }
+
+    #[test]
+    fn middleware_write() {
+        let _ = env_logger::try_init();
+
+        let mut sink = Vec::new();
+
+        let mut write = WriteStream::new(b"ping\r\n".to_vec()).with_middleware(|mut io| {
+            if let StreamIo::Write(Err(bytes)) = &mut io {
+                bytes.make_ascii_uppercase();
+            }
+
+            io
+        });
+
+        let mut arg = None;
+
+        loop {
+            match write.resume(arg.take()) {
+                CoroutineResult::Ok(_) => break,
+                CoroutineResult::Io(StreamIo::Write(Err(buffer))) => {
+                    let bytes_count = sink.write(&buffer).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(sink, b"PING\r\n");
+    }
+
+    #[test]
+    fn middleware_chain() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("pong\r\n".as_bytes(), []);
+
+        // limits reads to 2 bytes, then uppercases read bytes
+        let limit = |mut io: StreamIo| {
+            if let Some(buffer) = io.as_read_buffer() {
+                buffer.truncate(2);
+            }
+
+            io
+        };
+
+        let mut read = ReadStreamExact::new(6).with_middleware(Uppercase.chain(limit));
+        let mut arg = None;
+        let mut reads = 0;
+
+        let output = loop {
+            match read.resume(arg.take()) {
+                CoroutineResult::Ok(output) => break output,
+                CoroutineResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    assert_eq!(buffer.len(), 2);
+                    reads += 1;
+
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(output, b"PONG\r\n");
+        assert_eq!(reads, 3);
+    }
+}
@@ -4,12 +4,297 @@
 //! Coroutines emit [I/O] requests that need to be processed by
 //! [runtimes] in order to continue their progression.
 //!
+//! Coroutines never clone the bytes they accumulate: on error paths,
+//! partial buffers are moved out of the coroutine into the error.
+//! Shared handles like [`Cancel`] and [`ReadBudget`] are cheap to
+//! clone, whereas I/O, results and errors carrying bytes (for example
+//! [`ReadStreamExactError::UnexpectedEof`]) are expensive to clone,
+//! since their buffers get copied.
+//!
+//! Resuming a coroutine with an I/O response while it does not await
+//! one, for example before its first request or after its
+//! termination, fails with an invalid argument error. The response is
+//! never silently consumed.
+//!
+//! Coroutines sharing the same shape implement the [`Coroutine`]
+//! trait, which allows runtimes to drive them uniformly:
+//!
+//! ```
+//! use std::{error::Error, io::Read};
+//!
+//! use io_stream::{
+//!     coroutines::{
+//!         read_exact::ReadStreamExact, read_to_end::ReadStreamToEnd, Coroutine,
+//!         CoroutineResult,
+//!     },
+//!     io::{init_read_buffer, StreamIo, StreamOutput},
+//! };
+//!
+//! fn drive<C>(coroutine: &mut C, reader: &mut impl Read) -> Result<C::Output, Box<dyn Error>>
+//! where
+//!     C: Coroutine,
+//!     C::Error: Error + 'static,
+//! {
+//!     let mut arg = None;
+//!
+//!     loop {
+//!         match coroutine.resume(arg.take()) {
+//!             CoroutineResult::Ok(output) => break Ok(output),
+//!             CoroutineResult::Err(err) => break Err(err.into()),
+//!             CoroutineResult::Io(StreamIo::Read(Err(mut buffer))) => {
+//!                 let bytes_count = reader.read(init_read_buffer(&mut buffer))?;
+//!                 let output = StreamOutput {
+//!                     buffer,
+//!                     bytes_count,
+//!                 };
+//!                 arg = Some(StreamIo::Read(Ok(output)));
+//!             }
+//!             CoroutineResult::Io(io) => break Err(format!("unexpected I/O: {io:?}").into()),
+//!         }
+//!     }
+//! }
+//!
+//! let mut reader = "hello, world".as_bytes();
+//!
+//! let hello = drive(&mut ReadStreamExact::new(5), &mut reader).unwrap();
+//! assert_eq!(hello, b"hello");
+//!
+//! let world = drive(&mut ReadStreamToEnd::new(), &mut reader).unwrap();
+//! assert_eq!(world, b", world");
+//! ```
+//!
+//! [`Cancel`]: cancel::Cancel
+//! [`ReadBudget`]: read::ReadBudget
+//! [`ReadStreamExactError::UnexpectedEof`]: read_exact::ReadStreamExactError::UnexpectedEof
 //! [I/O]: crate::io::StreamIo
 //! [runtimes]: crate::runtimes
 
+use alloc::vec::Vec;
+
+use crate::io::{init_read_buffer, StreamIo, StreamOutput, StreamVectoredOutput};
+
+pub mod cancel;
+pub mod copy;
+#[cfg(feature = "cipher")]
+#[path = "decrypt-read.rs"]
+pub mod decrypt_read;
+#[cfg(feature = "cipher")]
+#[path = "encrypt-write.rs"]
+pub mod encrypt_write;
+pub mod flush;
+#[path = "fused-reader.rs"]
+pub mod fused_reader;
+#[path = "gather-read.rs"]
+pub mod gather_read;
+pub mod middleware;
+pub mod observer;
+#[path = "on-io.rs"]
+pub mod on_io;
 pub mod read;
+#[path = "read-amqp-frame.rs"]
+pub mod read_amqp_frame;
+#[path = "read-balanced.rs"]
+pub mod read_balanced;
+#[cfg(feature = "base64")]
+#[path = "read-base64-line.rs"]
+pub mod read_base64_line;
+#[cfg(feature = "bytes")]
+#[path = "read-bytes.rs"]
+pub mod read_bytes;
+#[path = "read-chunks.rs"]
+pub mod read_chunks;
+#[path = "read-decimal.rs"]
+pub mod read_decimal;
+#[path = "read-dns-message.rs"]
+pub mod read_dns_message;
+#[path = "read-dynamic.rs"]
+pub mod read_dynamic;
 #[path = "read-exact.rs"]
 pub mod read_exact;
+#[path = "read-float.rs"]
+pub mod read_float;
+#[path = "read-framed.rs"]
+pub mod read_framed;
+#[path = "read-grpc-message.rs"]
+pub mod read_grpc_message;
+#[cfg(feature = "hmac")]
+#[path = "read-hmac-verified.rs"]
+pub mod read_hmac_verified;
+#[path = "read-http-response.rs"]
+pub mod read_http_response;
+#[path = "read-imap-literal.rs"]
+pub mod read_imap_literal;
+#[path = "read-line.rs"]
+pub mod read_line;
+#[path = "read-list.rs"]
+pub mod read_list;
+#[path = "read-min-chunk.rs"]
+pub mod read_min_chunk;
+#[path = "read-mqtt-packet.rs"]
+pub mod read_mqtt_packet;
+#[path = "read-padded-field.rs"]
+pub mod read_padded_field;
+#[path = "read-parsed-lines.rs"]
+pub mod read_parsed_lines;
+#[path = "read-peek.rs"]
+pub mod read_peek;
+#[cfg(feature = "base64")]
+#[path = "read-pem.rs"]
+pub mod read_pem;
+#[path = "read-pkt-line.rs"]
+pub mod read_pkt_line;
+#[path = "read-record-array.rs"]
+pub mod read_record_array;
+#[path = "read-smtp-data.rs"]
+pub mod read_smtp_data;
+#[path = "read-stomp-frame.rs"]
+pub mod read_stomp_frame;
+#[path = "read-tar-entry.rs"]
+pub mod read_tar_entry;
 #[path = "read-to-end.rs"]
 pub mod read_to_end;
+#[path = "read-to-string.rs"]
+pub mod read_to_string;
+#[path = "read-until.rs"]
+pub mod read_until;
+#[path = "read-until-predicate.rs"]
+pub mod read_until_predicate;
+#[path = "read-utf16.rs"]
+pub mod read_utf16;
+#[path = "read-varint.rs"]
+pub mod read_varint;
+pub mod shutdown;
+pub mod skip;
+pub mod then;
 pub mod write;
+#[cfg(feature = "base64")]
+#[path = "write-base64.rs"]
+pub mod write_base64;
+#[path = "write-deferred-length.rs"]
+pub mod write_deferred_length;
+#[path = "write-http-request.rs"]
+pub mod write_http_request;
+#[path = "write-resp.rs"]
+pub mod write_resp;
+#[path = "write-smtp-data.rs"]
+pub mod write_smtp_data;
+#[path = "write-vectored.rs"]
+pub mod write_vectored;
+
+/// Output emitted after a [`Coroutine`] progression.
+#[derive(Clone, Debug)]
+pub enum CoroutineResult<O, E> {
+    /// The coroutine has successfully terminated its progression.
+    Ok(O),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(E),
+}
+
+/// I/O-free, resumable stream state machine.
+///
+/// Runtimes resume the coroutine with [`None`] first, then with the
+/// output of every [`StreamIo`] it emits, until it terminates.
+pub trait Coroutine {
+    /// The type emitted when the coroutine terminates successfully.
+    type Output;
+
+    /// The type emitted when the coroutine fails.
+    type Error;
+
+    /// Makes the coroutine progress.
+    fn resume(&mut self, arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error>;
+
+    /// Drives the coroutine over the given in-memory bytes, without
+    /// runtime.
+    ///
+    /// Read requests are satisfied from the given bytes, their
+    /// exhaustion being reported as the End Of File. Other requests
+    /// are acknowledged as if fully processed, written bytes being
+    /// discarded. This turns coroutines into plain functions over
+    /// byte slices, for testing or offline parsing:
+    ///
+    /// ```
+    /// use io_stream::coroutines::{read_line::ReadStreamLine, Coroutine};
+    ///
+    /// let line = ReadStreamLine::new().feed_all(b"hello\r\nworld").unwrap();
+    /// assert_eq!(line, "hello");
+    /// ```
+    fn feed_all(&mut self, mut bytes: &[u8]) -> Result<Self::Output, Self::Error> {
+        let mut arg = None;
+
+        loop {
+            let io = match self.resume(arg.take()) {
+                CoroutineResult::Ok(output) => break Ok(output),
+                CoroutineResult::Err(err) => break Err(err),
+                CoroutineResult::Io(io) => io,
+            };
+
+            arg = Some(match io {
+                StreamIo::Read(Err(mut buffer)) => {
+                    let bytes_count = bytes.len().min(init_read_buffer(&mut buffer).len());
+                    buffer[..bytes_count].copy_from_slice(&bytes[..bytes_count]);
+                    bytes = &bytes[bytes_count..];
+                    StreamIo::Read(Ok(StreamOutput {
+                        buffer,
+                        bytes_count,
+                    }))
+                }
+                StreamIo::Write(Err(buffer)) => StreamIo::Write(Ok(StreamOutput {
+                    bytes_count: buffer.len(),
+                    buffer,
+                })),
+                StreamIo::WriteVectored(Err(buffers)) => {
+                    StreamIo::WriteVectored(Ok(StreamVectoredOutput {
+                        bytes_count: buffers.iter().map(Vec::len).sum(),
+                        buffers,
+                    }))
+                }
+                StreamIo::Flush(_) => StreamIo::Flush(true),
+                StreamIo::Shutdown(_) => StreamIo::Shutdown(true),
+                // responses are not expected, and are given back as is
+                io => io,
+            });
+        }
+    }
+
+    /// Invokes the given hook right before emitting each I/O
+    /// request, see [`OnIo`].
+    ///
+    /// [`OnIo`]: on_io::OnIo
+    fn on_io<F: FnMut(&StreamIo)>(self, hook: F) -> on_io::OnIo<Self, F>
+    where
+        Self: Sized,
+    {
+        on_io::OnIo::new(self, hook)
+    }
+
+    /// Applies the given middleware to the I/O requests and responses
+    /// of this coroutine, see [`WithMiddleware`].
+    ///
+    /// [`WithMiddleware`]: middleware::WithMiddleware
+    fn with_middleware<M: middleware::Middleware>(
+        self,
+        middleware: M,
+    ) -> middleware::WithMiddleware<Self, M>
+    where
+        Self: Sized,
+    {
+        middleware::WithMiddleware::new(self, middleware)
+    }
+
+    /// Runs the given coroutine once this one terminates, see
+    /// [`Then`].
+    ///
+    /// [`Then`]: then::Then
+    fn then<C: Coroutine>(self, next: C) -> then::Then<Self, C>
+    where
+        Self: Sized,
+    {
+        then::Then::new(self, next)
+    }
+}
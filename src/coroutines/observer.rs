@@ -0,0 +1,69 @@
+//! Observation of the I/O processed by coroutines.
+
+use core::fmt;
+
+/// Observer notified of the bytes read and written by coroutines.
+///
+/// Observers are shared behind an [`Arc`], for example to wire up
+/// metrics like byte counters at the coroutine layer, regardless of
+/// the runtime. Coroutines without observer do not pay anything.
+///
+/// Both methods do nothing by default, so observers only need to
+/// implement the ones they care about.
+///
+/// [`Arc`]: alloc::sync::Arc
+pub trait StreamObserver: fmt::Debug + Send + Sync {
+    /// Called after each successful read, with the amount of bytes
+    /// read.
+    fn on_read(&self, _n: usize) {}
+
+    /// Called after each successful write, with the amount of bytes
+    /// written.
+    fn on_write(&self, _n: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        coroutines::read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::StreamObserver;
+
+    #[derive(Debug, Default)]
+    struct Counter {
+        reads: AtomicUsize,
+        bytes: AtomicUsize,
+    }
+
+    impl StreamObserver for Counter {
+        fn on_read(&self, n: usize) {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.bytes.fetch_add(n, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn observer() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdefghij".as_bytes(), []);
+
+        let counter = Arc::new(Counter::default());
+        let mut read = ReadStreamToEnd::with_capacity(4).with_observer(counter.clone());
+        let bytes = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamToEndResult::Ok(bytes) => bytes,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(bytes, b"abcdefghij");
+        assert_eq!(counter.reads.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.bytes.load(Ordering::SeqCst), 10);
+    }
+}
@@ -0,0 +1,102 @@
+//! I/O-free coroutine wrapper invoking a hook on each I/O request.
+
+use log::trace;
+
+use crate::io::StreamIo;
+
+use super::{Coroutine, CoroutineResult};
+
+/// I/O-free coroutine wrapper invoking a hook right before emitting
+/// each I/O request of the inner coroutine.
+///
+/// The hook receives the exact request, which makes it a convenient
+/// breakpoint to inspect or log a coroutine step by step, for example
+/// from a debugger or a REPL. Results are propagated untouched.
+///
+/// See [`Coroutine::on_io`].
+#[derive(Debug)]
+pub struct OnIo<C, F> {
+    /// The inner coroutine.
+    coroutine: C,
+
+    /// The hook invoked before emitting I/O requests.
+    hook: F,
+}
+
+impl<C: Coroutine, F: FnMut(&StreamIo)> OnIo<C, F> {
+    /// Wraps the given coroutine with the given hook.
+    pub fn new(coroutine: C, hook: F) -> Self {
+        trace!("init coroutine hooked on I/O");
+        Self { coroutine, hook }
+    }
+
+    /// Returns the inner coroutine.
+    pub fn into_inner(self) -> C {
+        self.coroutine
+    }
+}
+
+impl<C: Coroutine, F: FnMut(&StreamIo)> Coroutine for OnIo<C, F> {
+    type Output = C::Output;
+    type Error = C::Error;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error> {
+        let result = self.coroutine.resume(arg);
+
+        if let CoroutineResult::Io(io) = &result {
+            (self.hook)(io);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io::Read as _};
+
+    use crate::{
+        coroutines::{read_exact::ReadStreamExact, Coroutine, CoroutineResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::ChunkedCursor,
+    };
+
+    #[test]
+    fn on_io() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), [1, 3, 2]);
+
+        let hooked = Cell::new(0);
+        let mut emitted = 0;
+
+        let mut read = ReadStreamExact::with_capacity(2, 5).on_io(|io| {
+            assert!(matches!(io, StreamIo::Read(Err(_))));
+            hooked.set(hooked.get() + 1);
+        });
+
+        let mut arg = None;
+
+        let output = loop {
+            match read.resume(arg.take()) {
+                CoroutineResult::Ok(output) => break output,
+                CoroutineResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    emitted += 1;
+                    assert_eq!(hooked.get(), emitted);
+
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(output, b"abcde");
+        assert_eq!(emitted, 3);
+        assert_eq!(hooked.get(), 3);
+    }
+}
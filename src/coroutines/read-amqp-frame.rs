@@ -0,0 +1,257 @@
+//! I/O-free coroutine to read an AMQP 0-9-1 frame.
+
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamAmqpFrameError {
+    /// The frame is not terminated by [`ReadStreamAmqpFrame::FRAME_END`].
+    #[error("Invalid AMQP frame end {0:#04x}")]
+    InvalidFrameEnd(u8),
+
+    /// The frame size exceeds the maximum frame size.
+    #[error("AMQP frame of {0} bytes exceeds the maximum of {1} bytes")]
+    TooLarge(usize, usize),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    ///
+    /// Reaching the End Of File early leads to
+    /// [`ReadStreamExactError::UnexpectedEof`], which contains the
+    /// partial bytes of the header or of the payload.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains the frame type, the channel and the payload.
+pub type ReadStreamAmqpFrameResult = CoroutineResult<(u8, u16, Vec<u8>), ReadStreamAmqpFrameError>;
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the frame type, the channel and the payload size.
+    Header(ReadStreamExact),
+
+    /// Reading the payload and the frame end.
+    Payload(u8, u16, ReadStreamExact),
+}
+
+/// I/O-free coroutine to read an AMQP 0-9-1 frame.
+///
+/// A frame is made of a 1-byte type, a 2-byte big endian channel, a
+/// 4-byte big endian payload size, then that many bytes of payload,
+/// terminated by the [`Self::FRAME_END`] byte.
+#[derive(Debug)]
+pub struct ReadStreamAmqpFrame {
+    /// The read buffer capacity.
+    capacity: usize,
+
+    /// The maximum frame size.
+    max: usize,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamAmqpFrame {
+    /// The size of the header, frame type included.
+    pub const HEADER_SIZE: usize = 7;
+
+    /// The byte terminating each frame.
+    pub const FRAME_END: u8 = 0xce;
+
+    /// The default maximum frame size, the default `frame-max` of
+    /// RabbitMQ.
+    pub const DEFAULT_MAX_SIZE: usize = 128 * 1024;
+
+    /// Creates a new coroutine to read an AMQP frame using a buffer
+    /// with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read an AMQP frame using a buffer
+    /// with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read AMQP frame (capacity: {capacity})");
+        let read = ReadStreamExact::with_capacity(capacity, Self::HEADER_SIZE);
+        Self {
+            capacity,
+            max: Self::DEFAULT_MAX_SIZE,
+            state: State::Header(read),
+        }
+    }
+
+    /// Limits the frame size to the given maximum, as negotiated with
+    /// `frame-max`.
+    ///
+    /// Like `frame-max`, the maximum includes the header and the frame
+    /// end.
+    pub fn with_max(mut self, max: usize) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamAmqpFrameResult {
+        loop {
+            match &mut self.state {
+                State::Header(read) => {
+                    let header = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(header) => header,
+                        ReadStreamExactResult::Io(io) => break ReadStreamAmqpFrameResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamAmqpFrameResult::Err(err.into())
+                        }
+                    };
+
+                    let kind = header[0];
+                    let channel = u16::from_be_bytes([header[1], header[2]]);
+                    let size = [header[3], header[4], header[5], header[6]];
+                    let size = u32::from_be_bytes(size) as usize;
+                    let frame_size = size.saturating_add(Self::HEADER_SIZE + 1);
+
+                    if frame_size > self.max {
+                        let err = ReadStreamAmqpFrameError::TooLarge(frame_size, self.max);
+                        break ReadStreamAmqpFrameResult::Err(err);
+                    }
+
+                    debug!(
+                        "read AMQP frame header (type: {kind}, channel: {channel}, size: {size})"
+                    );
+                    // reads the frame end alongside the payload
+                    let read = ReadStreamExact::with_capacity(self.capacity, size + 1);
+                    self.state = State::Payload(kind, channel, read);
+                }
+                State::Payload(kind, channel, read) => {
+                    let mut payload = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(payload) => payload,
+                        ReadStreamExactResult::Io(io) => break ReadStreamAmqpFrameResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamAmqpFrameResult::Err(err.into())
+                        }
+                    };
+
+                    break match payload.pop() {
+                        Some(Self::FRAME_END) => {
+                            ReadStreamAmqpFrameResult::Ok((*kind, *channel, payload))
+                        }
+                        byte => {
+                            let err = ReadStreamAmqpFrameError::InvalidFrameEnd(byte.unwrap_or(0));
+                            ReadStreamAmqpFrameResult::Err(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl Coroutine for ReadStreamAmqpFrame {
+    type Output = (u8, u16, Vec<u8>);
+    type Error = ReadStreamAmqpFrameError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamAmqpFrameResult {
+        ReadStreamAmqpFrame::resume(self, arg)
+    }
+}
+
+impl Default for ReadStreamAmqpFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_amqp_frame::{ReadStreamAmqpFrameError, ReadStreamAmqpFrameResult},
+            Coroutine,
+        },
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamAmqpFrame;
+
+    fn read(mut read: ReadStreamAmqpFrame, input: &[u8]) -> ReadStreamAmqpFrameResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_amqp_frame() {
+        let _ = env_logger::try_init();
+
+        // method frame on channel 1
+        let input = b"\x01\x00\x01\x00\x00\x00\x05hello\xce";
+
+        match read(ReadStreamAmqpFrame::with_capacity(4), input) {
+            ReadStreamAmqpFrameResult::Ok((kind, channel, payload)) => {
+                assert_eq!(kind, 1);
+                assert_eq!(channel, 1);
+                assert_eq!(payload, b"hello");
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // heartbeat frame
+        match read(
+            ReadStreamAmqpFrame::new(),
+            b"\x08\x00\x00\x00\x00\x00\x00\xce",
+        ) {
+            ReadStreamAmqpFrameResult::Ok((8, 0, payload)) => assert!(payload.is_empty()),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_amqp_frame_invalid() {
+        let _ = env_logger::try_init();
+
+        let input = b"\x01\x00\x01\x00\x00\x00\x05hello\x00";
+
+        match read(ReadStreamAmqpFrame::new(), input) {
+            ReadStreamAmqpFrameResult::Err(ReadStreamAmqpFrameError::InvalidFrameEnd(0)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let read = ReadStreamAmqpFrame::new().with_max(4096);
+
+        match self::read(read, b"\x03\x00\x01\x00\x00\x10\x00") {
+            ReadStreamAmqpFrameResult::Err(ReadStreamAmqpFrameError::TooLarge(4104, 4096)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_amqp_frame_feed_all() {
+        let _ = env_logger::try_init();
+
+        let input = b"\x01\x00\x01\x00\x00\x00\x05hello\xce";
+        let frame = ReadStreamAmqpFrame::with_capacity(4)
+            .feed_all(input)
+            .unwrap();
+        assert_eq!(frame, (1, 1, b"hello".to_vec()));
+
+        // the exhaustion of the bytes is reported as EOF
+        match ReadStreamAmqpFrame::new().feed_all(&input[..8]) {
+            Err(ReadStreamAmqpFrameError::ReadExact(_)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! I/O-free coroutine to read bytes until braces and brackets
+//! balance.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamBalancedError {
+    /// The coroutine unexpectedly reached the End Of File.
+    ///
+    /// Contains the partial bytes read so far.
+    #[error("Unexpected EOF while reading balanced value")]
+    UnexpectedEof(Vec<u8>),
+
+    /// The value does not start with an opening delimiter, or a
+    /// closing delimiter does not match its opening one.
+    #[error("Unexpected byte {0:#04x} at position {1} of balanced value")]
+    UnexpectedByte(u8, usize),
+
+    /// The value exceeds the maximum length.
+    #[error("Balanced value exceeds {0} bytes")]
+    TooLong(usize),
+
+    /// The value exceeds the maximum nesting depth.
+    #[error("Balanced value exceeds depth {0}")]
+    TooDeep(usize),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamBalancedResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the bytes of the balanced value, delimiters included.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamBalancedError),
+}
+
+/// I/O-free coroutine to read bytes until braces and brackets
+/// balance.
+///
+/// The coroutine reads a single value starting with `{` or `[` (like
+/// a JSON object or array) and stops as soon as its closing delimiter
+/// is read. Delimiters inside double-quoted strings are ignored,
+/// backslash escapes included. Leading whitespaces are skipped.
+///
+/// Bytes read past the value are kept and can be retrieved with
+/// [`Self::take_leftover`].
+#[derive(Debug)]
+pub struct ReadStreamBalanced {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing read bytes.
+    buffer: Vec<u8>,
+
+    /// The position of the next byte to scan in the buffer.
+    pos: usize,
+
+    /// The closing delimiters expected, innermost last.
+    closers: Vec<u8>,
+
+    /// Whether the scan is inside a string.
+    in_string: bool,
+
+    /// Whether the previous byte was an escape inside a string.
+    escaped: bool,
+
+    /// The maximum length of the value.
+    max_len: usize,
+
+    /// The maximum nesting depth of the value.
+    max_depth: usize,
+}
+
+impl ReadStreamBalanced {
+    /// The default maximum length of a value.
+    pub const DEFAULT_MAX_LEN: usize = 1024 * 1024;
+
+    /// The default maximum nesting depth of a value.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Creates a new coroutine to read a balanced value using a
+    /// buffer with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a balanced value using a
+    /// buffer with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read balanced value (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            pos: 0,
+            closers: Vec::new(),
+            in_string: false,
+            escaped: false,
+            max_len: Self::DEFAULT_MAX_LEN,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Limits the length of the value to the given maximum.
+    pub fn with_max_len(mut self, max: usize) -> Self {
+        self.max_len = max;
+        self
+    }
+
+    /// Limits the nesting depth of the value to the given maximum.
+    pub fn with_max_depth(mut self, max: usize) -> Self {
+        self.max_depth = max;
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the value.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the value.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamBalancedResult {
+        loop {
+            if arg.is_none() {
+                match self.scan() {
+                    Ok(Some(value)) => break ReadStreamBalancedResult::Ok(value),
+                    Ok(None) => (),
+                    Err(err) => break ReadStreamBalancedResult::Err(err),
+                }
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamBalancedResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamBalancedResult::Io(io),
+                ReadStreamResult::Eof => {
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamBalancedError::UnexpectedEof(buffer);
+                    break ReadStreamBalancedResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+
+    /// Scans the unscanned bytes of the inner buffer.
+    ///
+    /// Returns `None` if more bytes are needed.
+    fn scan(&mut self) -> Result<Option<Vec<u8>>, ReadStreamBalancedError> {
+        while self.pos < self.buffer.len() {
+            let byte = self.buffer[self.pos];
+
+            if self.closers.is_empty() {
+                if byte.is_ascii_whitespace() {
+                    self.buffer.remove(self.pos);
+                    continue;
+                }
+
+                if byte != b'{' && byte != b'[' {
+                    return Err(ReadStreamBalancedError::UnexpectedByte(byte, self.pos));
+                }
+            }
+
+            if self.pos >= self.max_len {
+                return Err(ReadStreamBalancedError::TooLong(self.max_len));
+            }
+
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => (),
+                }
+            } else {
+                match byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => {
+                        if self.closers.len() >= self.max_depth {
+                            return Err(ReadStreamBalancedError::TooDeep(self.max_depth));
+                        }
+
+                        self.closers.push(if byte == b'{' { b'}' } else { b']' });
+                    }
+                    b'}' | b']' => {
+                        if self.closers.last() != Some(&byte) {
+                            return Err(ReadStreamBalancedError::UnexpectedByte(byte, self.pos));
+                        }
+
+                        self.closers.pop();
+
+                        if self.closers.is_empty() {
+                            let leftover = self.buffer.split_off(self.pos + 1);
+                            let value = mem::replace(&mut self.buffer, leftover);
+                            self.pos = 0;
+                            debug!("read balanced value of {} bytes", value.len());
+                            return Ok(Some(value));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            self.pos += 1;
+        }
+
+        Ok(None)
+    }
+}
+
+impl Default for ReadStreamBalanced {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_balanced::{ReadStreamBalancedError, ReadStreamBalancedResult},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamBalanced;
+
+    impl_io_result!(ReadStreamBalancedResult);
+
+    fn read(
+        mut balanced: ReadStreamBalanced,
+        input: &[u8],
+    ) -> (ReadStreamBalanced, ReadStreamBalancedResult) {
+        let mut reader = ChunkedCursor::with_pattern(input, []);
+        let result = resume_until_done(&mut reader, |arg| balanced.resume(arg));
+
+        (balanced, result)
+    }
+
+    #[test]
+    fn read_balanced_flat_object() {
+        let _ = env_logger::try_init();
+
+        let input = br#" {"a": 1, "b": 2}{"c": 3}"#;
+        let (balanced, result) = read(ReadStreamBalanced::with_capacity(4), input);
+
+        match result {
+            ReadStreamBalancedResult::Ok(value) => assert_eq!(value, br#"{"a": 1, "b": 2}"#),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(balanced.leftover(), br#"{"c"#);
+    }
+
+    #[test]
+    fn read_balanced_nested_array() {
+        let _ = env_logger::try_init();
+
+        let input = b"[1, [2, {\"a\": [3]}], []]\n";
+        let (balanced, result) = read(ReadStreamBalanced::new(), input);
+
+        match result {
+            ReadStreamBalancedResult::Ok(value) => assert_eq!(value, b"[1, [2, {\"a\": [3]}], []]"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(balanced.leftover(), b"\n");
+
+        let balanced = ReadStreamBalanced::new().with_max_depth(2);
+        let (_, result) = read(balanced, input);
+
+        match result {
+            ReadStreamBalancedResult::Err(ReadStreamBalancedError::TooDeep(2)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_balanced_string_with_braces() {
+        let _ = env_logger::try_init();
+
+        let input = br#"{"a": "}{ \" ]["}"#;
+        let (balanced, result) = read(ReadStreamBalanced::with_capacity(3), input);
+
+        match result {
+            ReadStreamBalancedResult::Ok(value) => assert_eq!(value, input),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert!(balanced.leftover().is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+//! I/O-free coroutine to read a base64-encoded line and decode it.
+
+use alloc::{string::String, vec::Vec};
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    DecodeError, Engine,
+};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_line::{ReadStreamLine, ReadStreamLineError, ReadStreamLineResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamBase64LineError {
+    /// The line is not valid base64.
+    ///
+    /// Contains the raw line and the decoding error.
+    #[error("Invalid base64 line: {1}")]
+    Base64(String, DecodeError),
+
+    /// Error from the [`ReadStreamLine`] coroutine.
+    #[error(transparent)]
+    ReadLine(#[from] ReadStreamLineError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamBase64LineResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the decoded bytes.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamBase64LineError),
+}
+
+/// The base64 alphabet.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Base64Alphabet {
+    /// The standard alphabet, using `+` and `/`.
+    #[default]
+    Standard,
+
+    /// The URL-safe alphabet, using `-` and `_`.
+    UrlSafe,
+}
+
+impl Base64Alphabet {
+    /// Builds the base64 engine for this alphabet.
+    ///
+    /// Encoding always pads, whereas decoding requires padding only
+    /// if `require_padding` is `true`.
+    pub fn engine(&self, require_padding: bool) -> GeneralPurpose {
+        let alphabet = match self {
+            Self::Standard => &alphabet::STANDARD,
+            Self::UrlSafe => &alphabet::URL_SAFE,
+        };
+
+        let padding = if require_padding {
+            DecodePaddingMode::RequireCanonical
+        } else {
+            DecodePaddingMode::Indifferent
+        };
+
+        let config = GeneralPurposeConfig::new().with_decode_padding_mode(padding);
+        GeneralPurpose::new(alphabet, config)
+    }
+}
+
+/// I/O-free coroutine to read a base64-encoded line and decode it.
+///
+/// The line ending is stripped before decoding. By default, the
+/// standard alphabet is used and padding is required.
+#[derive(Debug)]
+pub struct ReadStreamBase64Line {
+    /// The inner read line coroutine.
+    read: ReadStreamLine,
+
+    /// The base64 alphabet.
+    alphabet: Base64Alphabet,
+
+    /// Whether padding is required.
+    require_padding: bool,
+}
+
+impl ReadStreamBase64Line {
+    /// Creates a new coroutine to read a base64 line using a buffer
+    /// with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a base64 line using a buffer
+    /// with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read base64 line (capacity: {capacity})");
+        Self {
+            read: ReadStreamLine::with_capacity(capacity),
+            alphabet: Base64Alphabet::default(),
+            require_padding: true,
+        }
+    }
+
+    /// Decodes the line using the given alphabet.
+    pub fn with_alphabet(mut self, alphabet: Base64Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Defines whether padding is required.
+    pub fn require_padding(mut self, require: bool) -> Self {
+        self.require_padding = require;
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.read.extend(bytes);
+    }
+
+    /// Returns the bytes read past the line.
+    pub fn leftover(&self) -> &[u8] {
+        self.read.leftover()
+    }
+
+    /// Takes the bytes read past the line.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        self.read.take_leftover()
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamBase64LineResult {
+        let line = match self.read.resume(arg) {
+            ReadStreamLineResult::Ok(line) => line,
+            ReadStreamLineResult::Io(io) => return ReadStreamBase64LineResult::Io(io),
+            ReadStreamLineResult::Err(err) => return ReadStreamBase64LineResult::Err(err.into()),
+        };
+
+        let engine = self.alphabet.engine(self.require_padding);
+
+        match engine.decode(&line) {
+            Ok(bytes) => {
+                debug!("decoded {} base64 bytes", bytes.len());
+                ReadStreamBase64LineResult::Ok(bytes)
+            }
+            Err(err) => {
+                let err = ReadStreamBase64LineError::Base64(line, err);
+                ReadStreamBase64LineResult::Err(err)
+            }
+        }
+    }
+}
+
+impl Default for ReadStreamBase64Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_base64_line::{
+            Base64Alphabet, ReadStreamBase64LineError, ReadStreamBase64LineResult,
+        },
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamBase64Line;
+
+    impl_io_result!(ReadStreamBase64LineResult);
+
+    fn read(mut read: ReadStreamBase64Line, input: &[u8]) -> ReadStreamBase64LineResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_base64_line() {
+        let _ = env_logger::try_init();
+
+        // SASL PLAIN credentials
+        let input = b"AHVzZXIAcGFzc3dvcmQ=\r\n";
+
+        match read(ReadStreamBase64Line::with_capacity(4), input) {
+            ReadStreamBase64LineResult::Ok(bytes) => assert_eq!(bytes, b"\0user\0password"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let read = ReadStreamBase64Line::new().require_padding(false);
+
+        match self::read(read, b"AHVzZXIAcGFzc3dvcmQ\r\n") {
+            ReadStreamBase64LineResult::Ok(bytes) => assert_eq!(bytes, b"\0user\0password"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_base64_line_url_safe() {
+        let _ = env_logger::try_init();
+
+        let read = ReadStreamBase64Line::new().with_alphabet(Base64Alphabet::UrlSafe);
+
+        match self::read(read, b"-_-_\n") {
+            ReadStreamBase64LineResult::Ok(bytes) => assert_eq!(bytes, [0xfb, 0xff, 0xbf]),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // the standard alphabet rejects URL-safe symbols
+        match self::read(ReadStreamBase64Line::new(), b"-_-_\n") {
+            ReadStreamBase64LineResult::Err(ReadStreamBase64LineError::Base64(line, _)) => {
+                assert_eq!(line, "-_-_")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_base64_line_malformed() {
+        let _ = env_logger::try_init();
+
+        // missing padding
+        match read(ReadStreamBase64Line::new(), b"AHVzZXIAcGFzc3dvcmQ\r\n") {
+            ReadStreamBase64LineResult::Err(ReadStreamBase64LineError::Base64(..)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match read(ReadStreamBase64Line::new(), b"not base64!\r\n") {
+            ReadStreamBase64LineResult::Err(ReadStreamBase64LineError::Base64(..)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! I/O-free coroutine to read bytes into a [`BytesMut`].
+
+use alloc::vec::Vec;
+use core::mem;
+
+use bytes::{Bytes, BytesMut};
+use log::{debug, trace};
+
+use crate::io::{StreamIo, StreamOutput};
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamBytesResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the given [`BytesMut`], advanced by the amount of
+    /// read bytes.
+    Ok(BytesMut),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// The coroutine reached the End Of File.
+    ///
+    /// The untouched [`BytesMut`] can be retrieved with
+    /// [`ReadStreamBytes::into_inner`].
+    Eof,
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamError),
+}
+
+/// I/O-free coroutine to read bytes into the spare capacity of a
+/// [`BytesMut`].
+///
+/// At least [`Self::capacity`] bytes of spare capacity are reserved
+/// before reading, which does not reallocate if the [`BytesMut`] is
+/// already big enough.
+///
+/// Since [`StreamIo`] requests own their buffer, the allocation of an
+/// empty [`BytesMut`] is lent to the runtime as is, without copying
+/// bytes. Otherwise the bytes are read into an intermediate buffer,
+/// then appended to the [`BytesMut`].
+#[derive(Debug)]
+pub struct ReadStreamBytes {
+    /// The inner read coroutine, used for non-empty [`BytesMut`].
+    read: ReadStream,
+
+    /// The caller-provided bytes.
+    bytes: BytesMut,
+
+    /// Whether the allocation of the bytes is lent to the runtime.
+    lent: bool,
+}
+
+impl ReadStreamBytes {
+    /// Creates a new coroutine to read bytes into the given
+    /// [`BytesMut`], reserving [`ReadStream::DEFAULT_CAPACITY`] bytes
+    /// at least.
+    ///
+    /// See [`Self::with_capacity`] for a custom capacity.
+    pub fn new(bytes: BytesMut) -> Self {
+        Self::with_capacity(bytes, ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read bytes into the given
+    /// [`BytesMut`], reserving the given capacity at least.
+    pub fn with_capacity(bytes: BytesMut, capacity: usize) -> Self {
+        trace!("init coroutine to read bytes into BytesMut (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            bytes,
+            lent: false,
+        }
+    }
+
+    /// Returns the minimum spare capacity reserved before reading.
+    pub fn capacity(&self) -> usize {
+        self.read.capacity()
+    }
+
+    /// Returns the inner [`BytesMut`].
+    pub fn into_inner(self) -> BytesMut {
+        self.bytes
+    }
+
+    /// Makes the read progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamBytesResult {
+        let Some(arg) = arg else {
+            self.bytes.reserve(self.capacity());
+
+            if !self.bytes.is_empty() {
+                return self.resume_read(None);
+            }
+
+            let mut buffer = Vec::from(mem::take(&mut self.bytes));
+            buffer.resize(buffer.capacity(), 0);
+            self.lent = true;
+
+            trace!("wants I/O to read bytes into BytesMut");
+            return ReadStreamBytesResult::Io(StreamIo::Read(Err(buffer)));
+        };
+
+        if !self.lent {
+            return self.resume_read(Some(arg));
+        }
+
+        let output = match arg {
+            StreamIo::Read(Ok(output)) => output,
+            arg => {
+                let err = ReadStreamError::InvalidArgument("read output", arg);
+                return ReadStreamBytesResult::Err(err);
+            }
+        };
+
+        let StreamOutput {
+            mut buffer,
+            bytes_count,
+        } = output;
+
+        self.lent = false;
+        buffer.truncate(bytes_count);
+        self.bytes = Bytes::from(buffer).into();
+
+        match bytes_count {
+            0 => ReadStreamBytesResult::Eof,
+            n => {
+                debug!("read {n} bytes into BytesMut");
+                ReadStreamBytesResult::Ok(mem::take(&mut self.bytes))
+            }
+        }
+    }
+
+    /// Makes the inner read progress, then appends the read bytes.
+    fn resume_read(&mut self, arg: Option<StreamIo>) -> ReadStreamBytesResult {
+        let output = match self.read.resume(arg) {
+            ReadStreamResult::Ok(output) => output,
+            ReadStreamResult::Io(io) => return ReadStreamBytesResult::Io(io),
+            ReadStreamResult::Eof => return ReadStreamBytesResult::Eof,
+            ReadStreamResult::Err(err) => return ReadStreamBytesResult::Err(err),
+        };
+
+        self.bytes.extend_from_slice(output.bytes());
+        self.read.replace(output.buffer);
+        debug!("appended {} bytes to BytesMut", output.bytes_count);
+        ReadStreamBytesResult::Ok(mem::take(&mut self.bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use crate::{
+        coroutines::read_bytes::ReadStreamBytesResult,
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamBytes;
+
+    impl_io_result!(ReadStreamBytesResult);
+
+    fn read(mut read: ReadStreamBytes, cursor: &mut ChunkedCursor) -> BytesMut {
+        match resume_until_done(cursor, |arg| read.resume(arg)) {
+            ReadStreamBytesResult::Ok(bytes) => bytes,
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_bytes_no_realloc() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern(*b"abcdefghij", [6, 4]);
+
+        let bytes = BytesMut::with_capacity(64);
+        let ptr = bytes.as_ptr();
+
+        // the empty BytesMut allocation is lent to the runtime
+        let bytes = read(ReadStreamBytes::with_capacity(bytes, 16), &mut reader);
+        assert_eq!(bytes, "abcdef");
+        assert_eq!(bytes.as_ptr(), ptr);
+        assert_eq!(bytes.capacity(), 64);
+
+        // the non-empty BytesMut is appended in its spare capacity
+        let bytes = read(ReadStreamBytes::with_capacity(bytes, 16), &mut reader);
+        assert_eq!(bytes, "abcdefghij");
+        assert_eq!(bytes.as_ptr(), ptr);
+        assert_eq!(bytes.capacity(), 64);
+    }
+
+    #[test]
+    fn read_bytes_reserve() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern(*b"abcdef", []);
+
+        let read = ReadStreamBytes::with_capacity(BytesMut::new(), 4);
+        let bytes = self::read(read, &mut reader);
+
+        // the lent allocation may be bigger than the reserved capacity
+        assert!(bytes.capacity() >= 4);
+        assert!(!bytes.is_empty());
+        assert!(b"abcdef".starts_with(&bytes));
+    }
+}
@@ -0,0 +1,169 @@
+//! I/O-free coroutine to read a stream chunk by chunk.
+
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+
+use crate::io::{StreamIo, StreamOutput};
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamChunksResult {
+    /// The coroutine has read a chunk.
+    ///
+    /// The buffer of the chunk should be given back with
+    /// [`ReadStreamChunks::replace`] once processed, so that it gets
+    /// reused for the next chunk.
+    Chunk(StreamOutput),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// The coroutine reached the End Of File, and all read chunks
+    /// have already been returned.
+    Eof,
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamError),
+}
+
+/// I/O-free coroutine to read a stream chunk by chunk.
+///
+/// Each read is returned to the caller as a chunk, which is processed
+/// before resuming the coroutine for the next one: unlike
+/// [`ReadStreamToEnd`], the whole stream is never held in memory,
+/// which suits large bodies that are hashed or forwarded on the fly.
+/// The End Of File is sticky: once reached, resuming keeps returning
+/// [`ReadStreamChunksResult::Eof`] without emitting I/O.
+///
+/// [`ReadStreamToEnd`]: super::read_to_end::ReadStreamToEnd
+#[derive(Debug)]
+pub struct ReadStreamChunks {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The total amount of bytes read so far.
+    total: usize,
+
+    /// Whether the End Of File has been reached.
+    eof: bool,
+}
+
+impl ReadStreamChunks {
+    /// Creates a new coroutine to read chunks using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read chunks using a buffer with the
+    /// given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read chunks (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            total: 0,
+            eof: false,
+        }
+    }
+
+    /// Returns the total amount of bytes read so far.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Gives the buffer of a processed chunk back to the coroutine.
+    ///
+    /// See [`ReadStream::replace`].
+    pub fn replace(&mut self, buffer: Vec<u8>) {
+        self.read.replace(buffer);
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamChunksResult {
+        if self.eof && arg.is_none() {
+            return ReadStreamChunksResult::Eof;
+        }
+
+        match self.read.resume(arg) {
+            ReadStreamResult::Ok(output) => {
+                self.total += output.bytes_count;
+                debug!("read chunk of {} bytes", output.bytes_count);
+                ReadStreamChunksResult::Chunk(output)
+            }
+            ReadStreamResult::Io(io) => ReadStreamChunksResult::Io(io),
+            ReadStreamResult::Eof => {
+                debug!("reached EOF after reading {} bytes", self.total);
+                self.eof = true;
+                ReadStreamChunksResult::Eof
+            }
+            ReadStreamResult::Err(err) => ReadStreamChunksResult::Err(err),
+        }
+    }
+}
+
+impl Default for ReadStreamChunks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use crate::{
+        coroutines::read_chunks::ReadStreamChunksResult,
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::ChunkedCursor,
+    };
+
+    use super::ReadStreamChunks;
+
+    #[test]
+    fn read_chunks() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdefghij".as_bytes(), []);
+
+        let mut read = ReadStreamChunks::with_capacity(4);
+        let mut chunks = Vec::new();
+        let mut ptrs = Vec::new();
+        let mut arg = None;
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamChunksResult::Chunk(output) => {
+                    chunks.push(output.bytes().to_vec());
+                    read.replace(output.buffer);
+                }
+                ReadStreamChunksResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    ptrs.push(buffer.as_ptr());
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                ReadStreamChunksResult::Eof => break,
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(chunks, [&b"abcd"[..], b"efgh", b"ij"]);
+        assert_eq!(chunks.concat(), b"abcdefghij");
+        assert_eq!(read.total(), 10);
+
+        // the buffer is reused across chunks
+        assert!(ptrs.iter().all(|ptr| *ptr == ptrs[0]));
+
+        // the End Of File is sticky
+        assert!(matches!(read.resume(None), ReadStreamChunksResult::Eof));
+    }
+}
@@ -0,0 +1,270 @@
+//! I/O-free coroutine to read an ASCII decimal integer.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamDecimalError {
+    /// The coroutine unexpectedly reached the End Of File.
+    ///
+    /// Contains the digits read so far.
+    #[error("Unexpected EOF while reading decimal")]
+    UnexpectedEof(Vec<u8>),
+
+    /// The integer does not start with a digit.
+    #[error("Expected decimal digit, got byte {0:#04x}")]
+    NoDigits(u8),
+
+    /// The integer is not terminated by the expected delimiter.
+    #[error("Expected decimal delimiter {0:#04x}, got byte {1:#04x}")]
+    InvalidDelimiter(u8, u8),
+
+    /// The integer does not fit into 64 bits.
+    #[error("Decimal overflows 64 bits")]
+    Overflow,
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+pub type ReadStreamDecimalResult = CoroutineResult<u64, ReadStreamDecimalError>;
+
+/// I/O-free coroutine to read an ASCII decimal integer.
+///
+/// Digits are read until the first non-digit byte, which terminates
+/// the integer, as found in netstrings, bencode or chunked transfer
+/// encoding. The terminating byte is not consumed: it is kept with the
+/// other bytes read past the integer, and can be retrieved with
+/// [`Self::take_leftover`].
+///
+/// Overflows are detected as digits are read, so that an endless
+/// stream of digits fails early.
+#[derive(Debug)]
+pub struct ReadStreamDecimal {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing read bytes not yet consumed.
+    buffer: Vec<u8>,
+
+    /// The expected delimiter, if any.
+    delimiter: Option<u8>,
+}
+
+impl ReadStreamDecimal {
+    /// Creates a new coroutine to read a decimal integer using a
+    /// buffer with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a decimal integer using a
+    /// buffer with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read decimal (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            delimiter: None,
+        }
+    }
+
+    /// Requires the integer to be terminated by the given delimiter.
+    ///
+    /// Any other non-digit byte fails with
+    /// [`ReadStreamDecimalError::InvalidDelimiter`].
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the integer, delimiter included.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the integer, delimiter included.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamDecimalResult {
+        loop {
+            if arg.is_none() {
+                match self.decode() {
+                    Ok(Some(n)) => break ReadStreamDecimalResult::Ok(n),
+                    Ok(None) => (),
+                    Err(err) => break ReadStreamDecimalResult::Err(err),
+                }
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamDecimalResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamDecimalResult::Io(io),
+                ReadStreamResult::Eof => {
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamDecimalError::UnexpectedEof(buffer);
+                    break ReadStreamDecimalResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+
+    /// Tries to decode a decimal integer from the inner buffer.
+    ///
+    /// Returns `None` if more bytes are needed.
+    fn decode(&mut self) -> Result<Option<u64>, ReadStreamDecimalError> {
+        let end = self.buffer.iter().position(|byte| !byte.is_ascii_digit());
+        let digits = &self.buffer[..end.unwrap_or(self.buffer.len())];
+
+        // at most 20 digits are parsed before overflowing
+        let mut n = 0u64;
+
+        for digit in digits {
+            n = n
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(u64::from(digit - b'0')))
+                .ok_or(ReadStreamDecimalError::Overflow)?;
+        }
+
+        let Some(end) = end else {
+            return Ok(None);
+        };
+
+        let byte = self.buffer[end];
+
+        if end == 0 {
+            return Err(ReadStreamDecimalError::NoDigits(byte));
+        }
+
+        if let Some(delimiter) = self.delimiter {
+            if byte != delimiter {
+                return Err(ReadStreamDecimalError::InvalidDelimiter(delimiter, byte));
+            }
+        }
+
+        self.buffer.drain(..end);
+        debug!("read decimal {n} on {end} digits");
+        Ok(Some(n))
+    }
+}
+
+impl Coroutine for ReadStreamDecimal {
+    type Output = u64;
+    type Error = ReadStreamDecimalError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamDecimalResult {
+        ReadStreamDecimal::resume(self, arg)
+    }
+}
+
+impl Default for ReadStreamDecimal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_decimal::{ReadStreamDecimalError, ReadStreamDecimalResult},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamDecimal;
+
+    fn read(
+        mut decimal: ReadStreamDecimal,
+        input: &[u8],
+    ) -> (ReadStreamDecimal, ReadStreamDecimalResult) {
+        let mut reader = ChunkedCursor::with_pattern(input, []);
+        let result = resume_until_done(&mut reader, |arg| decimal.resume(arg));
+
+        (decimal, result)
+    }
+
+    #[test]
+    fn read_decimal() {
+        let _ = env_logger::try_init();
+
+        // netstring length
+        let decimal = ReadStreamDecimal::with_capacity(2).with_delimiter(b':');
+        let (decimal, result) = read(decimal, b"12:hello world,");
+
+        match result {
+            ReadStreamDecimalResult::Ok(12) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(decimal.leftover(), b":h");
+
+        match read(ReadStreamDecimal::new(), b"18446744073709551615\r\n").1 {
+            ReadStreamDecimalResult::Ok(u64::MAX) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match read(ReadStreamDecimal::new().with_delimiter(b':'), b"12 ").1 {
+            ReadStreamDecimalResult::Err(ReadStreamDecimalError::InvalidDelimiter(
+                expected,
+                got,
+            )) => {
+                assert_eq!(expected, b':');
+                assert_eq!(got, b' ');
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_decimal_overflow() {
+        let _ = env_logger::try_init();
+
+        // fails before reaching the delimiter
+        let input = [b'9'; 64];
+
+        match read(ReadStreamDecimal::with_capacity(8), &input).1 {
+            ReadStreamDecimalResult::Err(ReadStreamDecimalError::Overflow) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match read(ReadStreamDecimal::new(), b"18446744073709551616 ").1 {
+            ReadStreamDecimalResult::Err(ReadStreamDecimalError::Overflow) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_decimal_no_digits() {
+        let _ = env_logger::try_init();
+
+        match read(ReadStreamDecimal::new(), b"-12\r\n").1 {
+            ReadStreamDecimalResult::Err(ReadStreamDecimalError::NoDigits(b'-')) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! I/O-free coroutine to read a DNS message over TCP.
+
+use alloc::vec::Vec;
+
+use log::trace;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_framed::{PrefixWidth, ReadStreamFramed, ReadStreamFramedError},
+    Coroutine, CoroutineResult,
+};
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains the raw DNS message, without the length prefix.
+pub type ReadStreamDnsMessageResult = CoroutineResult<Vec<u8>, ReadStreamFramedError>;
+
+/// I/O-free coroutine to read a DNS message over TCP, see [RFC 1035
+/// section 4.2.2].
+///
+/// Over TCP, and over TLS, each message is prefixed by a 2-byte big
+/// endian length, which bounds messages to [`Self::MAX_SIZE`] bytes.
+/// The message is returned raw, parsing it is left to the caller.
+///
+/// [RFC 1035 section 4.2.2]: https://www.rfc-editor.org/rfc/rfc1035#section-4.2.2
+#[derive(Debug)]
+pub struct ReadStreamDnsMessage {
+    /// The inner read framed coroutine.
+    read: ReadStreamFramed,
+}
+
+impl ReadStreamDnsMessage {
+    /// The maximum size of a DNS message, implied by the 2-byte
+    /// length prefix.
+    pub const MAX_SIZE: usize = u16::MAX as usize;
+
+    /// Creates a new coroutine to read a DNS message using a buffer
+    /// with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a DNS message using a buffer
+    /// with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read DNS message (capacity: {capacity})");
+        let read = ReadStreamFramed::with_capacity(capacity, PrefixWidth::U16)
+            .with_max_frame(Self::MAX_SIZE as u64);
+        Self { read }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamDnsMessageResult {
+        self.read.resume(arg)
+    }
+}
+
+impl Default for ReadStreamDnsMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coroutine for ReadStreamDnsMessage {
+    type Output = Vec<u8>;
+    type Error = ReadStreamFramedError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamDnsMessageResult {
+        ReadStreamDnsMessage::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_dns_message::ReadStreamDnsMessageResult,
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamDnsMessage;
+
+    fn read(mut read: ReadStreamDnsMessage, input: &[u8]) -> ReadStreamDnsMessageResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_dns_message() {
+        let _ = env_logger::try_init();
+
+        // header of a query with ID 0xabcd and one question
+        let input = b"\x00\x0c\xab\xcd\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\xff";
+
+        match read(ReadStreamDnsMessage::with_capacity(5), input) {
+            ReadStreamDnsMessageResult::Ok(message) => assert_eq!(message, input[2..14]),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_empty_dns_message() {
+        let _ = env_logger::try_init();
+
+        match read(ReadStreamDnsMessage::new(), b"\x00\x00\xab\xcd") {
+            ReadStreamDnsMessageResult::Ok(message) => assert!(message.is_empty()),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
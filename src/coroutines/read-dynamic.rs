@@ -0,0 +1,239 @@
+//! I/O-free coroutine to read bytes until a callback, inspecting the
+//! bytes read so far, decides to stop.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// The amount of bytes to read next, as returned by the callback of
+/// the [`ReadStreamDynamic`] coroutine.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReadTarget {
+    /// Keeps reading until the given total amount of bytes has been
+    /// read.
+    More(usize),
+
+    /// Stops reading.
+    Done,
+}
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamDynamicError {
+    /// The callback asked for a total not greater than the amount of
+    /// bytes already read, which would never make progress.
+    #[error("Invalid read target {0}, already read {1} bytes")]
+    InvalidTarget(usize, usize),
+
+    /// The coroutine unexpectedly reached the End Of File.
+    ///
+    /// Contains the partial bytes read so far, moved out of the
+    /// coroutine.
+    #[error("Unexpected EOF, expected to read {0} bytes")]
+    UnexpectedEof(usize, Vec<u8>),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamDynamicResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains all the bytes read.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamDynamicError),
+}
+
+/// I/O-free coroutine to read bytes until a callback, inspecting the
+/// bytes read so far, decides to stop.
+///
+/// This suits self-describing formats, where the amount of bytes to
+/// read is revealed by bytes already read, like a length field
+/// embedded partway.
+///
+/// The callback is first called with no byte, then each time the
+/// total it returned has been reached. Reads are limited to the
+/// remaining bytes, so that no byte is read past the target.
+#[derive(Debug)]
+pub struct ReadStreamDynamic<F> {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing the bytes read so far.
+    buffer: Vec<u8>,
+
+    /// The callback returning the next target.
+    target: F,
+
+    /// The current total amount of bytes to read, if any.
+    total: Option<usize>,
+}
+
+impl<F: FnMut(&[u8]) -> ReadTarget> ReadStreamDynamic<F> {
+    /// Creates a new coroutine to read bytes until the given callback
+    /// returns [`ReadTarget::Done`], using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(target: F) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, target)
+    }
+
+    /// Creates a new coroutine to read bytes until the given callback
+    /// returns [`ReadTarget::Done`], using a buffer with the given
+    /// capacity.
+    pub fn with_capacity(capacity: usize, target: F) -> Self {
+        trace!("init coroutine to read dynamic amount of bytes (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            target,
+            total: None,
+        }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read so far.
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamDynamicResult {
+        loop {
+            let total = match self.total {
+                Some(total) if self.buffer.len() < total => total,
+                _ => match (self.target)(&self.buffer) {
+                    ReadTarget::Done => {
+                        debug!("read {} dynamic bytes", self.buffer.len());
+                        self.total = None;
+                        break ReadStreamDynamicResult::Ok(mem::take(&mut self.buffer));
+                    }
+                    ReadTarget::More(total) if total <= self.buffer.len() => {
+                        let err = ReadStreamDynamicError::InvalidTarget(total, self.buffer.len());
+                        break ReadStreamDynamicResult::Err(err);
+                    }
+                    ReadTarget::More(total) => {
+                        debug!("read target updated to {total} bytes");
+                        *self.total.insert(total)
+                    }
+                },
+            };
+
+            let remaining = total - self.buffer.len();
+
+            if remaining < self.read.capacity() {
+                self.read.limit_next_read(remaining);
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Io(io) => break ReadStreamDynamicResult::Io(io),
+                ReadStreamResult::Err(err) => break ReadStreamDynamicResult::Err(err.into()),
+                ReadStreamResult::Eof => {
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamDynamicError::UnexpectedEof(total, buffer);
+                    break ReadStreamDynamicResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_dynamic::{ReadStreamDynamicError, ReadStreamDynamicResult, ReadTarget},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamDynamic;
+
+    impl_io_result!(ReadStreamDynamicResult);
+
+    /// Reads a big-endian `u32` length, then the payload it reveals.
+    fn length_prefixed(bytes: &[u8]) -> ReadTarget {
+        let Some(len) = bytes.get(..4) else {
+            return ReadTarget::More(4);
+        };
+
+        let total = 4 + u32::from_be_bytes(len.try_into().unwrap()) as usize;
+
+        if bytes.len() < total {
+            ReadTarget::More(total)
+        } else {
+            ReadTarget::Done
+        }
+    }
+
+    fn read<F>(
+        mut read: ReadStreamDynamic<F>,
+        cursor: &mut ChunkedCursor,
+    ) -> ReadStreamDynamicResult
+    where
+        F: FnMut(&[u8]) -> ReadTarget,
+    {
+        resume_until_done(cursor, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_dynamic() {
+        let _ = env_logger::try_init();
+
+        // simulates partial reads of 3 bytes max
+        let mut cursor = ChunkedCursor::new(*b"\0\0\0\x05hellotail", 3);
+        let mut targets = Vec::new();
+
+        let read = ReadStreamDynamic::with_capacity(8, |bytes: &[u8]| {
+            let target = length_prefixed(bytes);
+            targets.push(target);
+            target
+        });
+
+        match self::read(read, &mut cursor) {
+            ReadStreamDynamicResult::Ok(bytes) => assert_eq!(bytes, b"\0\0\0\x05hello"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // the target grows once the length has been read
+        let expected = [ReadTarget::More(4), ReadTarget::More(9), ReadTarget::Done];
+        assert_eq!(targets, expected);
+        assert_eq!(cursor.remaining(), b"tail");
+    }
+
+    #[test]
+    fn read_dynamic_unexpected_eof() {
+        let _ = env_logger::try_init();
+
+        let mut cursor = ChunkedCursor::new(*b"\0\0\0\x05hel", 3);
+
+        match read(ReadStreamDynamic::new(length_prefixed), &mut cursor) {
+            ReadStreamDynamicResult::Err(ReadStreamDynamicError::UnexpectedEof(9, bytes)) => {
+                assert_eq!(bytes, b"\0\0\0\x05hel")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
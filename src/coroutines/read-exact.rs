@@ -1,43 +1,68 @@
 //! I/O-free coroutine to read bytes into a buffer until it reaches a
 //! given amount of bytes.
 
-use std::mem;
+use alloc::{collections::TryReserveError, sync::Arc, vec::Vec};
+use core::mem;
+
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 use log::{debug, trace};
 use thiserror::Error;
 
 use crate::{coroutines::read::ReadStreamResult, io::StreamIo};
 
-use super::read::{ReadStream, ReadStreamError};
+use super::{
+    cancel::Cancel,
+    observer::StreamObserver,
+    read::{ReadBudget, ReadStream, ReadStreamError},
+    Coroutine, CoroutineResult,
+};
 
 /// Errors that can occur during the coroutine progression.
+///
+/// Cloning is expensive when the error carries the partial bytes.
 #[derive(Clone, Debug, Error)]
 pub enum ReadStreamExactError {
     /// The coroutine unexpectedly reached the End Of File.
+    ///
+    /// Contains the partial bytes read so far, moved out of the
+    /// coroutine.
     #[error("Unexpected EOF, expected to read {0}/{1} more bytes")]
     UnexpectedEof(usize, usize, Vec<u8>),
 
+    /// The buffer for the given amount of bytes cannot be allocated.
+    ///
+    /// Occurs for impossibly large amounts, especially on 32-bit
+    /// targets, instead of aborting.
+    #[error("Cannot allocate buffer to read {0} bytes: {1}")]
+    Alloc(usize, TryReserveError),
+
     /// Error from the [`Read`] coroutine.
     #[error(transparent)]
     Read(#[from] ReadStreamError),
+
+    /// The deadline of the whole operation has been exceeded.
+    ///
+    /// Contains the partial bytes read so far, moved out of the
+    /// coroutine.
+    ///
+    /// Only deadlines set with `with_deadline` can be exceeded, which
+    /// requires the `std` feature. The variant is always defined, so
+    /// that the error enum does not depend on features.
+    #[error("Deadline exceeded after reading {} bytes", .0.len())]
+    DeadlineExceeded(Vec<u8>),
 }
 
 /// Output emitted after a coroutine finishes its progression.
-#[derive(Clone, Debug)]
-pub enum ReadStreamExactResult {
-    /// The coroutine has successfully terminated its progression.
-    Ok(Vec<u8>),
-
-    /// A stream I/O needs to be performed to make the coroutine
-    /// progress.
-    Io(StreamIo),
-
-    /// An error occured during the coroutine progression.
-    Err(ReadStreamExactError),
-}
+pub type ReadStreamExactResult = CoroutineResult<Vec<u8>, ReadStreamExactError>;
 
 /// I/O-free coroutine to read bytes into a buffer until it reaches a
 /// given amount of bytes.
+///
+/// The buffer for the whole amount of bytes is allocated upfront.
+/// Failing to allocate it does not abort: the coroutine fails with
+/// [`ReadStreamExactError::Alloc`] instead.
 #[derive(Debug)]
 pub struct ReadStreamExact {
     /// The inner read coroutine.
@@ -48,6 +73,13 @@ pub struct ReadStreamExact {
 
     /// The exact amount of bytes to read.
     max: usize,
+
+    /// The byte to count alongside its occurrences so far, if any.
+    tap: Option<(u8, usize)>,
+
+    /// The deadline of the whole operation, if any.
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
 }
 
 impl ReadStreamExact {
@@ -64,8 +96,75 @@ impl ReadStreamExact {
     pub fn with_capacity(capacity: usize, max: usize) -> Self {
         trace!("init coroutine to read exactly {max} bytes (capacity: {capacity})");
         let read = ReadStream::with_capacity(capacity.min(max));
-        let buffer = Vec::with_capacity(max);
-        Self { read, buffer, max }
+        let mut buffer = Vec::new();
+        // allocation failures are reported on resume
+        let _ = buffer.try_reserve_exact(max);
+        let tap = None;
+        Self {
+            read,
+            buffer,
+            max,
+            tap,
+            #[cfg(feature = "std")]
+            deadline: None,
+        }
+    }
+
+    /// Limits the amount of bytes read by the coroutine with the
+    /// given shared budget.
+    pub fn with_budget(mut self, budget: ReadBudget) -> Self {
+        self.read = self.read.with_budget(budget);
+        self
+    }
+
+    /// Notifies the given observer of each successful read.
+    pub fn with_observer(mut self, observer: Arc<dyn StreamObserver>) -> Self {
+        self.read = self.read.with_observer(observer);
+        self
+    }
+
+    /// Counts the occurrences of the given byte in each chunk as it
+    /// is read, see [`Self::count`].
+    ///
+    /// For example, counting `b'\n'` gives the amount of lines of
+    /// the payload without a second pass. Bytes given to
+    /// [`Self::extend`] are not counted.
+    pub fn with_count(mut self, byte: u8) -> Self {
+        self.tap = Some((byte, 0));
+        self
+    }
+
+    /// Returns the occurrences of the counted byte read so far.
+    ///
+    /// Requires [`Self::with_count`], otherwise 0 is returned.
+    pub fn count(&self) -> usize {
+        match self.tap {
+            Some((_, count)) => count,
+            None => 0,
+        }
+    }
+
+    /// Aborts the whole operation once the given deadline is
+    /// exceeded, with [`ReadStreamExactError::DeadlineExceeded`].
+    ///
+    /// Unlike runtime timeouts, which cover a single I/O, the
+    /// deadline bounds the latency of the whole operation regardless
+    /// of the runtime driving it. The coroutine stays I/O-free: the
+    /// deadline is only compared with [`Instant::now`] before each
+    /// read request, which requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    ///
+    /// When cancelled, the bytes read so far are kept in the inner
+    /// buffer.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.read = self.read.with_cancel(cancel);
+        self
     }
 
     /// Extends the inner buffer with the given bytes slice.
@@ -73,19 +172,43 @@ impl ReadStreamExact {
         self.buffer.extend(bytes);
     }
 
+    /// Consumes the coroutine and returns the bytes read so far.
+    ///
+    /// This salvages the partial state of an abandoned operation, for
+    /// example when another `tokio::select!` branch wins. Bytes of an
+    /// in-flight read request are owned by the runtime, hence not
+    /// returned. The coroutine is consumed, so it cannot be resumed
+    /// anymore.
+    pub fn into_partial(self) -> Vec<u8> {
+        self.buffer
+    }
+
     /// Makes the coroutine progress.
     pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamExactResult {
         loop {
-            if self.buffer.len() >= self.max {
+            // a response is always given to the inner coroutine, which
+            // rejects it if it does not await one
+            if arg.is_none() && self.buffer.len() >= self.max {
                 let buffer = mem::take(&mut self.buffer);
                 break ReadStreamExactResult::Ok(buffer);
             }
 
-            let remaining = self.max - self.buffer.len();
+            let remaining = self.max.saturating_sub(self.buffer.len());
             debug!("{remaining} remaining bytes to read");
 
+            if let Err(err) = self.buffer.try_reserve_exact(remaining) {
+                break ReadStreamExactResult::Err(ReadStreamExactError::Alloc(self.max, err));
+            }
+
             if remaining < self.read.capacity() {
-                self.read.truncate(remaining);
+                self.read.limit_next_read(remaining);
+            }
+
+            #[cfg(feature = "std")]
+            if arg.is_none() {
+                if let Some(err) = self.check_deadline() {
+                    break ReadStreamExactResult::Err(err);
+                }
             }
 
             let output = match self.read.resume(arg.take()) {
@@ -99,37 +222,283 @@ impl ReadStreamExact {
                 }
             };
 
+            if let Some((byte, count)) = &mut self.tap {
+                *count += memchr::memchr_iter(*byte, output.bytes()).count();
+            }
+
             self.buffer.extend(output.bytes());
             self.read.replace(output.buffer);
         }
     }
+
+    /// Returns the deadline exceeded error, moving out the bytes read
+    /// so far, if the deadline has been exceeded.
+    #[cfg(feature = "std")]
+    fn check_deadline(&mut self) -> Option<ReadStreamExactError> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                let buffer = mem::take(&mut self.buffer);
+                trace!("deadline exceeded after reading {} bytes", buffer.len());
+                Some(ReadStreamExactError::DeadlineExceeded(buffer))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Coroutine for ReadStreamExact {
+    type Output = Vec<u8>;
+    type Error = ReadStreamExactError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamExactResult {
+        ReadStreamExact::resume(self, arg)
+    }
+}
+
+/// Errors that can occur during the [`ReadStreamExactInto`] coroutine
+/// progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamExactIntoError {
+    /// The coroutine unexpectedly reached the End Of File.
+    ///
+    /// Contains the amount of bytes of the target slice filled so
+    /// far, and the length of the target slice.
+    #[error("Unexpected EOF, filled only {0}/{1} bytes")]
+    UnexpectedEof(usize, usize),
+
+    /// Error from the [`Read`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a [`ReadStreamExactInto`] coroutine finishes
+/// its progression.
+pub type ReadStreamExactIntoResult = CoroutineResult<(), ReadStreamExactIntoError>;
+
+/// I/O-free coroutine to read bytes until it completely fills a
+/// caller-provided slice.
+///
+/// Same as [`ReadStreamExact`], except that bytes are copied into the
+/// borrowed target, for example a fixed-size header array on the
+/// stack, instead of an owned buffer.
+#[derive(Debug)]
+pub struct ReadStreamExactInto<'a> {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The target slice to fill.
+    target: &'a mut [u8],
+
+    /// The amount of bytes of the target filled so far.
+    filled: usize,
+}
+
+impl<'a> ReadStreamExactInto<'a> {
+    /// Creates a new coroutine to fill the given slice using a read
+    /// buffer with [`Read::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(target: &'a mut [u8]) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, target)
+    }
+
+    /// Creates a new coroutine to fill the given slice using a read
+    /// buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, target: &'a mut [u8]) -> Self {
+        let len = target.len();
+        trace!("init coroutine to fill {len} bytes (capacity: {capacity})");
+        let read = ReadStream::with_capacity(capacity.min(len));
+        Self {
+            read,
+            target,
+            filled: 0,
+        }
+    }
+
+    /// Limits the amount of bytes read by the coroutine with the
+    /// given shared budget.
+    pub fn with_budget(mut self, budget: ReadBudget) -> Self {
+        self.read = self.read.with_budget(budget);
+        self
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.read = self.read.with_cancel(cancel);
+        self
+    }
+
+    /// Returns the amount of bytes of the target filled so far.
+    pub fn filled(&self) -> usize {
+        self.filled
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamExactIntoResult {
+        loop {
+            let len = self.target.len();
+
+            if arg.is_none() && self.filled >= len {
+                break ReadStreamExactIntoResult::Ok(());
+            }
+
+            let remaining = len.saturating_sub(self.filled);
+            debug!("{remaining} remaining bytes to fill");
+
+            if remaining < self.read.capacity() {
+                self.read.limit_next_read(remaining);
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamExactIntoResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamExactIntoResult::Io(io),
+                ReadStreamResult::Eof => {
+                    let err = ReadStreamExactIntoError::UnexpectedEof(self.filled, len);
+                    break ReadStreamExactIntoResult::Err(err);
+                }
+            };
+
+            let bytes = output.bytes();
+            let n = bytes.len().min(remaining);
+            self.target[self.filled..self.filled + n].copy_from_slice(&bytes[..n]);
+            self.filled += n;
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+impl Coroutine for ReadStreamExactInto<'_> {
+    type Output = ();
+    type Error = ReadStreamExactIntoError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamExactIntoResult {
+        ReadStreamExactInto::resume(self, arg)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::io::{BufReader, Read as _};
+    use std::io::Read as _;
 
     use crate::{
-        coroutines::read_exact::{ReadStreamExactError, ReadStreamExactResult},
-        io::{StreamIo, StreamOutput},
+        coroutines::{
+            cancel::Cancel,
+            read::{ReadStream, ReadStreamError},
+            read_exact::{
+                ReadStreamExactError, ReadStreamExactIntoError, ReadStreamExactIntoResult,
+                ReadStreamExactResult,
+            },
+        },
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::{resume_until_done, ChunkedCursor},
     };
 
-    use super::ReadStreamExact;
+    use super::{ReadStreamExact, ReadStreamExactInto};
 
     #[test]
     fn read_exact_smaller_capacity() {
         let _ = env_logger::try_init();
 
-        let mut reader = BufReader::new("abcdef".as_bytes());
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), [1, 3, 2]);
 
         let mut read = ReadStreamExact::with_capacity(3, 4);
+        let output = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamExactResult::Ok(output) => output,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(output, b"abcd");
+
+        let mut remaining = vec![0; 4];
+        let bytes_count = reader.read(&mut remaining).unwrap();
+
+        assert_eq!(bytes_count, 2);
+        assert_eq!(&remaining[..bytes_count], b"ef");
+    }
+
+    #[test]
+    fn read_exact_bigger_capacity() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamExact::with_capacity(5, 4);
+        let output = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamExactResult::Ok(output) => output,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(output, b"abcd");
+
+        let mut remaining = vec![0; 4];
+        let bytes_count = reader.read(&mut remaining).unwrap();
+
+        assert_eq!(bytes_count, 2);
+        assert_eq!(&remaining[..bytes_count], b"ef");
+    }
+
+    #[test]
+    fn read_exact_0() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamExact::with_capacity(5, 0);
+        read.extend("123".as_bytes().to_vec());
+
+        let output = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamExactResult::Ok(output) => output,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(output, b"123");
+    }
+
+    #[test]
+    fn read_eof() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamExact::new(8);
+        let mut arg = None;
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamExactResult::Err(ReadStreamExactError::UnexpectedEof(2, 8, output)) => {
+                    break assert_eq!(output, b"abcdef");
+                }
+                ReadStreamExactResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn read_eof_moves_buffer() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamExact::with_capacity(4, 8);
+        let ptr = read.buffer.as_ptr();
         let mut arg = None;
 
         let output = loop {
             match read.resume(arg.take()) {
-                ReadStreamExactResult::Ok(output) => break output,
+                ReadStreamExactResult::Err(ReadStreamExactError::UnexpectedEof(_, _, output)) => {
+                    break output
+                }
                 ReadStreamExactResult::Io(StreamIo::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
                     let output = StreamOutput {
                         buffer,
                         bytes_count,
@@ -140,29 +509,130 @@ mod tests {
             }
         };
 
-        assert_eq!(output, b"abcd");
+        assert_eq!(output, b"abcdef");
+        assert_eq!(output.as_ptr(), ptr);
+    }
 
-        let mut remaining = vec![0; 4];
-        let bytes_count = reader.read(&mut remaining).unwrap();
+    #[test]
+    fn read_exact_cancelled() {
+        let _ = env_logger::try_init();
 
-        assert_eq!(bytes_count, 2);
-        assert_eq!(&remaining[..bytes_count], b"ef");
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), []);
+        let cancel = Cancel::new();
+
+        let mut read = ReadStreamExact::with_capacity(2, 6).with_cancel(cancel.clone());
+
+        let io = match read.resume(None) {
+            ReadStreamExactResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                let output = StreamOutput {
+                    buffer,
+                    bytes_count,
+                };
+                StreamIo::Read(Ok(output))
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        cancel.cancel();
+
+        match read.resume(Some(io)) {
+            ReadStreamExactResult::Err(ReadStreamExactError::Read(ReadStreamError::Cancelled)) => {}
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(read.buffer, b"ab");
     }
 
     #[test]
-    fn read_exact_bigger_capacity() {
+    fn read_exact_alloc() {
         let _ = env_logger::try_init();
 
-        let mut reader = BufReader::new("abcdef".as_bytes());
+        // impossibly large on any target
+        let mut read = ReadStreamExact::new(usize::MAX);
 
-        let mut read = ReadStreamExact::with_capacity(5, 4);
+        match read.resume(None) {
+            ReadStreamExactResult::Err(ReadStreamExactError::Alloc(usize::MAX, _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_exact_into_slice() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern([0, 0, 1, 2, b'x'].as_slice(), [1, 3, 2]);
+
+        let mut prefix = [0u8; 4];
+        let mut read = ReadStreamExactInto::with_capacity(3, &mut prefix);
+        let mut arg = None;
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamExactIntoResult::Ok(()) => break,
+                ReadStreamExactIntoResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(u32::from_be_bytes(prefix), 258);
+
+        // the byte following the prefix is left in the reader
+        let mut remaining = [0; 4];
+        assert_eq!(reader.read(&mut remaining).unwrap(), 1);
+        assert_eq!(remaining[0], b'x');
+
+        let mut prefix = [0u8; 4];
+        let mut reader = ChunkedCursor::with_pattern([0, 0].as_slice(), [1, 3, 2]);
+        let mut read = ReadStreamExactInto::new(&mut prefix);
         let mut arg = None;
 
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamExactIntoResult::Err(ReadStreamExactIntoError::UnexpectedEof(2, 4)) => {
+                    break
+                }
+                ReadStreamExactIntoResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn read_exact_keeps_capacity() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdefghij".as_bytes(), []);
+
+        let mut read = ReadStreamExact::with_capacity(4, 10);
+        let mut arg = None;
+        let mut lens = Vec::new();
+
         let output = loop {
             match read.resume(arg.take()) {
                 ReadStreamExactResult::Ok(output) => break output,
                 ReadStreamExactResult::Io(StreamIo::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
+                    // the allocation is kept even for shorter reads
+                    assert!(buffer.capacity() >= 4);
+                    lens.push(init_read_buffer(&mut buffer).len());
+
+                    // simulates partial reads of 3 bytes max
+                    let len = buffer.len().min(3);
+                    let bytes_count = reader.read(&mut buffer[..len]).unwrap();
                     let output = StreamOutput {
                         buffer,
                         bytes_count,
@@ -171,32 +641,36 @@ mod tests {
                 }
                 other => unreachable!("Unexpected result: {other:?}"),
             }
-        };
 
-        assert_eq!(output, b"abcd");
+            assert_eq!(read.read.capacity(), 4);
+        };
 
-        let mut remaining = vec![0; 4];
-        let bytes_count = reader.read(&mut remaining).unwrap();
+        assert_eq!(output, b"abcdefghij");
 
-        assert_eq!(bytes_count, 2);
-        assert_eq!(&remaining[..bytes_count], b"ef");
+        // 10 = 3 + 3 + 3 (limited to 1)
+        assert_eq!(lens, [4, 4, 4, 1]);
     }
 
     #[test]
-    fn read_exact_0() {
+    fn read_exact_full_reads() {
         let _ = env_logger::try_init();
 
-        let mut reader = BufReader::new("abcdef".as_bytes());
-
-        let mut read = ReadStreamExact::with_capacity(5, 0);
-        read.extend("123".as_bytes().to_vec());
+        let input = vec![b'a'; 1024 * 1024];
+        let mut reader = ChunkedCursor::with_pattern(input.as_slice(), []);
 
+        let mut read = ReadStreamExact::new(input.len());
         let mut arg = None;
+        let mut reads = 0;
 
         let output = loop {
             match read.resume(arg.take()) {
                 ReadStreamExactResult::Ok(output) => break output,
                 ReadStreamExactResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    // every request uses the whole read capacity
+                    let len = init_read_buffer(&mut buffer).len();
+                    assert_eq!(len, ReadStream::DEFAULT_CAPACITY);
+                    reads += 1;
+
                     let bytes_count = reader.read(&mut buffer).unwrap();
                     let output = StreamOutput {
                         buffer,
@@ -208,25 +682,24 @@ mod tests {
             }
         };
 
-        assert_eq!(output, b"123");
+        assert_eq!(output, input);
+        assert_eq!(reads, input.len() / ReadStream::DEFAULT_CAPACITY);
     }
 
     #[test]
-    fn read_eof() {
+    fn read_exact_into_partial() {
         let _ = env_logger::try_init();
 
-        let mut reader = BufReader::new("abcdef".as_bytes());
+        let mut reader = ChunkedCursor::with_pattern("abcdefgh".as_bytes(), []);
 
-        let mut read = ReadStreamExact::new(8);
+        let mut read = ReadStreamExact::with_capacity(3, 8);
         let mut arg = None;
 
-        loop {
+        // abandons the read after two reads
+        for _ in 0..2 {
             match read.resume(arg.take()) {
-                ReadStreamExactResult::Err(ReadStreamExactError::UnexpectedEof(2, 8, output)) => {
-                    break assert_eq!(output, b"abcdef");
-                }
                 ReadStreamExactResult::Io(StreamIo::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
                     let output = StreamOutput {
                         buffer,
                         bytes_count,
@@ -236,5 +709,66 @@ mod tests {
                 other => unreachable!("Unexpected result: {other:?}"),
             }
         }
+
+        // the second response has not been processed yet
+        assert_eq!(read.into_partial(), b"abc");
+    }
+
+    #[test]
+    fn read_exact_count() {
+        let _ = env_logger::try_init();
+
+        let payload = "first\nsecond\n\nfourth\nrest";
+        let mut reader = ChunkedCursor::with_pattern(payload.as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamExact::with_capacity(4, payload.len()).with_count(b'\n');
+        let output = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamExactResult::Ok(output) => output,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        let expected = output.iter().filter(|b| **b == b'\n').count();
+        assert_eq!(expected, 4);
+        assert_eq!(read.count(), expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_exact_deadline() {
+        use std::{
+            thread,
+            time::{Duration, Instant},
+        };
+
+        let _ = env_logger::try_init();
+
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let mut read = ReadStreamExact::new(1024).with_deadline(deadline);
+        let mut arg = None;
+        let mut reads = 0;
+
+        // the slow mock never completes the read
+        let bytes = loop {
+            match read.resume(arg.take()) {
+                ReadStreamExactResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    thread::sleep(Duration::from_millis(5));
+                    init_read_buffer(&mut buffer)[0] = b'a';
+                    reads += 1;
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count: 1,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                ReadStreamExactResult::Err(ReadStreamExactError::DeadlineExceeded(bytes)) => {
+                    break bytes
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert!(Instant::now() >= deadline);
+        assert_eq!(bytes.len(), reads);
+        assert!(bytes.iter().all(|byte| *byte == b'a'));
     }
 }
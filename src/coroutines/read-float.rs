@@ -0,0 +1,191 @@
+//! I/O-free coroutine to read an IEEE 754 floating-point number.
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    read_framed::Endianness,
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamFloatError {
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+pub type ReadStreamFloatResult = CoroutineResult<Float, ReadStreamFloatError>;
+
+/// The floating-point number returned by the coroutine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Float {
+    /// Single precision number.
+    F32(f32),
+
+    /// Double precision number.
+    F64(f64),
+}
+
+impl Float {
+    /// Returns the number as double precision, which is lossless.
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Self::F32(n) => f64::from(*n),
+            Self::F64(n) => *n,
+        }
+    }
+}
+
+/// I/O-free coroutine to read an IEEE 754 floating-point number.
+///
+/// The exact amount of bytes of the number is read, then decoded
+/// from its bits using the configured byte order. NaN and infinity
+/// are decoded as is.
+#[derive(Debug)]
+pub struct ReadStreamFloat {
+    /// The inner read exact coroutine.
+    read: ReadStreamExact,
+
+    /// The byte order of the number.
+    endianness: Endianness,
+}
+
+impl ReadStreamFloat {
+    /// Creates a new coroutine to read a single precision, big
+    /// endian number.
+    pub fn f32_be() -> Self {
+        Self::new(4, Endianness::Big)
+    }
+
+    /// Creates a new coroutine to read a single precision, little
+    /// endian number.
+    pub fn f32_le() -> Self {
+        Self::new(4, Endianness::Little)
+    }
+
+    /// Creates a new coroutine to read a double precision, big endian
+    /// number.
+    pub fn f64_be() -> Self {
+        Self::new(8, Endianness::Big)
+    }
+
+    /// Creates a new coroutine to read a double precision, little
+    /// endian number.
+    pub fn f64_le() -> Self {
+        Self::new(8, Endianness::Little)
+    }
+
+    /// Creates a new coroutine to read a number of the given size.
+    fn new(size: usize, endianness: Endianness) -> Self {
+        trace!("init coroutine to read {size}-byte float ({endianness:?} endian)");
+        let read = ReadStreamExact::with_capacity(size, size);
+        Self { read, endianness }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.read.extend(bytes);
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamFloatResult {
+        let bytes = match self.read.resume(arg) {
+            ReadStreamExactResult::Ok(bytes) => bytes,
+            ReadStreamExactResult::Io(io) => return ReadStreamFloatResult::Io(io),
+            ReadStreamExactResult::Err(err) => return ReadStreamFloatResult::Err(err.into()),
+        };
+
+        let float = match (bytes.len(), self.endianness) {
+            (4, Endianness::Big) => Float::F32(f32::from_be_bytes(to_array(&bytes))),
+            (4, Endianness::Little) => Float::F32(f32::from_le_bytes(to_array(&bytes))),
+            (_, Endianness::Big) => Float::F64(f64::from_be_bytes(to_array(&bytes))),
+            (_, Endianness::Little) => Float::F64(f64::from_le_bytes(to_array(&bytes))),
+        };
+
+        debug!("read float {float:?}");
+        ReadStreamFloatResult::Ok(float)
+    }
+}
+
+impl Coroutine for ReadStreamFloat {
+    type Output = Float;
+    type Error = ReadStreamFloatError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamFloatResult {
+        ReadStreamFloat::resume(self, arg)
+    }
+}
+
+/// Copies the first bytes of the given slice into an array.
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(&bytes[..N]);
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_float::{Float, ReadStreamFloatResult},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamFloat;
+
+    fn read(mut read: ReadStreamFloat, input: &[u8]) -> Float {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamFloatResult::Ok(float) => float,
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_f32() {
+        let _ = env_logger::try_init();
+
+        for n in [0.0, -1.5, f32::MIN_POSITIVE, f32::MAX, f32::INFINITY] {
+            assert_eq!(
+                read(ReadStreamFloat::f32_be(), &n.to_be_bytes()),
+                Float::F32(n)
+            );
+            assert_eq!(
+                read(ReadStreamFloat::f32_le(), &n.to_le_bytes()),
+                Float::F32(n)
+            );
+        }
+
+        let nan = read(ReadStreamFloat::f32_le(), &f32::NAN.to_le_bytes());
+        assert!(nan.as_f64().is_nan());
+    }
+
+    #[test]
+    fn read_f64() {
+        let _ = env_logger::try_init();
+
+        for n in [0.0, -0.0, 1234.5678, 1e-300, f64::NEG_INFINITY] {
+            assert_eq!(
+                read(ReadStreamFloat::f64_be(), &n.to_be_bytes()),
+                Float::F64(n)
+            );
+            assert_eq!(
+                read(ReadStreamFloat::f64_le(), &n.to_le_bytes()),
+                Float::F64(n)
+            );
+        }
+
+        // the bits of NaN are decoded as is
+        let nan = f64::from_bits(0x7ff8_0000_dead_beef);
+
+        match read(ReadStreamFloat::f64_be(), &nan.to_be_bytes()) {
+            Float::F64(n) => assert_eq!(n.to_bits(), nan.to_bits()),
+            other => unreachable!("Unexpected float: {other:?}"),
+        }
+    }
+}
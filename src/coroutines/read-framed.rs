@@ -0,0 +1,282 @@
+//! I/O-free coroutine to read a length-prefixed frame.
+
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamFramedError {
+    /// The advertised frame length exceeds the maximum frame size.
+    #[error("Frame of {0} bytes exceeds the maximum frame size")]
+    FrameTooLarge(u64),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    ///
+    /// Reaching the End Of File early leads to
+    /// [`ReadStreamExactError::UnexpectedEof`], which contains the
+    /// partial bytes of the prefix or of the payload.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains the frame payload, without the length prefix.
+pub type ReadStreamFramedResult = CoroutineResult<Vec<u8>, ReadStreamFramedError>;
+
+/// The width of the length prefix.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PrefixWidth {
+    /// 1-byte length prefix.
+    U8,
+
+    /// 2-byte length prefix.
+    U16,
+
+    /// 4-byte length prefix.
+    U32,
+
+    /// 8-byte length prefix.
+    U64,
+}
+
+impl PrefixWidth {
+    /// Returns the amount of bytes of the prefix.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+            Self::U64 => 8,
+        }
+    }
+}
+
+/// The byte order of the length prefix.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Endianness {
+    /// Big endian, also known as network byte order.
+    #[default]
+    Big,
+
+    /// Little endian.
+    Little,
+}
+
+impl Endianness {
+    /// Decodes the given prefix bytes as an unsigned integer.
+    pub fn decode(&self, prefix: &[u8]) -> u64 {
+        let mut bytes = [0; 8];
+
+        match self {
+            Self::Big => {
+                bytes[8 - prefix.len()..].copy_from_slice(prefix);
+                u64::from_be_bytes(bytes)
+            }
+            Self::Little => {
+                bytes[..prefix.len()].copy_from_slice(prefix);
+                u64::from_le_bytes(bytes)
+            }
+        }
+    }
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the length prefix.
+    Prefix(ReadStreamExact),
+
+    /// Reading the payload.
+    Payload(ReadStreamExact),
+}
+
+/// I/O-free coroutine to read a length-prefixed frame.
+///
+/// A frame is made of an unsigned length prefix of the configured
+/// width and byte order, then that many bytes of payload. The byte
+/// order defaults to big endian, and the frame size is not limited by
+/// default, see [`Self::with_max_frame`].
+#[derive(Debug)]
+pub struct ReadStreamFramed {
+    /// The read buffer capacity.
+    capacity: usize,
+
+    /// The byte order of the length prefix.
+    endianness: Endianness,
+
+    /// The maximum frame size, if any.
+    max_frame: Option<u64>,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamFramed {
+    /// Creates a new coroutine to read a frame prefixed by a length
+    /// of the given width, using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(width: PrefixWidth) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, width)
+    }
+
+    /// Creates a new coroutine to read a frame prefixed by a length
+    /// of the given width, using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, width: PrefixWidth) -> Self {
+        trace!("init coroutine to read {width:?}-prefixed frame (capacity: {capacity})");
+        let read = ReadStreamExact::with_capacity(capacity, width.size());
+        Self {
+            capacity,
+            endianness: Endianness::default(),
+            max_frame: None,
+            state: State::Prefix(read),
+        }
+    }
+
+    /// Decodes the length prefix using the given byte order.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Limits the frame size to the given maximum.
+    ///
+    /// Frames advertising a bigger length are rejected before reading
+    /// their payload. Since the payload buffer is allocated upfront,
+    /// a limit should always be set when reading from untrusted peers.
+    pub fn with_max_frame(mut self, max: u64) -> Self {
+        self.max_frame = Some(max);
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamFramedResult {
+        loop {
+            match &mut self.state {
+                State::Prefix(read) => {
+                    let prefix = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(prefix) => prefix,
+                        ReadStreamExactResult::Io(io) => break ReadStreamFramedResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamFramedResult::Err(err.into())
+                        }
+                    };
+
+                    let len = self.endianness.decode(&prefix);
+
+                    let too_large = match self.max_frame {
+                        Some(max) => len > max,
+                        None => false,
+                    };
+
+                    // lengths not fitting in memory are too large too
+                    let size = match usize::try_from(len) {
+                        Ok(size) if !too_large => size,
+                        _ => {
+                            let err = ReadStreamFramedError::FrameTooLarge(len);
+                            break ReadStreamFramedResult::Err(err);
+                        }
+                    };
+
+                    debug!("read frame prefix (length: {len})");
+
+                    // the argument has been consumed by the prefix
+                    // reader, so the payload reader starts fresh
+                    let read = ReadStreamExact::with_capacity(self.capacity, size);
+                    self.state = State::Payload(read);
+                }
+                State::Payload(read) => match read.resume(arg.take()) {
+                    ReadStreamExactResult::Ok(payload) => {
+                        break ReadStreamFramedResult::Ok(payload)
+                    }
+                    ReadStreamExactResult::Io(io) => break ReadStreamFramedResult::Io(io),
+                    ReadStreamExactResult::Err(err) => {
+                        break ReadStreamFramedResult::Err(err.into())
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Coroutine for ReadStreamFramed {
+    type Output = Vec<u8>;
+    type Error = ReadStreamFramedError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamFramedResult {
+        ReadStreamFramed::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_exact::ReadStreamExactError,
+            read_framed::{Endianness, PrefixWidth, ReadStreamFramedError, ReadStreamFramedResult},
+        },
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamFramed;
+
+    fn read(mut read: ReadStreamFramed, input: &[u8]) -> ReadStreamFramedResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_framed_u16_big_endian() {
+        let _ = env_logger::try_init();
+
+        let input = b"\x00\x03abcdef";
+
+        for capacity in [1, 2, 1024] {
+            let framed = ReadStreamFramed::with_capacity(capacity, PrefixWidth::U16);
+
+            match read(framed, input) {
+                ReadStreamFramedResult::Ok(payload) => assert_eq!(payload, b"abc"),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        // the payload is truncated
+        match read(ReadStreamFramed::new(PrefixWidth::U16), &input[..4]) {
+            ReadStreamFramedResult::Err(ReadStreamFramedError::ReadExact(
+                ReadStreamExactError::UnexpectedEof(1, 3, partial),
+            )) => assert_eq!(partial, b"ab"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_framed_little_endian_too_large() {
+        let _ = env_logger::try_init();
+
+        let framed = ReadStreamFramed::new(PrefixWidth::U32).with_endianness(Endianness::Little);
+
+        match read(framed, b"\x03\x00\x00\x00abc") {
+            ReadStreamFramedResult::Ok(payload) => assert_eq!(payload, b"abc"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let framed = ReadStreamFramed::new(PrefixWidth::U64).with_max_frame(1024);
+
+        match read(framed, b"\xff\xff\xff\xff\xff\xff\xff\xffabc") {
+            ReadStreamFramedResult::Err(ReadStreamFramedError::FrameTooLarge(u64::MAX)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
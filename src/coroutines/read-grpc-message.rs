@@ -0,0 +1,251 @@
+//! I/O-free coroutine to read a gRPC length-prefixed message.
+
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamGrpcMessageError {
+    /// The compressed flag is neither 0 nor 1.
+    #[error("Invalid gRPC compressed flag {0}")]
+    InvalidFlag(u8),
+
+    /// The message length exceeds the maximum message size.
+    #[error("gRPC message of {0} bytes exceeds the maximum of {1} bytes")]
+    TooLarge(usize, usize),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    ///
+    /// Reaching the End Of File early leads to
+    /// [`ReadStreamExactError::UnexpectedEof`], which contains the
+    /// partial bytes of the prefix or of the payload.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamGrpcMessageResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok(GrpcMessage),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamGrpcMessageError),
+}
+
+/// The gRPC message returned by the coroutine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GrpcMessage {
+    /// Whether the payload is compressed, using the encoding
+    /// negotiated for the call.
+    pub compressed: bool,
+
+    /// The message payload.
+    pub payload: Vec<u8>,
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the compressed flag and the message length.
+    Prefix(ReadStreamExact),
+
+    /// Reading the message payload.
+    Payload(bool, ReadStreamExact),
+}
+
+/// I/O-free coroutine to read a gRPC length-prefixed message.
+///
+/// A message is made of a 1-byte compressed flag, a 4-byte big endian
+/// length, then that many bytes of payload.
+#[derive(Debug)]
+pub struct ReadStreamGrpcMessage {
+    /// The read buffer capacity.
+    capacity: usize,
+
+    /// The maximum message size.
+    max: usize,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamGrpcMessage {
+    /// The size of the prefix, compressed flag included.
+    pub const PREFIX_SIZE: usize = 5;
+
+    /// The default maximum message size.
+    pub const DEFAULT_MAX_SIZE: usize = 4 * 1024 * 1024;
+
+    /// Creates a new coroutine to read a gRPC message using a buffer
+    /// with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a gRPC message using a buffer
+    /// with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read gRPC message (capacity: {capacity})");
+        let read = ReadStreamExact::with_capacity(capacity, Self::PREFIX_SIZE);
+        Self {
+            capacity,
+            max: Self::DEFAULT_MAX_SIZE,
+            state: State::Prefix(read),
+        }
+    }
+
+    /// Limits the message size to the given maximum.
+    pub fn with_max(mut self, max: usize) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamGrpcMessageResult {
+        loop {
+            match &mut self.state {
+                State::Prefix(read) => {
+                    let prefix = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(prefix) => prefix,
+                        ReadStreamExactResult::Io(io) => break ReadStreamGrpcMessageResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamGrpcMessageResult::Err(err.into())
+                        }
+                    };
+
+                    let compressed = match prefix[0] {
+                        0 => false,
+                        1 => true,
+                        flag => {
+                            let err = ReadStreamGrpcMessageError::InvalidFlag(flag);
+                            break ReadStreamGrpcMessageResult::Err(err);
+                        }
+                    };
+
+                    let len = [prefix[1], prefix[2], prefix[3], prefix[4]];
+                    let len = u32::from_be_bytes(len) as usize;
+
+                    if len > self.max {
+                        let err = ReadStreamGrpcMessageError::TooLarge(len, self.max);
+                        break ReadStreamGrpcMessageResult::Err(err);
+                    }
+
+                    debug!("read gRPC message prefix (compressed: {compressed}, length: {len})");
+                    let read = ReadStreamExact::with_capacity(self.capacity, len);
+                    self.state = State::Payload(compressed, read);
+                }
+                State::Payload(compressed, read) => match read.resume(arg.take()) {
+                    ReadStreamExactResult::Ok(payload) => {
+                        let message = GrpcMessage {
+                            compressed: *compressed,
+                            payload,
+                        };
+                        break ReadStreamGrpcMessageResult::Ok(message);
+                    }
+                    ReadStreamExactResult::Io(io) => break ReadStreamGrpcMessageResult::Io(io),
+                    ReadStreamExactResult::Err(err) => {
+                        break ReadStreamGrpcMessageResult::Err(err.into())
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Default for ReadStreamGrpcMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_exact::ReadStreamExactError,
+            read_grpc_message::{
+                GrpcMessage, ReadStreamGrpcMessageError, ReadStreamGrpcMessageResult,
+            },
+        },
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamGrpcMessage;
+
+    impl_io_result!(ReadStreamGrpcMessageResult);
+
+    fn read(mut read: ReadStreamGrpcMessage, input: &[u8]) -> ReadStreamGrpcMessageResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_uncompressed_message() {
+        let _ = env_logger::try_init();
+
+        let input = b"\x00\x00\x00\x00\x05hello\x00";
+
+        let message = match read(ReadStreamGrpcMessage::with_capacity(3), input) {
+            ReadStreamGrpcMessageResult::Ok(message) => message,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        let expected = GrpcMessage {
+            compressed: false,
+            payload: b"hello".to_vec(),
+        };
+
+        assert_eq!(message, expected);
+
+        // the payload is truncated
+        match read(ReadStreamGrpcMessage::new(), &input[..8]) {
+            ReadStreamGrpcMessageResult::Err(ReadStreamGrpcMessageError::ReadExact(
+                ReadStreamExactError::UnexpectedEof(2, 5, partial),
+            )) => assert_eq!(partial, b"hel"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_compressed_message() {
+        let _ = env_logger::try_init();
+
+        let input = b"\x01\x00\x00\x00\x03\x1f\x8b\x08";
+
+        let message = match read(ReadStreamGrpcMessage::new(), input) {
+            ReadStreamGrpcMessageResult::Ok(message) => message,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert!(message.compressed);
+        assert_eq!(message.payload, b"\x1f\x8b\x08");
+    }
+
+    #[test]
+    fn read_message_too_large() {
+        let _ = env_logger::try_init();
+
+        let input = b"\x00\x00\x00\x01\x00";
+
+        match read(ReadStreamGrpcMessage::new().with_max(255), input) {
+            ReadStreamGrpcMessageResult::Err(ReadStreamGrpcMessageError::TooLarge(256, 255)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
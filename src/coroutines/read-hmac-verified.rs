@@ -0,0 +1,170 @@
+//! I/O-free coroutine to read a payload authenticated by a trailing
+//! HMAC.
+
+use alloc::vec::Vec;
+
+use hmac::{Hmac, Mac};
+use log::{debug, trace};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+};
+
+/// The HMAC-SHA256 type.
+type HmacSha256 = Hmac<Sha256>;
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamHmacVerifiedError {
+    /// The trailing HMAC does not match the payload.
+    #[error("HMAC verification failed")]
+    Auth,
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamHmacVerifiedResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the authenticated payload, without the trailing HMAC.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamHmacVerifiedError),
+}
+
+/// I/O-free coroutine to read a payload authenticated by a trailing
+/// HMAC-SHA256.
+///
+/// The coroutine reads exactly the payload followed by the
+/// [`Self::MAC_LEN`] bytes of the HMAC, computes the HMAC of the
+/// payload with the given key and compares both in constant time.
+#[derive(Debug)]
+pub struct ReadStreamHmacVerified {
+    /// The inner read exact coroutine.
+    read: ReadStreamExact,
+
+    /// The HMAC state, keyed with the given key.
+    mac: HmacSha256,
+
+    /// The length of the payload.
+    len: usize,
+}
+
+impl ReadStreamHmacVerified {
+    /// The length of the HMAC-SHA256 trailer.
+    pub const MAC_LEN: usize = 32;
+
+    /// Creates a new coroutine to read a payload of the given length
+    /// authenticated with the given key, using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(key: impl AsRef<[u8]>, len: usize) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, key, len)
+    }
+
+    /// Creates a new coroutine to read a payload of the given length
+    /// authenticated with the given key, using a buffer with the
+    /// given capacity.
+    pub fn with_capacity(capacity: usize, key: impl AsRef<[u8]>, len: usize) -> Self {
+        trace!("init coroutine to read {len} HMAC-verified bytes (capacity: {capacity})");
+        let mac = HmacSha256::new_from_slice(key.as_ref()).expect("HMAC accepts keys of any size");
+        let read = ReadStreamExact::with_capacity(capacity, len + Self::MAC_LEN);
+        Self { read, mac, len }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamHmacVerifiedResult {
+        let mut payload = match self.read.resume(arg) {
+            ReadStreamExactResult::Ok(bytes) => bytes,
+            ReadStreamExactResult::Io(io) => return ReadStreamHmacVerifiedResult::Io(io),
+            ReadStreamExactResult::Err(err) => {
+                return ReadStreamHmacVerifiedResult::Err(err.into())
+            }
+        };
+
+        let tag = payload.split_off(self.len);
+
+        let mut mac = self.mac.clone();
+        mac.update(&payload);
+
+        // compares in constant time
+        if mac.verify_slice(&tag).is_err() {
+            return ReadStreamHmacVerifiedResult::Err(ReadStreamHmacVerifiedError::Auth);
+        }
+
+        debug!("verified HMAC of {} bytes", payload.len());
+        ReadStreamHmacVerifiedResult::Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_hmac_verified::{
+            ReadStreamHmacVerifiedError, ReadStreamHmacVerifiedResult,
+        },
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamHmacVerified;
+
+    impl_io_result!(ReadStreamHmacVerifiedResult);
+
+    // RFC 4231 test case 1
+    const KEY: [u8; 20] = [0x0b; 20];
+    const MAC: &str = "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7";
+
+    fn input(payload: &[u8]) -> Vec<u8> {
+        let mut input = payload.to_vec();
+
+        for i in (0..MAC.len()).step_by(2) {
+            input.push(u8::from_str_radix(&MAC[i..i + 2], 16).unwrap());
+        }
+
+        input
+    }
+
+    fn read(mut read: ReadStreamHmacVerified, input: &[u8]) -> ReadStreamHmacVerifiedResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_hmac_verified() {
+        let _ = env_logger::try_init();
+
+        let read = ReadStreamHmacVerified::with_capacity(5, KEY, 8);
+
+        match self::read(read, &input(b"Hi There")) {
+            ReadStreamHmacVerifiedResult::Ok(payload) => assert_eq!(payload, b"Hi There"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_hmac_verified_tampered() {
+        let _ = env_logger::try_init();
+
+        let read = ReadStreamHmacVerified::new(KEY, 8);
+
+        match self::read(read, &input(b"Hi there")) {
+            ReadStreamHmacVerifiedResult::Err(ReadStreamHmacVerifiedError::Auth) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
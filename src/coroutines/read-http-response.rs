@@ -0,0 +1,717 @@
+//! I/O-free coroutine to read an HTTP/1.1 response.
+
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::mem;
+
+use log::{debug, trace};
+use memchr::memmem;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    read_to_end::{ReadStreamToEnd, ReadStreamToEndError, ReadStreamToEndResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamHttpResponseError {
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF while reading HTTP response {0}")]
+    UnexpectedEof(&'static str, Vec<u8>),
+
+    /// The response head exceeds the maximum allowed size.
+    #[error("HTTP response head exceeds {0} bytes")]
+    HeadTooLarge(usize),
+
+    /// A chunk size or trailer line exceeds the maximum allowed size.
+    #[error("HTTP chunk line exceeds {0} bytes")]
+    LineTooLarge(usize),
+
+    /// The response body exceeds the maximum allowed size.
+    #[error("HTTP response body exceeds {0} bytes")]
+    BodyTooLarge(usize),
+
+    /// The status line could not be parsed.
+    #[error("Invalid HTTP status line {0:?}")]
+    InvalidStatusLine(String),
+
+    /// A header line could not be parsed.
+    #[error("Invalid HTTP header {0:?}")]
+    InvalidHeader(String),
+
+    /// The `Content-Length` header could not be parsed.
+    #[error("Invalid HTTP Content-Length {0:?}")]
+    InvalidContentLength(String),
+
+    /// A chunk size line could not be parsed.
+    #[error("Invalid HTTP chunk size {0:?}")]
+    InvalidChunkSize(String),
+
+    /// A chunk is not terminated by CRLF.
+    #[error("Invalid HTTP chunk terminator {0:?}")]
+    InvalidChunkTerminator(Vec<u8>),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+
+    /// Error from the [`ReadStreamToEnd`] coroutine.
+    #[error(transparent)]
+    ReadToEnd(#[from] ReadStreamToEndError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamHttpResponseResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok(HttpResponse),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamHttpResponseError),
+}
+
+/// The HTTP response returned by the coroutine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HttpResponse {
+    /// The status code.
+    pub status: u16,
+
+    /// The headers, in order of appearance.
+    ///
+    /// Trailers of a chunked body are appended after the head
+    /// headers.
+    pub headers: Vec<(String, String)>,
+
+    /// The decoded body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Returns the value of the first header matching the given
+    /// name, case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val.as_str())
+    }
+}
+
+/// The chunked body decoding state.
+#[derive(Debug)]
+enum Chunk {
+    /// Expecting a chunk size line.
+    Size,
+
+    /// Expecting the given amount of chunk data bytes.
+    Data(usize),
+
+    /// Expecting the CRLF following chunk data.
+    DataEnd,
+
+    /// Expecting trailer lines until an empty one, with the amount
+    /// of trailer bytes already read.
+    Trailers(usize),
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the status line and the headers.
+    Head,
+
+    /// Reading a chunked body.
+    Chunked(Chunk),
+
+    /// Reading a `Content-Length`-delimited body.
+    Length(ReadStreamExact),
+
+    /// Reading a body until EOF.
+    ToEnd(ReadStreamToEnd),
+}
+
+/// I/O-free coroutine to read an HTTP/1.1 response.
+///
+/// The body framing is detected from the response headers: if
+/// `Transfer-Encoding` contains `chunked` the body is decoded chunk
+/// by chunk, if `Content-Length` is present exactly that amount of
+/// bytes is read, otherwise the body is read until EOF.
+///
+/// The body size is limited to [`Self::DEFAULT_MAX_BODY`] by default,
+/// see [`Self::with_max_body`]. Chunk size and trailer lines are
+/// limited to [`Self::DEFAULT_MAX_LINE`] by default, see
+/// [`Self::with_max_line`]. Trailers are appended to the response
+/// headers. Bytes read past a chunked or
+/// `Content-Length`-delimited body, like a pipelined response, are
+/// kept and can be retrieved with [`Self::take_leftover`].
+#[derive(Debug)]
+pub struct ReadStreamHttpResponse {
+    /// The inner read coroutine, used for the head and chunked body.
+    read: ReadStream,
+
+    /// The buffer containing read bytes not yet parsed.
+    buffer: Vec<u8>,
+
+    /// The maximum size of the response head.
+    max_head: usize,
+
+    /// The maximum size of the response body.
+    max_body: usize,
+
+    /// The maximum size of a chunk size or trailer line.
+    max_line: usize,
+
+    /// The response being built.
+    response: HttpResponse,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamHttpResponse {
+    /// The default maximum size of the response head.
+    pub const DEFAULT_MAX_HEAD: usize = 64 * 1024;
+
+    /// The default maximum size of the response body.
+    pub const DEFAULT_MAX_BODY: usize = 16 * 1024 * 1024;
+
+    /// The default maximum size of a chunk size or trailer line.
+    pub const DEFAULT_MAX_LINE: usize = 8 * 1024;
+
+    /// Creates a new coroutine to read an HTTP response using a
+    /// buffer with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read an HTTP response using a
+    /// buffer with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read HTTP response (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            max_head: Self::DEFAULT_MAX_HEAD,
+            max_body: Self::DEFAULT_MAX_BODY,
+            max_line: Self::DEFAULT_MAX_LINE,
+            response: HttpResponse::default(),
+            state: State::Head,
+        }
+    }
+
+    /// Sets the maximum size of the response head.
+    pub fn with_max_head(mut self, max: usize) -> Self {
+        self.max_head = max;
+        self
+    }
+
+    /// Sets the maximum size of the response body.
+    ///
+    /// The limit is enforced before allocating the body. Chunked and
+    /// `Content-Length`-delimited bodies exceeding it fail with
+    /// [`ReadStreamHttpResponseError::BodyTooLarge`], whereas bodies
+    /// read until EOF fail with
+    /// [`ReadStreamToEndError::LimitExceeded`].
+    pub fn with_max_body(mut self, max: usize) -> Self {
+        self.max_body = max;
+        self
+    }
+
+    /// Sets the maximum size of a chunk size or trailer line,
+    /// CRLF excluded.
+    ///
+    /// Longer lines fail with
+    /// [`ReadStreamHttpResponseError::LineTooLarge`]. The trailers as
+    /// a whole are limited by [`Self::with_max_head`].
+    pub fn with_max_line(mut self, max: usize) -> Self {
+        self.max_line = max;
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the response.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the response.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamHttpResponseResult {
+        loop {
+            let eof = match &mut self.state {
+                State::Head => {
+                    if let Some(n) = memmem::find(&self.buffer, b"\r\n\r\n") {
+                        let mut head: Vec<u8> = self.buffer.drain(..n + 4).collect();
+                        head.truncate(n);
+
+                        if let Err(err) = self.parse_head(&head) {
+                            break ReadStreamHttpResponseResult::Err(err);
+                        }
+
+                        continue;
+                    }
+
+                    if self.buffer.len() > self.max_head {
+                        let err = ReadStreamHttpResponseError::HeadTooLarge(self.max_head);
+                        break ReadStreamHttpResponseResult::Err(err);
+                    }
+
+                    "head"
+                }
+                State::Chunked(chunk) => match chunk {
+                    Chunk::Size => {
+                        if let Some(n) = memmem::find(&self.buffer, b"\r\n") {
+                            if n > self.max_line {
+                                let err = ReadStreamHttpResponseError::LineTooLarge(self.max_line);
+                                break ReadStreamHttpResponseResult::Err(err);
+                            }
+
+                            let line: Vec<u8> = self.buffer.drain(..n + 2).collect();
+                            let line = String::from_utf8_lossy(&line[..n]);
+                            let size = line.split(';').next().unwrap_or_default().trim();
+
+                            let Ok(size) = usize::from_str_radix(size, 16) else {
+                                let err = ReadStreamHttpResponseError::InvalidChunkSize(
+                                    line.into_owned(),
+                                );
+                                break ReadStreamHttpResponseResult::Err(err);
+                            };
+
+                            debug!("read chunk size {size}");
+
+                            if self.response.body.len().saturating_add(size) > self.max_body {
+                                let err = ReadStreamHttpResponseError::BodyTooLarge(self.max_body);
+                                break ReadStreamHttpResponseResult::Err(err);
+                            }
+
+                            *chunk = match size {
+                                0 => Chunk::Trailers(0),
+                                n => Chunk::Data(n),
+                            };
+
+                            continue;
+                        }
+
+                        if self.buffer.len() > self.max_line {
+                            let err = ReadStreamHttpResponseError::LineTooLarge(self.max_line);
+                            break ReadStreamHttpResponseResult::Err(err);
+                        }
+
+                        "chunk size"
+                    }
+                    Chunk::Data(remaining) => {
+                        if !self.buffer.is_empty() {
+                            let n = self.buffer.len().min(*remaining);
+                            self.response.body.extend(self.buffer.drain(..n));
+                            *remaining -= n;
+
+                            if *remaining == 0 {
+                                *chunk = Chunk::DataEnd;
+                            }
+
+                            continue;
+                        }
+
+                        "chunk data"
+                    }
+                    Chunk::DataEnd => {
+                        if self.buffer.len() >= 2 {
+                            let crlf: Vec<u8> = self.buffer.drain(..2).collect();
+
+                            if crlf != b"\r\n" {
+                                let err = ReadStreamHttpResponseError::InvalidChunkTerminator(crlf);
+                                break ReadStreamHttpResponseResult::Err(err);
+                            }
+
+                            *chunk = Chunk::Size;
+                            continue;
+                        }
+
+                        "chunk terminator"
+                    }
+                    Chunk::Trailers(total) => {
+                        if let Some(n) = memmem::find(&self.buffer, b"\r\n") {
+                            if n > self.max_line {
+                                let err = ReadStreamHttpResponseError::LineTooLarge(self.max_line);
+                                break ReadStreamHttpResponseResult::Err(err);
+                            }
+
+                            let line: Vec<u8> = self.buffer.drain(..n + 2).collect();
+
+                            if n == 0 {
+                                let response = mem::take(&mut self.response);
+                                break ReadStreamHttpResponseResult::Ok(response);
+                            }
+
+                            *total += n + 2;
+
+                            if *total > self.max_head {
+                                let err = ReadStreamHttpResponseError::HeadTooLarge(self.max_head);
+                                break ReadStreamHttpResponseResult::Err(err);
+                            }
+
+                            let line = String::from_utf8_lossy(&line[..n]);
+
+                            if let Err(err) = self.parse_header(&line) {
+                                break ReadStreamHttpResponseResult::Err(err);
+                            }
+
+                            continue;
+                        }
+
+                        if self.buffer.len() > self.max_line {
+                            let err = ReadStreamHttpResponseError::LineTooLarge(self.max_line);
+                            break ReadStreamHttpResponseResult::Err(err);
+                        }
+
+                        "chunk trailers"
+                    }
+                },
+                State::Length(read) => match read.resume(arg.take()) {
+                    ReadStreamExactResult::Ok(body) => {
+                        let mut response = mem::take(&mut self.response);
+                        response.body = body;
+                        break ReadStreamHttpResponseResult::Ok(response);
+                    }
+                    ReadStreamExactResult::Io(io) => break ReadStreamHttpResponseResult::Io(io),
+                    ReadStreamExactResult::Err(err) => {
+                        break ReadStreamHttpResponseResult::Err(err.into())
+                    }
+                },
+                State::ToEnd(read) => match read.resume(arg.take()) {
+                    ReadStreamToEndResult::Ok(body) => {
+                        let mut response = mem::take(&mut self.response);
+                        response.body = body;
+                        break ReadStreamHttpResponseResult::Ok(response);
+                    }
+                    ReadStreamToEndResult::Io(io) => break ReadStreamHttpResponseResult::Io(io),
+                    ReadStreamToEndResult::Err(err) => {
+                        break ReadStreamHttpResponseResult::Err(err.into())
+                    }
+                },
+            };
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Io(io) => break ReadStreamHttpResponseResult::Io(io),
+                ReadStreamResult::Err(err) => break ReadStreamHttpResponseResult::Err(err.into()),
+                ReadStreamResult::Eof => {
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamHttpResponseError::UnexpectedEof(eof, buffer);
+                    break ReadStreamHttpResponseResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+
+    /// Parses the status line and headers, then determines the body
+    /// framing.
+    ///
+    /// Bytes read past the head are carried over to the body reader.
+    fn parse_head(&mut self, head: &[u8]) -> Result<(), ReadStreamHttpResponseError> {
+        let head = String::from_utf8_lossy(head);
+        let mut lines = head.split("\r\n");
+
+        let status_line = lines.next().unwrap_or_default();
+        let mut parts = status_line.splitn(3, ' ');
+
+        let status = match (parts.next(), parts.next()) {
+            (Some(version), Some(status)) if version.starts_with("HTTP/") => status.parse().ok(),
+            _ => None,
+        };
+
+        let Some(status) = status else {
+            let line = status_line.to_owned();
+            return Err(ReadStreamHttpResponseError::InvalidStatusLine(line));
+        };
+
+        debug!("read HTTP status {status}");
+        self.response.status = status;
+
+        for line in lines {
+            self.parse_header(line)?;
+        }
+
+        let mut buffer = mem::take(&mut self.buffer);
+
+        // 1xx, 204 and 304 responses never contain a body
+        if (100..200).contains(&status) || status == 204 || status == 304 {
+            self.buffer = buffer;
+            let read = ReadStreamExact::new(0);
+            self.state = State::Length(read);
+            return Ok(());
+        }
+
+        let chunked = self
+            .response
+            .header("Transfer-Encoding")
+            .map(|val| val.to_ascii_lowercase().contains("chunked"))
+            .unwrap_or_default();
+
+        if chunked {
+            trace!("read chunked body");
+            self.buffer = buffer;
+            self.state = State::Chunked(Chunk::Size);
+            return Ok(());
+        }
+
+        if let Some(len) = self.response.header("Content-Length") {
+            let Ok(len) = len.parse::<usize>() else {
+                let len = len.to_owned();
+                return Err(ReadStreamHttpResponseError::InvalidContentLength(len));
+            };
+
+            if len > self.max_body {
+                return Err(ReadStreamHttpResponseError::BodyTooLarge(self.max_body));
+            }
+
+            trace!("read body of {len} bytes");
+            self.buffer = buffer.split_off(buffer.len().min(len));
+            let mut read = ReadStreamExact::with_capacity(self.read.capacity(), len);
+            read.extend(buffer);
+            self.state = State::Length(read);
+            return Ok(());
+        }
+
+        if buffer.len() > self.max_body {
+            return Err(ReadStreamHttpResponseError::BodyTooLarge(self.max_body));
+        }
+
+        trace!("read body until EOF");
+        let mut read = ReadStreamToEnd::with_limit(self.read.capacity(), self.max_body);
+        read.extend(buffer);
+        self.state = State::ToEnd(read);
+        Ok(())
+    }
+
+    /// Parses a header or trailer line and appends it to the
+    /// response headers.
+    fn parse_header(&mut self, line: &str) -> Result<(), ReadStreamHttpResponseError> {
+        let Some((key, val)) = line.split_once(':') else {
+            return Err(ReadStreamHttpResponseError::InvalidHeader(line.to_owned()));
+        };
+
+        let header = (key.trim().to_owned(), val.trim().to_owned());
+        self.response.headers.push(header);
+        Ok(())
+    }
+}
+
+impl Default for ReadStreamHttpResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_http_response::{
+                HttpResponse, ReadStreamHttpResponseError, ReadStreamHttpResponseResult,
+            },
+            read_to_end::ReadStreamToEndError,
+        },
+        io::{StreamIo, StreamOutput},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamHttpResponse;
+
+    impl_io_result!(ReadStreamHttpResponseResult);
+
+    fn read(response: &str, capacity: usize) -> HttpResponse {
+        let mut reader = ChunkedCursor::with_pattern(response.as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamHttpResponse::with_capacity(capacity);
+        match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamHttpResponseResult::Ok(output) => output,
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_content_length() {
+        let _ = env_logger::try_init();
+
+        let response =
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 11\r\n\r\nhello world";
+
+        for capacity in [3, 16, 1024] {
+            let output = read(response, capacity);
+
+            assert_eq!(output.status, 200);
+            assert_eq!(output.header("content-type"), Some("text/plain"));
+            assert_eq!(output.body, b"hello world");
+        }
+    }
+
+    #[test]
+    fn read_chunked() {
+        let _ = env_logger::try_init();
+
+        let response = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nX-Trailer: yes\r\n\r\n";
+
+        for capacity in [3, 16, 1024] {
+            let output = read(response, capacity);
+
+            assert_eq!(output.status, 200);
+            assert_eq!(output.body, b"hello world");
+            assert_eq!(output.header("X-Trailer"), Some("yes"));
+            assert_eq!(output.headers.last().unwrap().0, "X-Trailer");
+        }
+    }
+
+    #[test]
+    fn read_chunk_line_too_large() {
+        let _ = env_logger::try_init();
+
+        let result = |response: &[u8]| {
+            let mut read = ReadStreamHttpResponse::new().with_max_line(8);
+            read.extend(response.iter().copied());
+            read.resume(None)
+        };
+
+        // fails without waiting for the end of the line
+        match result(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n000000000") {
+            ReadStreamHttpResponseResult::Err(ReadStreamHttpResponseError::LineTooLarge(8)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match result(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1;ext=long\r\na\r\n") {
+            ReadStreamHttpResponseResult::Err(ReadStreamHttpResponseError::LineTooLarge(8)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match result(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nX-Trailer: y") {
+            ReadStreamHttpResponseResult::Err(ReadStreamHttpResponseError::LineTooLarge(8)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut read = ReadStreamHttpResponse::new().with_max_head(64);
+        read.extend(*b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n");
+        read.extend(b"X-Trailer: yes\r\n".repeat(8));
+
+        match read.resume(None) {
+            ReadStreamHttpResponseResult::Err(ReadStreamHttpResponseError::HeadTooLarge(64)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_to_eof() {
+        let _ = env_logger::try_init();
+
+        let response = "HTTP/1.0 404 Not Found\r\nConnection: close\r\n\r\nnot found";
+
+        for capacity in [3, 16, 1024] {
+            let output = read(response, capacity);
+
+            assert_eq!(output.status, 404);
+            assert_eq!(output.body, b"not found");
+        }
+    }
+
+    #[test]
+    fn read_no_content() {
+        let _ = env_logger::try_init();
+
+        let response = "HTTP/1.1 204 No Content\r\nServer: test\r\n\r\n";
+        let output = read(response, 1024);
+
+        assert_eq!(output.status, 204);
+        assert_eq!(output.header("Server"), Some("test"));
+        assert!(output.body.is_empty());
+    }
+
+    #[test]
+    fn read_pipelined() {
+        let _ = env_logger::try_init();
+
+        let mut read = ReadStreamHttpResponse::new();
+        read.extend(
+            *b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nokHTTP/1.1 204 No Content\r\n\r\n",
+        );
+
+        match read.resume(None) {
+            ReadStreamHttpResponseResult::Ok(output) => assert_eq!(output.body, b"ok"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(read.leftover(), b"HTTP/1.1 204 No Content\r\n\r\n");
+
+        let mut next = ReadStreamHttpResponse::new();
+        next.extend(read.take_leftover());
+
+        match next.resume(None) {
+            ReadStreamHttpResponseResult::Ok(output) => assert_eq!(output.status, 204),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_body_too_large() {
+        let _ = env_logger::try_init();
+
+        let result = |response: &[u8]| {
+            let mut read = ReadStreamHttpResponse::new().with_max_body(4);
+            read.extend(response.iter().copied());
+            read.resume(None)
+        };
+
+        // fails before allocating the announced body
+        match result(b"HTTP/1.1 200 OK\r\nContent-Length: 99999999999\r\n\r\n") {
+            ReadStreamHttpResponseResult::Err(ReadStreamHttpResponseError::BodyTooLarge(4)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match result(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\n") {
+            ReadStreamHttpResponseResult::Err(ReadStreamHttpResponseError::BodyTooLarge(4)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut read = ReadStreamHttpResponse::with_capacity(8).with_max_body(4);
+        read.extend(*b"HTTP/1.0 200 OK\r\n\r\nab");
+
+        let output = StreamOutput {
+            buffer: b"cde".to_vec(),
+            bytes_count: 3,
+        };
+
+        let result = match read.resume(None) {
+            ReadStreamHttpResponseResult::Io(_) => read.resume(Some(StreamIo::Read(Ok(output)))),
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        match result {
+            ReadStreamHttpResponseResult::Err(ReadStreamHttpResponseError::ReadToEnd(
+                ReadStreamToEndError::LimitExceeded(4, _),
+            )) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
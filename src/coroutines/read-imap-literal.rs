@@ -0,0 +1,265 @@
+//! I/O-free coroutine to read an IMAP literal.
+
+use alloc::{string::String, vec::Vec};
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamImapLiteralError {
+    /// The coroutine unexpectedly reached the End Of File while
+    /// reading the literal specifier.
+    #[error("Unexpected EOF while reading IMAP literal specifier")]
+    UnexpectedEof(Vec<u8>),
+
+    /// The literal specifier could not be parsed.
+    #[error("Invalid IMAP literal specifier {0:?}")]
+    InvalidSpecifier(Vec<u8>),
+
+    /// The literal length exceeds the maximum allowed length.
+    #[error("IMAP literal of {0} bytes exceeds the maximum of {1} bytes")]
+    TooLarge(usize, usize),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamImapLiteralResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamImapLiteralError),
+}
+
+/// I/O-free coroutine to read an IMAP literal.
+///
+/// A literal is made of a `{N}\r\n` specifier line followed by
+/// exactly `N` bytes of data. The non-synchronizing variant `{N+}`
+/// is also supported.
+#[derive(Debug)]
+pub struct ReadStreamImapLiteral {
+    /// The inner read coroutine, used for the specifier line.
+    read: ReadStream,
+
+    /// The inner exact read coroutine, used for the literal data.
+    read_exact: Option<ReadStreamExact>,
+
+    /// The buffer containing read bytes not yet consumed.
+    buffer: Vec<u8>,
+
+    /// The maximum length of the literal data.
+    max: usize,
+
+    /// Whether the specifier was non-synchronizing.
+    non_sync: bool,
+}
+
+impl ReadStreamImapLiteral {
+    /// The maximum length of a specifier line.
+    const MAX_SPECIFIER: usize = 32;
+
+    /// Creates a new coroutine to read an IMAP literal of at most
+    /// `max` bytes, using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(max: usize) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, max)
+    }
+
+    /// Creates a new coroutine to read an IMAP literal of at most
+    /// `max` bytes, using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, max: usize) -> Self {
+        trace!("init coroutine to read IMAP literal (capacity: {capacity}, max: {max})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            read_exact: None,
+            buffer: Vec::new(),
+            max,
+            non_sync: false,
+        }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the literal data.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns `true` if the specifier was non-synchronizing
+    /// (`{N+}`).
+    pub fn is_non_sync(&self) -> bool {
+        self.non_sync
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamImapLiteralResult {
+        loop {
+            if let Some(read) = &mut self.read_exact {
+                break match read.resume(arg.take()) {
+                    ReadStreamExactResult::Ok(data) => ReadStreamImapLiteralResult::Ok(data),
+                    ReadStreamExactResult::Io(io) => ReadStreamImapLiteralResult::Io(io),
+                    ReadStreamExactResult::Err(err) => ReadStreamImapLiteralResult::Err(err.into()),
+                };
+            }
+
+            if let Some(n) = memchr::memchr(b'\n', &self.buffer) {
+                let line: Vec<u8> = self.buffer.drain(..=n).collect();
+
+                let len = match self.parse_specifier(line) {
+                    Ok(len) => len,
+                    Err(err) => break ReadStreamImapLiteralResult::Err(err),
+                };
+
+                debug!("read IMAP literal specifier of {len} bytes");
+
+                let n = len.min(self.buffer.len());
+                let mut read = ReadStreamExact::with_capacity(self.read.capacity(), len);
+                read.extend(self.buffer.drain(..n));
+                self.read_exact = Some(read);
+                continue;
+            }
+
+            if self.buffer.len() > Self::MAX_SPECIFIER {
+                let line = mem::take(&mut self.buffer);
+                let err = ReadStreamImapLiteralError::InvalidSpecifier(line);
+                break ReadStreamImapLiteralResult::Err(err);
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamImapLiteralResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamImapLiteralResult::Io(io),
+                ReadStreamResult::Eof => {
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamImapLiteralError::UnexpectedEof(buffer);
+                    break ReadStreamImapLiteralResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+
+    /// Parses the given `{N}\r\n` or `{N+}\r\n` specifier line.
+    fn parse_specifier(&mut self, line: Vec<u8>) -> Result<usize, ReadStreamImapLiteralError> {
+        let invalid = |line| ReadStreamImapLiteralError::InvalidSpecifier(line);
+
+        let Some(spec) = line.strip_suffix(b"\r\n") else {
+            return Err(invalid(line));
+        };
+
+        let Some(spec) = spec.strip_prefix(b"{").and_then(|s| s.strip_suffix(b"}")) else {
+            return Err(invalid(line));
+        };
+
+        let spec = match spec.strip_suffix(b"+") {
+            Some(spec) => {
+                self.non_sync = true;
+                spec
+            }
+            None => spec,
+        };
+
+        if spec.is_empty() || !spec.iter().all(u8::is_ascii_digit) {
+            return Err(invalid(line));
+        }
+
+        let Ok(len) = String::from_utf8_lossy(spec).parse::<usize>() else {
+            return Err(invalid(line));
+        };
+
+        if len > self.max {
+            return Err(ReadStreamImapLiteralError::TooLarge(len, self.max));
+        }
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_imap_literal::{ReadStreamImapLiteralError, ReadStreamImapLiteralResult},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamImapLiteral;
+
+    impl_io_result!(ReadStreamImapLiteralResult);
+
+    fn read(input: &str, max: usize) -> (ReadStreamImapLiteral, ReadStreamImapLiteralResult) {
+        let mut reader = ChunkedCursor::with_pattern(input.as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamImapLiteral::with_capacity(4, max);
+        let result = resume_until_done(&mut reader, |arg| read.resume(arg));
+
+        (read, result)
+    }
+
+    #[test]
+    fn read_sync_literal() {
+        let _ = env_logger::try_init();
+
+        let (read, result) = read("{11}\r\nhello world)\r\n", 64);
+
+        match result {
+            ReadStreamImapLiteralResult::Ok(data) => assert_eq!(data, b"hello world"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert!(!read.is_non_sync());
+    }
+
+    #[test]
+    fn read_non_sync_literal() {
+        let _ = env_logger::try_init();
+
+        let (read, result) = read("{5+}\r\nhello", 64);
+
+        match result {
+            ReadStreamImapLiteralResult::Ok(data) => assert_eq!(data, b"hello"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert!(read.is_non_sync());
+    }
+
+    #[test]
+    fn read_literal_too_large() {
+        let _ = env_logger::try_init();
+
+        let (_, result) = read("{11}\r\nhello world", 8);
+
+        match result {
+            ReadStreamImapLiteralResult::Err(ReadStreamImapLiteralError::TooLarge(11, 8)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
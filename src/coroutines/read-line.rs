@@ -0,0 +1,224 @@
+//! I/O-free coroutine to read a single UTF-8 line.
+
+use alloc::{string::String, vec::Vec};
+
+use log::trace;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_until::{ReadStreamUntil, ReadStreamUntilError, ReadStreamUntilResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamLineError {
+    /// The line is not valid UTF-8.
+    ///
+    /// Contains the raw line, which can be decoded lossily.
+    #[error("Invalid UTF-8 line")]
+    InvalidUtf8(Vec<u8>),
+
+    /// Error from the [`ReadStreamUntil`] coroutine.
+    #[error(transparent)]
+    ReadUntil(#[from] ReadStreamUntilError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamLineResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok(String),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamLineError),
+}
+
+/// I/O-free coroutine to read a single UTF-8 line.
+///
+/// Lines are terminated by `\n`. By default, the line ending (`\n`
+/// or `\r\n`) is stripped from the returned line, see
+/// [`Self::strip_crlf`].
+///
+/// Bytes read past the line are kept and can be retrieved with
+/// [`Self::take_leftover`].
+#[derive(Debug)]
+pub struct ReadStreamLine {
+    /// The inner read until coroutine.
+    read: ReadStreamUntil,
+
+    /// Whether the line ending should be stripped.
+    strip_crlf: bool,
+}
+
+impl ReadStreamLine {
+    /// Creates a new coroutine to read a line using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a line using a buffer with the
+    /// given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read line (capacity: {capacity})");
+        Self {
+            read: ReadStreamUntil::with_capacity(capacity, b'\n'),
+            strip_crlf: true,
+        }
+    }
+
+    /// Defines whether the line ending should be stripped from the
+    /// returned line.
+    pub fn strip_crlf(mut self, strip: bool) -> Self {
+        self.strip_crlf = strip;
+        self
+    }
+
+    /// Fails with [`ReadStreamUntilError::ScanLimitExceeded`] when
+    /// the line, line ending included, exceeds the given amount of
+    /// bytes.
+    ///
+    /// See [`ReadStreamUntil::with_max_scan`].
+    pub fn with_max_scan(mut self, limit: usize) -> Self {
+        self.read = self.read.with_max_scan(limit);
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.read.extend(bytes);
+    }
+
+    /// Returns the bytes read past the line.
+    pub fn leftover(&self) -> &[u8] {
+        self.read.leftover()
+    }
+
+    /// Takes the bytes read past the line.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        self.read.take_leftover()
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamLineResult {
+        let mut line = match self.read.resume(arg) {
+            ReadStreamUntilResult::Ok(line) => line,
+            ReadStreamUntilResult::Io(io) => return ReadStreamLineResult::Io(io),
+            ReadStreamUntilResult::Err(err) => return ReadStreamLineResult::Err(err.into()),
+        };
+
+        if self.strip_crlf {
+            line.pop();
+
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+
+        match String::from_utf8(line) {
+            Ok(line) => ReadStreamLineResult::Ok(line),
+            Err(err) => {
+                let err = ReadStreamLineError::InvalidUtf8(err.into_bytes());
+                ReadStreamLineResult::Err(err)
+            }
+        }
+    }
+}
+
+impl Default for ReadStreamLine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coroutine for ReadStreamLine {
+    type Output = String;
+    type Error = ReadStreamLineError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error> {
+        match ReadStreamLine::resume(self, arg) {
+            ReadStreamLineResult::Ok(line) => CoroutineResult::Ok(line),
+            ReadStreamLineResult::Io(io) => CoroutineResult::Io(io),
+            ReadStreamLineResult::Err(err) => CoroutineResult::Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_line::{ReadStreamLineError, ReadStreamLineResult},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamLine;
+
+    impl_io_result!(ReadStreamLineResult);
+
+    fn read(mut line: ReadStreamLine, input: &[u8]) -> (ReadStreamLine, ReadStreamLineResult) {
+        let mut reader = ChunkedCursor::with_pattern(input, []);
+        let result = resume_until_done(&mut reader, |arg| line.resume(arg));
+
+        (line, result)
+    }
+
+    #[test]
+    fn read_line_crlf_across_reads() {
+        let _ = env_logger::try_init();
+
+        // the first read ends with \r, the second starts with \n
+        let input = b"* OK\r\n* BYE\r\n";
+
+        let (line, result) = read(ReadStreamLine::with_capacity(5), input);
+
+        match result {
+            ReadStreamLineResult::Ok(line) => assert_eq!(line, "* OK"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(line.leftover(), b"* BY");
+
+        let (_, result) = read(ReadStreamLine::with_capacity(5).strip_crlf(false), input);
+
+        match result {
+            ReadStreamLineResult::Ok(line) => assert_eq!(line, "* OK\r\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_line_bare_lf() {
+        let _ = env_logger::try_init();
+
+        let (_, result) = read(ReadStreamLine::with_capacity(5), b"* OK\n");
+
+        match result {
+            ReadStreamLineResult::Ok(line) => assert_eq!(line, "* OK"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_line_invalid_utf8() {
+        let _ = env_logger::try_init();
+
+        let (_, result) = read(ReadStreamLine::new(), b"caf\xe9\r\n");
+
+        match result {
+            ReadStreamLineResult::Err(ReadStreamLineError::InvalidUtf8(bytes)) => {
+                assert_eq!(String::from_utf8_lossy(&bytes), "caf\u{fffd}")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! I/O-free coroutine to read a count-prefixed list of items.
+
+use alloc::vec::Vec;
+use core::{fmt, mem};
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    read_framed::{Endianness, PrefixWidth},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamListError<E> {
+    /// The advertised count exceeds the maximum count.
+    #[error("List of {0} items exceeds the maximum count")]
+    TooManyItems(u64),
+
+    /// The inner coroutine failed to read the item at the given
+    /// index.
+    #[error("Cannot read list item {0}: {1}")]
+    Item(usize, E),
+
+    /// Error from the [`ReadStreamExact`] coroutine reading the count
+    /// prefix.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains the outputs of the inner coroutines, in order.
+pub type ReadStreamListResult<T, E> = CoroutineResult<Vec<T>, ReadStreamListError<E>>;
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State<C> {
+    /// Reading the count prefix.
+    Count(ReadStreamExact),
+
+    /// Reading the item at the given index.
+    Item(usize, C),
+}
+
+/// I/O-free coroutine to read a count-prefixed list of items.
+///
+/// A list is made of an unsigned count prefix of the configured width
+/// and byte order, then that many items, each one read by a new inner
+/// coroutine built by the given factory. The byte order defaults to
+/// big endian, and the count is limited to
+/// [`Self::DEFAULT_MAX_COUNT`] by default.
+///
+/// The factory receives the bytes read past the previous item, so
+/// that it can seed the next inner coroutine with them, usually with
+/// its `extend` method. Since the bytes read past an item depend on
+/// the inner coroutine, they are only taken when the extractor is
+/// given, see [`Self::with_leftover`]. Inner coroutines reading
+/// exact amounts of bytes never read past their item, so they do not
+/// need any.
+pub struct ReadStreamList<C: Coroutine, F> {
+    /// The factory building the inner coroutines.
+    factory: F,
+
+    /// The function taking the bytes read past an item, if any.
+    leftover: Option<fn(&mut C) -> Vec<u8>>,
+
+    /// The bytes read past the last item.
+    remaining: Vec<u8>,
+
+    /// The byte order of the count prefix.
+    endianness: Endianness,
+
+    /// The maximum count.
+    max_count: u64,
+
+    /// The amount of items to read.
+    count: usize,
+
+    /// The outputs of the inner coroutines.
+    items: Vec<C::Output>,
+
+    /// The current state.
+    state: State<C>,
+}
+
+impl<C, F> ReadStreamList<C, F>
+where
+    C: Coroutine,
+    F: FnMut(Vec<u8>) -> C,
+{
+    /// The default maximum count.
+    pub const DEFAULT_MAX_COUNT: u64 = 1024;
+
+    /// Creates a new coroutine to read a list prefixed by a count of
+    /// the given width, reading each item with a coroutine built by
+    /// the given factory.
+    pub fn new(width: PrefixWidth, factory: F) -> Self {
+        trace!("init coroutine to read {width:?}-prefixed list");
+        Self {
+            factory,
+            leftover: None,
+            remaining: Vec::new(),
+            endianness: Endianness::default(),
+            max_count: Self::DEFAULT_MAX_COUNT,
+            count: 0,
+            items: Vec::new(),
+            state: State::Count(ReadStreamExact::with_capacity(width.size(), width.size())),
+        }
+    }
+
+    /// Decodes the count prefix using the given byte order.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Limits the count to the given maximum.
+    ///
+    /// Lists advertising a bigger count are rejected before reading
+    /// their items.
+    pub fn with_max_count(mut self, max: u64) -> Self {
+        self.max_count = max;
+        self
+    }
+
+    /// Takes the bytes read past each item with the given function,
+    /// in order to give them to the factory building the next inner
+    /// coroutine.
+    pub fn with_leftover(mut self, leftover: fn(&mut C) -> Vec<u8>) -> Self {
+        self.leftover = Some(leftover);
+        self
+    }
+
+    /// Takes the bytes read past the last item.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.remaining)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(
+        &mut self,
+        mut arg: Option<StreamIo>,
+    ) -> ReadStreamListResult<C::Output, C::Error> {
+        loop {
+            match &mut self.state {
+                State::Count(read) => {
+                    let prefix = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(prefix) => prefix,
+                        ReadStreamExactResult::Io(io) => break ReadStreamListResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamListResult::Err(err.into())
+                        }
+                    };
+
+                    let count = self.endianness.decode(&prefix);
+
+                    let count = match usize::try_from(count) {
+                        Ok(n) if count <= self.max_count => n,
+                        _ => {
+                            let err = ReadStreamListError::TooManyItems(count);
+                            break ReadStreamListResult::Err(err);
+                        }
+                    };
+
+                    debug!("read list count prefix (count: {count})");
+                    self.count = count;
+                    self.items = Vec::with_capacity(count);
+                }
+                State::Item(index, item) => {
+                    let output = match item.resume(arg.take()) {
+                        CoroutineResult::Ok(output) => output,
+                        CoroutineResult::Io(io) => break ReadStreamListResult::Io(io),
+                        CoroutineResult::Err(err) => {
+                            let err = ReadStreamListError::Item(*index, err);
+                            break ReadStreamListResult::Err(err);
+                        }
+                    };
+
+                    debug!("read list item {index}");
+                    self.items.push(output);
+
+                    if let Some(leftover) = self.leftover {
+                        self.remaining = leftover(item);
+                    }
+                }
+            }
+
+            if self.items.len() >= self.count {
+                let items = mem::take(&mut self.items);
+                break ReadStreamListResult::Ok(items);
+            }
+
+            let item = (self.factory)(mem::take(&mut self.remaining));
+            self.state = State::Item(self.items.len(), item);
+        }
+    }
+}
+
+impl<C: Coroutine, F> fmt::Debug for ReadStreamList<C, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadStreamList")
+            .field("endianness", &self.endianness)
+            .field("max_count", &self.max_count)
+            .field("count", &self.count)
+            .field("items", &self.items.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, F> Coroutine for ReadStreamList<C, F>
+where
+    C: Coroutine,
+    F: FnMut(Vec<u8>) -> C,
+{
+    type Output = Vec<C::Output>;
+    type Error = ReadStreamListError<C::Error>;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamListResult<C::Output, C::Error> {
+        ReadStreamList::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_exact::ReadStreamExact,
+            read_framed::PrefixWidth,
+            read_line::ReadStreamLine,
+            read_list::{ReadStreamListError, ReadStreamListResult},
+            Coroutine,
+        },
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamList;
+
+    fn read<C, F>(
+        list: &mut ReadStreamList<C, F>,
+        input: &[u8],
+    ) -> ReadStreamListResult<C::Output, C::Error>
+    where
+        C: Coroutine,
+        F: FnMut(Vec<u8>) -> C,
+    {
+        let mut reader = ChunkedCursor::with_pattern(input, []);
+        resume_until_done(&mut reader, |arg| list.resume(arg))
+    }
+
+    #[test]
+    fn read_list() {
+        let _ = env_logger::try_init();
+
+        let mut list =
+            ReadStreamList::new(PrefixWidth::U16, |_| ReadStreamExact::with_capacity(3, 4));
+
+        match read(&mut list, b"\0\x03aaaabbbbcccctail") {
+            ReadStreamListResult::Ok(items) => assert_eq!(items, [b"aaaa", b"bbbb", b"cccc"]),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let list = ReadStreamList::new(PrefixWidth::U8, |_| ReadStreamExact::new(4));
+        let mut list = list.with_max_count(2);
+
+        match read(&mut list, b"\x03aaaabbbbcccc") {
+            ReadStreamListResult::Err(ReadStreamListError::TooManyItems(3)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_list_leftover() {
+        let _ = env_logger::try_init();
+
+        let factory = |leftover| {
+            let mut read = ReadStreamLine::with_capacity(32);
+            read.extend(leftover);
+            read
+        };
+
+        let mut list = ReadStreamList::new(PrefixWidth::U8, factory)
+            .with_leftover(ReadStreamLine::take_leftover);
+
+        // the first line reader reads all the lines at once
+        match read(&mut list, b"\x02first\nsecond\nnext") {
+            ReadStreamListResult::Ok(items) => assert_eq!(items, ["first", "second"]),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(list.take_leftover(), b"next");
+    }
+}
@@ -0,0 +1,171 @@
+//! I/O-free coroutine to read chunks of bytes of at least a given
+//! size.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamMinChunkError {
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamMinChunkResult {
+    /// The coroutine has successfully read a chunk.
+    ///
+    /// The chunk contains at least the minimum size of bytes, unless
+    /// the End Of File has been reached.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// The coroutine reached the End Of File, and all read bytes
+    /// have already been returned.
+    Eof,
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamMinChunkError),
+}
+
+/// I/O-free coroutine to read chunks of bytes of at least a given
+/// size.
+///
+/// The coroutine keeps reading until the accumulated bytes reach the
+/// minimum size, coalescing small reads into larger chunks. Unlike
+/// [`ReadStreamExact`], reaching the End Of File before the minimum
+/// size is not an error: the remaining bytes are returned as a last,
+/// smaller chunk.
+///
+/// Once a chunk is returned, the coroutine can be resumed again to
+/// read the next one.
+///
+/// [`ReadStreamExact`]: super::read_exact::ReadStreamExact
+#[derive(Debug)]
+pub struct ReadStreamMinChunk {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing the accumulated bytes.
+    buffer: Vec<u8>,
+
+    /// The minimum size of a chunk.
+    min_size: usize,
+
+    /// Whether the End Of File has been reached.
+    eof: bool,
+}
+
+impl ReadStreamMinChunk {
+    /// Creates a new coroutine to read chunks of at least `min_size`
+    /// bytes using a buffer with [`ReadStream::DEFAULT_CAPACITY`]
+    /// capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(min_size: usize) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, min_size)
+    }
+
+    /// Creates a new coroutine to read chunks of at least `min_size`
+    /// bytes using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, min_size: usize) -> Self {
+        trace!("init coroutine to read chunks of at least {min_size} bytes (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::with_capacity(min_size),
+            min_size,
+            eof: false,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamMinChunkResult {
+        loop {
+            if arg.is_none() {
+                if self.eof {
+                    break ReadStreamMinChunkResult::Eof;
+                }
+
+                if !self.buffer.is_empty() && self.buffer.len() >= self.min_size {
+                    let chunk = mem::replace(&mut self.buffer, Vec::with_capacity(self.min_size));
+                    break ReadStreamMinChunkResult::Ok(chunk);
+                }
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamMinChunkResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamMinChunkResult::Io(io),
+                ReadStreamResult::Eof => {
+                    self.eof = true;
+
+                    if self.buffer.is_empty() {
+                        break ReadStreamMinChunkResult::Eof;
+                    }
+
+                    debug!("reached EOF with {} remaining bytes", self.buffer.len());
+                    let chunk = mem::take(&mut self.buffer);
+                    break ReadStreamMinChunkResult::Ok(chunk);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use crate::{
+        coroutines::read_min_chunk::ReadStreamMinChunkResult,
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::ChunkedCursor,
+    };
+
+    use super::ReadStreamMinChunk;
+
+    #[test]
+    fn read_min_chunk() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdefghij".as_bytes(), [1, 3, 2]);
+
+        // trickles reads of 1 byte
+        let mut read = ReadStreamMinChunk::with_capacity(1, 4);
+        let mut arg = None;
+        let mut chunks = Vec::new();
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamMinChunkResult::Ok(chunk) => chunks.push(chunk),
+                ReadStreamMinChunkResult::Eof => break,
+                ReadStreamMinChunkResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(chunks, [&b"abcd"[..], b"efgh", b"ij"]);
+    }
+}
@@ -0,0 +1,303 @@
+//! I/O-free coroutine to read an MQTT control packet.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    read_varint::{ReadStreamVarint, ReadStreamVarintError, ReadStreamVarintResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamMqttPacketError {
+    /// The coroutine unexpectedly reached the End Of File before the
+    /// fixed header byte.
+    #[error("Unexpected EOF while reading MQTT fixed header")]
+    UnexpectedEof,
+
+    /// The remaining length exceeds the maximum packet size.
+    #[error("MQTT packet of {0} bytes exceeds the maximum of {1} bytes")]
+    TooLarge(u64, usize),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+
+    /// Error from the [`ReadStreamVarint`] coroutine.
+    #[error(transparent)]
+    ReadVarint(#[from] ReadStreamVarintError),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamMqttPacketResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok(MqttPacket),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamMqttPacketError),
+}
+
+/// The MQTT control packet returned by the coroutine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MqttPacket {
+    /// The control packet type, from the 4 most significant bits of
+    /// the fixed header byte.
+    pub packet_type: u8,
+
+    /// The flags, from the 4 least significant bits of the fixed
+    /// header byte.
+    pub flags: u8,
+
+    /// The packet content following the fixed header (variable
+    /// header and payload).
+    pub payload: Vec<u8>,
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the fixed header byte.
+    Header,
+
+    /// Reading the remaining length.
+    Length(ReadStreamVarint),
+
+    /// Reading the remaining bytes.
+    Payload(ReadStreamExact),
+}
+
+/// I/O-free coroutine to read an MQTT control packet.
+///
+/// A packet is made of a fixed header byte, a remaining length
+/// encoded as a varint on at most 4 bytes, then that many bytes.
+#[derive(Debug)]
+pub struct ReadStreamMqttPacket {
+    /// The inner read coroutine, used for the fixed header byte.
+    read: ReadStream,
+
+    /// The buffer containing read bytes not yet consumed.
+    buffer: Vec<u8>,
+
+    /// The maximum packet size.
+    max: usize,
+
+    /// The packet being built.
+    packet: MqttPacket,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamMqttPacket {
+    /// The maximum amount of bytes the remaining length can be
+    /// encoded on.
+    pub const MAX_LENGTH_BYTES: usize = 4;
+
+    /// The maximum packet size allowed by the protocol.
+    pub const MAX_PACKET_SIZE: usize = 268_435_455;
+
+    /// Creates a new coroutine to read an MQTT packet using a buffer
+    /// with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read an MQTT packet using a buffer
+    /// with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read MQTT packet (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            max: Self::MAX_PACKET_SIZE,
+            packet: MqttPacket::default(),
+            state: State::Header,
+        }
+    }
+
+    /// Limits the packet size to the given maximum.
+    ///
+    /// The limit cannot exceed [`Self::MAX_PACKET_SIZE`].
+    pub fn with_max(mut self, max: usize) -> Self {
+        self.max = max.min(Self::MAX_PACKET_SIZE);
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the packet.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamMqttPacketResult {
+        loop {
+            match &mut self.state {
+                State::Header => {
+                    if !self.buffer.is_empty() {
+                        let byte = self.buffer.remove(0);
+                        self.packet.packet_type = byte >> 4;
+                        self.packet.flags = byte & 0x0f;
+                        debug!("read MQTT packet type {}", self.packet.packet_type);
+
+                        let mut read = ReadStreamVarint::with_capacity(self.read.capacity())
+                            .with_max_bytes(Self::MAX_LENGTH_BYTES);
+                        read.extend(mem::take(&mut self.buffer));
+                        self.state = State::Length(read);
+                        continue;
+                    }
+
+                    let output = match self.read.resume(arg.take()) {
+                        ReadStreamResult::Ok(output) => output,
+                        ReadStreamResult::Err(err) => {
+                            break ReadStreamMqttPacketResult::Err(err.into())
+                        }
+                        ReadStreamResult::Io(io) => break ReadStreamMqttPacketResult::Io(io),
+                        ReadStreamResult::Eof => {
+                            let err = ReadStreamMqttPacketError::UnexpectedEof;
+                            break ReadStreamMqttPacketResult::Err(err);
+                        }
+                    };
+
+                    self.buffer.extend(output.bytes());
+                    self.read.replace(output.buffer);
+                }
+                State::Length(read) => {
+                    let len = match read.resume(arg.take()) {
+                        ReadStreamVarintResult::Ok(len) => len,
+                        ReadStreamVarintResult::Io(io) => break ReadStreamMqttPacketResult::Io(io),
+                        ReadStreamVarintResult::Err(err) => {
+                            break ReadStreamMqttPacketResult::Err(err.into())
+                        }
+                    };
+
+                    let len = match usize::try_from(len) {
+                        Ok(len) if len <= self.max => len,
+                        _ => {
+                            let err = ReadStreamMqttPacketError::TooLarge(len, self.max);
+                            break ReadStreamMqttPacketResult::Err(err);
+                        }
+                    };
+
+                    debug!("read MQTT remaining length {len}");
+
+                    let mut leftover = read.take_leftover();
+                    let n = len.min(leftover.len());
+                    self.buffer = leftover.split_off(n);
+
+                    let mut read = ReadStreamExact::with_capacity(self.read.capacity(), len);
+                    read.extend(leftover);
+                    self.state = State::Payload(read);
+                }
+                State::Payload(read) => match read.resume(arg.take()) {
+                    ReadStreamExactResult::Ok(payload) => {
+                        let mut packet = mem::take(&mut self.packet);
+                        packet.payload = payload;
+                        break ReadStreamMqttPacketResult::Ok(packet);
+                    }
+                    ReadStreamExactResult::Io(io) => break ReadStreamMqttPacketResult::Io(io),
+                    ReadStreamExactResult::Err(err) => {
+                        break ReadStreamMqttPacketResult::Err(err.into())
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Default for ReadStreamMqttPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_mqtt_packet::{
+            MqttPacket, ReadStreamMqttPacketError, ReadStreamMqttPacketResult,
+        },
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamMqttPacket;
+
+    impl_io_result!(ReadStreamMqttPacketResult);
+
+    fn read(mut read: ReadStreamMqttPacket, input: &[u8]) -> ReadStreamMqttPacketResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_small_packet() {
+        let _ = env_logger::try_init();
+
+        // PUBLISH with QoS 1, remaining length 5
+        let input = [0x32, 0x05, b'h', b'e', b'l', b'l', b'o', 0xe0];
+
+        let packet = match read(ReadStreamMqttPacket::with_capacity(3), &input) {
+            ReadStreamMqttPacketResult::Ok(packet) => packet,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        let expected = MqttPacket {
+            packet_type: 3,
+            flags: 2,
+            payload: b"hello".to_vec(),
+        };
+
+        assert_eq!(packet, expected);
+    }
+
+    #[test]
+    fn read_multi_byte_length_packet() {
+        let _ = env_logger::try_init();
+
+        // remaining length 321 encoded as 0xc1 0x02
+        let mut input = vec![0x30, 0xc1, 0x02];
+        input.extend(vec![b'x'; 321]);
+
+        let packet = match read(ReadStreamMqttPacket::with_capacity(16), &input) {
+            ReadStreamMqttPacketResult::Ok(packet) => packet,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(packet.packet_type, 3);
+        assert_eq!(packet.payload, vec![b'x'; 321]);
+    }
+
+    #[test]
+    fn read_packet_too_large() {
+        let _ = env_logger::try_init();
+
+        let input = [0x30, 0xc1, 0x02];
+
+        match read(ReadStreamMqttPacket::new().with_max(128), &input) {
+            ReadStreamMqttPacketResult::Err(ReadStreamMqttPacketError::TooLarge(321, 128)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! I/O-free coroutines to read fixed-width, padded fields.
+
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutines progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamPaddedFieldError {
+    /// The trimmed field is not a valid octal integer.
+    ///
+    /// Contains the trimmed field.
+    #[error("Invalid octal field {0:?}")]
+    InvalidOctal(Vec<u8>),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a [`ReadStreamPaddedField`] coroutine
+/// finishes its progression.
+///
+/// Contains the field content, without the trailing padding.
+pub type ReadStreamPaddedFieldResult = CoroutineResult<Vec<u8>, ReadStreamPaddedFieldError>;
+
+/// Output emitted after a [`ReadStreamOctalField`] coroutine finishes
+/// its progression.
+pub type ReadStreamOctalFieldResult = CoroutineResult<u64, ReadStreamPaddedFieldError>;
+
+/// I/O-free coroutine to read a fixed-width field, then trim its
+/// trailing padding bytes.
+///
+/// Binary formats like tar use such fields, padded with `\0` or
+/// spaces.
+#[derive(Debug)]
+pub struct ReadStreamPaddedField {
+    /// The inner read exact coroutine.
+    read: ReadStreamExact,
+
+    /// The padding byte.
+    pad: u8,
+}
+
+impl ReadStreamPaddedField {
+    /// Creates a new coroutine to read a field of the given width
+    /// padded with the given byte, using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(width: usize, pad: u8) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, width, pad)
+    }
+
+    /// Creates a new coroutine to read a field of the given width
+    /// padded with the given byte, using a buffer with the given
+    /// capacity.
+    pub fn with_capacity(capacity: usize, width: usize, pad: u8) -> Self {
+        trace!("init coroutine to read {width}-byte field padded with {pad:#04x}");
+        let read = ReadStreamExact::with_capacity(capacity, width);
+        Self { read, pad }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.read.extend(bytes);
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamPaddedFieldResult {
+        let mut field = match self.read.resume(arg) {
+            ReadStreamExactResult::Ok(field) => field,
+            ReadStreamExactResult::Io(io) => return ReadStreamPaddedFieldResult::Io(io),
+            ReadStreamExactResult::Err(err) => return ReadStreamPaddedFieldResult::Err(err.into()),
+        };
+
+        let len = trim_padding(&field, &[self.pad]).len();
+        field.truncate(len);
+        debug!("read padded field of {len} bytes");
+        ReadStreamPaddedFieldResult::Ok(field)
+    }
+}
+
+impl Coroutine for ReadStreamPaddedField {
+    type Output = Vec<u8>;
+    type Error = ReadStreamPaddedFieldError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamPaddedFieldResult {
+        ReadStreamPaddedField::resume(self, arg)
+    }
+}
+
+/// I/O-free coroutine to read a fixed-width, octal ASCII field, then
+/// parse it as an unsigned integer.
+///
+/// Leading spaces as well as trailing spaces and `\0` are trimmed
+/// before parsing, as found in tar numeric fields. A field containing
+/// only padding is parsed as 0.
+#[derive(Debug)]
+pub struct ReadStreamOctalField {
+    /// The inner read exact coroutine.
+    read: ReadStreamExact,
+}
+
+impl ReadStreamOctalField {
+    /// Creates a new coroutine to read an octal field of the given
+    /// width, using a buffer with [`ReadStream::DEFAULT_CAPACITY`]
+    /// capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(width: usize) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, width)
+    }
+
+    /// Creates a new coroutine to read an octal field of the given
+    /// width, using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, width: usize) -> Self {
+        trace!("init coroutine to read {width}-byte octal field");
+        let read = ReadStreamExact::with_capacity(capacity, width);
+        Self { read }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.read.extend(bytes);
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamOctalFieldResult {
+        let mut field = match self.read.resume(arg) {
+            ReadStreamExactResult::Ok(field) => field,
+            ReadStreamExactResult::Io(io) => return ReadStreamOctalFieldResult::Io(io),
+            ReadStreamExactResult::Err(err) => return ReadStreamOctalFieldResult::Err(err.into()),
+        };
+
+        let len = trim_padding(&field, &OCTAL_PADS).len();
+        field.truncate(len);
+
+        let Some(n) = parse_octal(&field) else {
+            let err = ReadStreamPaddedFieldError::InvalidOctal(field);
+            return ReadStreamOctalFieldResult::Err(err);
+        };
+
+        debug!("read octal field {n}");
+        ReadStreamOctalFieldResult::Ok(n)
+    }
+}
+
+impl Coroutine for ReadStreamOctalField {
+    type Output = u64;
+    type Error = ReadStreamPaddedFieldError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamOctalFieldResult {
+        ReadStreamOctalField::resume(self, arg)
+    }
+}
+
+/// The padding bytes of octal fields.
+pub(crate) const OCTAL_PADS: [u8; 2] = [0, b' '];
+
+/// Trims the trailing bytes of the given field that are one of the
+/// given padding bytes.
+pub(crate) fn trim_padding<'a>(field: &'a [u8], pads: &[u8]) -> &'a [u8] {
+    let n = field.iter().rev().take_while(|b| pads.contains(b)).count();
+    &field[..field.len() - n]
+}
+
+/// Parses the given octal ASCII field as an unsigned integer.
+///
+/// Leading spaces as well as trailing [`OCTAL_PADS`] are trimmed.
+/// Returns [`None`] on invalid digits or overflow.
+pub(crate) fn parse_octal(field: &[u8]) -> Option<u64> {
+    let field = trim_padding(field, &OCTAL_PADS);
+    let start = field.iter().take_while(|b| **b == b' ').count();
+
+    field[start..]
+        .iter()
+        .try_fold(0u64, |n, digit| match digit {
+            b'0'..=b'7' => n.checked_mul(8)?.checked_add(u64::from(digit - b'0')),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::{
+        coroutines::{read_padded_field::ReadStreamPaddedFieldError, Coroutine, CoroutineResult},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::{ReadStreamOctalField, ReadStreamPaddedField};
+
+    fn read<C: Coroutine>(
+        mut read: C,
+        cursor: &mut ChunkedCursor,
+    ) -> CoroutineResult<C::Output, C::Error> {
+        resume_until_done(cursor, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_null_padded_field() {
+        let _ = env_logger::try_init();
+
+        let mut input = b"hello.txt".to_vec();
+        input.resize(16, 0);
+        input.extend(b"next");
+
+        let mut reader = ChunkedCursor::with_pattern(input.as_slice(), [1, 3, 2]);
+
+        match read(ReadStreamPaddedField::with_capacity(5, 16, 0), &mut reader) {
+            CoroutineResult::Ok(field) => assert_eq!(field, b"hello.txt"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // only the field width is consumed
+        let mut next = Vec::new();
+        reader.read_to_end(&mut next).unwrap();
+        assert_eq!(next, b"next");
+    }
+
+    #[test]
+    fn read_octal_field() {
+        let _ = env_logger::try_init();
+
+        // tar size field of 1234 bytes
+        let mut reader =
+            ChunkedCursor::with_pattern(b"00000002322\0 000644\0".as_slice(), [1, 3, 2]);
+
+        match read(ReadStreamOctalField::new(12), &mut reader) {
+            CoroutineResult::Ok(size) => assert_eq!(size, 1234),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match read(ReadStreamOctalField::new(8), &mut reader) {
+            CoroutineResult::Ok(mode) => assert_eq!(mode, 0o644),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut reader = ChunkedCursor::with_pattern(b"12 9\0".as_slice(), [1, 3, 2]);
+
+        match read(ReadStreamOctalField::new(5), &mut reader) {
+            CoroutineResult::Err(ReadStreamPaddedFieldError::InvalidOctal(field)) => {
+                assert_eq!(field, b"12 9")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
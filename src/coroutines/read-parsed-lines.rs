@@ -0,0 +1,214 @@
+//! I/O-free coroutine to read a given amount of newline-delimited
+//! records, parsing each of them into a typed value.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamParsedLinesError<E> {
+    /// The parser failed to parse the line at the given index.
+    ///
+    /// Contains the index of the line, the raw line and the parser
+    /// error.
+    #[error("Cannot parse line {0}: {2}")]
+    Parse(usize, Vec<u8>, E),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamParsedLinesResult<T, E> {
+    /// The coroutine has successfully terminated its progression.
+    Ok(Vec<T>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamParsedLinesError<E>),
+}
+
+/// I/O-free coroutine to read a given amount of newline-delimited
+/// records, parsing each of them into a typed value.
+///
+/// Lines are delimited by `\n`, and the optional trailing `\r` is
+/// stripped before parsing. The coroutine stops either once the
+/// given amount of lines has been parsed or when reaching EOF.
+#[derive(Debug)]
+pub struct ReadStreamParsedLines<T, F> {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing read bytes not yet parsed.
+    buffer: Vec<u8>,
+
+    /// The parser applied to each line.
+    parser: F,
+
+    /// The parsed lines.
+    lines: Vec<T>,
+
+    /// The amount of lines to read.
+    count: usize,
+}
+
+impl<T, E, F> ReadStreamParsedLines<T, F>
+where
+    F: FnMut(&[u8]) -> Result<T, E>,
+{
+    /// Creates a new coroutine to read the given amount of lines
+    /// using a buffer with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(count: usize, parser: F) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, count, parser)
+    }
+
+    /// Creates a new coroutine to read the given amount of lines
+    /// using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, count: usize, parser: F) -> Self {
+        trace!("init coroutine to read {count} parsed lines (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            parser,
+            lines: Vec::with_capacity(count),
+            count,
+        }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the last parsed line.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamParsedLinesResult<T, E> {
+        loop {
+            if arg.is_none() && self.lines.len() >= self.count {
+                let lines = mem::take(&mut self.lines);
+                break ReadStreamParsedLinesResult::Ok(lines);
+            }
+
+            if let Some(n) = memchr::memchr(b'\n', &self.buffer) {
+                let line: Vec<u8> = self.buffer.drain(..=n).collect();
+
+                if let Err(err) = self.parse(line) {
+                    break ReadStreamParsedLinesResult::Err(err);
+                }
+
+                continue;
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamParsedLinesResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamParsedLinesResult::Io(io),
+                ReadStreamResult::Eof => {
+                    if !self.buffer.is_empty() {
+                        let line = mem::take(&mut self.buffer);
+
+                        if let Err(err) = self.parse(line) {
+                            break ReadStreamParsedLinesResult::Err(err);
+                        }
+                    }
+
+                    debug!("reached EOF after {} lines", self.lines.len());
+                    let lines = mem::take(&mut self.lines);
+                    break ReadStreamParsedLinesResult::Ok(lines);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+
+    /// Strips the line ending of the given line, then parses it.
+    fn parse(&mut self, mut line: Vec<u8>) -> Result<(), ReadStreamParsedLinesError<E>> {
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        match (self.parser)(&line) {
+            Ok(item) => {
+                self.lines.push(item);
+                Ok(())
+            }
+            Err(err) => {
+                let index = self.lines.len();
+                Err(ReadStreamParsedLinesError::Parse(index, line, err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::ParseIntError;
+
+    use crate::{
+        coroutines::read_parsed_lines::{ReadStreamParsedLinesError, ReadStreamParsedLinesResult},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamParsedLines;
+
+    impl_io_result!(ReadStreamParsedLinesResult<T, E>);
+
+    fn parse_i32(line: &[u8]) -> Result<i32, ParseIntError> {
+        String::from_utf8_lossy(line).parse()
+    }
+
+    #[test]
+    fn read_parsed_lines() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("1\r\n-22\n333\n4444\n".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamParsedLines::with_capacity(3, 3, parse_i32);
+        let output = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamParsedLinesResult::Ok(output) => output,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(output, vec![1, -22, 333]);
+    }
+
+    #[test]
+    fn read_parsed_lines_error() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("1\nabc\n3\n".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamParsedLines::new(3, parse_i32);
+
+        match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamParsedLinesResult::Err(ReadStreamParsedLinesError::Parse(1, line, _)) => {
+                assert_eq!(line, b"abc")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
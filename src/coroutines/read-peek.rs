@@ -0,0 +1,178 @@
+//! I/O-free coroutine to peek the first bytes of a stream without
+//! consuming them.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+
+use crate::io::StreamIo;
+
+use super::{
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains a copy of the peeked bytes.
+pub type ReadStreamPeekResult = CoroutineResult<Vec<u8>, ReadStreamError>;
+
+/// I/O-free coroutine to peek the first bytes of a stream without
+/// consuming them.
+///
+/// This suits protocols requiring to inspect the first bytes, like a
+/// TLS record type or an HTTP method, before deciding how to parse
+/// the stream.
+///
+/// The coroutine reads up to the given amount of bytes, never more,
+/// and returns a copy of them while keeping them as leftover. The
+/// leftover can then be taken with [`Self::take_leftover`] to seed the
+/// next coroutine, so that it sees the peeked bytes again:
+///
+/// ```
+/// use io_stream::coroutines::{read_peek::ReadStreamPeek, read_to_end::ReadStreamToEnd};
+///
+/// let mut peek = ReadStreamPeek::new(3);
+/// // … peek the first bytes …
+///
+/// let mut read = ReadStreamToEnd::new();
+/// read.extend(peek.take_leftover());
+/// ```
+#[derive(Debug)]
+pub struct ReadStreamPeek {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The peeked bytes.
+    buffer: Vec<u8>,
+
+    /// The maximum amount of bytes to peek.
+    len: usize,
+}
+
+impl ReadStreamPeek {
+    /// Creates a new coroutine to peek up to the given amount of
+    /// bytes.
+    pub fn new(len: usize) -> Self {
+        trace!("init coroutine to peek {len} bytes");
+        Self {
+            read: ReadStream::with_capacity(len),
+            buffer: Vec::with_capacity(len),
+            len,
+        }
+    }
+
+    /// Returns the peeked bytes.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the peeked bytes, in order to seed the next coroutine
+    /// with them.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    ///
+    /// Reaching the End Of File before peeking the given amount of
+    /// bytes is not an error: the bytes peeked so far are returned.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamPeekResult {
+        loop {
+            if arg.is_none() && self.buffer.len() >= self.len {
+                break ReadStreamPeekResult::Ok(self.buffer.clone());
+            }
+
+            self.read
+                .limit_next_read(self.len.saturating_sub(self.buffer.len()));
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Io(io) => break ReadStreamPeekResult::Io(io),
+                ReadStreamResult::Err(err) => break ReadStreamPeekResult::Err(err),
+                ReadStreamResult::Eof => {
+                    debug!("reached EOF after peeking {} bytes", self.buffer.len());
+                    break ReadStreamPeekResult::Ok(self.buffer.clone());
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+impl Coroutine for ReadStreamPeek {
+    type Output = Vec<u8>;
+    type Error = ReadStreamError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamPeekResult {
+        ReadStreamPeek::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use crate::{
+        coroutines::{
+            read_peek::ReadStreamPeekResult,
+            read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+        },
+        io::{init_read_buffer, StreamIo, StreamOutput},
+    };
+
+    use super::ReadStreamPeek;
+
+    fn read(reader: &mut &[u8], mut buffer: Vec<u8>) -> StreamIo {
+        // simulates partial reads of 2 bytes max
+        let len = init_read_buffer(&mut buffer).len().min(2);
+        let bytes_count = reader.read(&mut buffer[..len]).unwrap();
+        let output = StreamOutput {
+            buffer,
+            bytes_count,
+        };
+        StreamIo::Read(Ok(output))
+    }
+
+    #[test]
+    fn read_peek() {
+        let _ = env_logger::try_init();
+
+        let mut reader = &b"GET / HTTP/1.1"[..];
+
+        let mut peek = ReadStreamPeek::new(3);
+        let mut arg = None;
+
+        let peeked = loop {
+            match peek.resume(arg.take()) {
+                ReadStreamPeekResult::Ok(bytes) => break bytes,
+                ReadStreamPeekResult::Io(StreamIo::Read(Err(buffer))) => {
+                    arg = Some(read(&mut reader, buffer))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(peeked, b"GET");
+        assert_eq!(reader, b" / HTTP/1.1");
+
+        let mut read = ReadStreamToEnd::new();
+        read.extend(peek.take_leftover());
+        let mut arg = None;
+
+        let bytes = loop {
+            match read.resume(arg.take()) {
+                ReadStreamToEndResult::Ok(bytes) => break bytes,
+                ReadStreamToEndResult::Io(StreamIo::Read(Err(buffer))) => {
+                    arg = Some(self::read(&mut reader, buffer))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(bytes, b"GET / HTTP/1.1");
+    }
+}
@@ -0,0 +1,239 @@
+//! I/O-free coroutine to read a Git pkt-line.
+
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamPktLineError {
+    /// The length prefix is not made of 4 hexadecimal digits.
+    #[error("Invalid pkt-line length {0:?}")]
+    InvalidLength([u8; 4]),
+
+    /// The pkt-line length exceeds the maximum pkt-line size.
+    #[error("pkt-line of {0} bytes exceeds the maximum of {max} bytes", max = ReadStreamPktLine::MAX_SIZE)]
+    TooLarge(usize),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    ///
+    /// Reaching the End Of File early leads to
+    /// [`ReadStreamExactError::UnexpectedEof`], which contains the
+    /// partial bytes of the prefix or of the payload.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+pub type ReadStreamPktLineResult = CoroutineResult<PktLine, ReadStreamPktLineError>;
+
+/// The pkt-line returned by the coroutine.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PktLine {
+    /// The `0000` flush packet, ending a message.
+    Flush,
+
+    /// The `0001` delimiter packet, separating sections of a message
+    /// (protocol v2).
+    Delimiter,
+
+    /// The `0002` response end packet, ending a stateless response
+    /// (protocol v2).
+    ResponseEnd,
+
+    /// The payload of a data pkt-line, without the length prefix.
+    Data(Vec<u8>),
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the hexadecimal length prefix.
+    Prefix(ReadStreamExact),
+
+    /// Reading the payload.
+    Payload(ReadStreamExact),
+}
+
+/// I/O-free coroutine to read a Git pkt-line, see [gitprotocol-common].
+///
+/// A pkt-line is made of a 4-digit hexadecimal length, which includes
+/// the 4 bytes of the length itself, then the payload. Lengths below
+/// 4 are reserved to special packets, see [`PktLine`].
+///
+/// [gitprotocol-common]: https://git-scm.com/docs/gitprotocol-common#_pkt_line_format
+#[derive(Debug)]
+pub struct ReadStreamPktLine {
+    /// The read buffer capacity.
+    capacity: usize,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamPktLine {
+    /// The size of the length prefix.
+    pub const PREFIX_SIZE: usize = 4;
+
+    /// The maximum size of a pkt-line, length prefix included.
+    pub const MAX_SIZE: usize = 65520;
+
+    /// Creates a new coroutine to read a pkt-line using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a pkt-line using a buffer with
+    /// the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read pkt-line (capacity: {capacity})");
+        let read = ReadStreamExact::with_capacity(capacity, Self::PREFIX_SIZE);
+        Self {
+            capacity,
+            state: State::Prefix(read),
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamPktLineResult {
+        loop {
+            match &mut self.state {
+                State::Prefix(read) => {
+                    let prefix = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(prefix) => prefix,
+                        ReadStreamExactResult::Io(io) => break ReadStreamPktLineResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamPktLineResult::Err(err.into())
+                        }
+                    };
+
+                    let prefix = [prefix[0], prefix[1], prefix[2], prefix[3]];
+
+                    let len = match decode(prefix) {
+                        Some(0) => break ReadStreamPktLineResult::Ok(PktLine::Flush),
+                        Some(1) => break ReadStreamPktLineResult::Ok(PktLine::Delimiter),
+                        Some(2) => break ReadStreamPktLineResult::Ok(PktLine::ResponseEnd),
+                        Some(len) if len >= Self::PREFIX_SIZE => len,
+                        _ => {
+                            let err = ReadStreamPktLineError::InvalidLength(prefix);
+                            break ReadStreamPktLineResult::Err(err);
+                        }
+                    };
+
+                    if len > Self::MAX_SIZE {
+                        let err = ReadStreamPktLineError::TooLarge(len);
+                        break ReadStreamPktLineResult::Err(err);
+                    }
+
+                    debug!("read pkt-line prefix (length: {len})");
+                    let read =
+                        ReadStreamExact::with_capacity(self.capacity, len - Self::PREFIX_SIZE);
+                    self.state = State::Payload(read);
+                }
+                State::Payload(read) => match read.resume(arg.take()) {
+                    ReadStreamExactResult::Ok(payload) => {
+                        break ReadStreamPktLineResult::Ok(PktLine::Data(payload))
+                    }
+                    ReadStreamExactResult::Io(io) => break ReadStreamPktLineResult::Io(io),
+                    ReadStreamExactResult::Err(err) => {
+                        break ReadStreamPktLineResult::Err(err.into())
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Coroutine for ReadStreamPktLine {
+    type Output = PktLine;
+    type Error = ReadStreamPktLineError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamPktLineResult {
+        ReadStreamPktLine::resume(self, arg)
+    }
+}
+
+impl Default for ReadStreamPktLine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes the given hexadecimal length prefix.
+fn decode(prefix: [u8; 4]) -> Option<usize> {
+    prefix.iter().try_fold(0, |len, byte| {
+        let digit = char::from(*byte).to_digit(16)?;
+        Some(len * 16 + digit as usize)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_pkt_line::{PktLine, ReadStreamPktLineError, ReadStreamPktLineResult},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamPktLine;
+
+    fn read(cursor: &mut ChunkedCursor, capacity: usize) -> ReadStreamPktLineResult {
+        let mut read = ReadStreamPktLine::with_capacity(capacity);
+        resume_until_done(cursor, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_pkt_lines() {
+        let _ = env_logger::try_init();
+
+        let mut reader =
+            ChunkedCursor::with_pattern("0006a\n000bfoobar\n0000".as_bytes(), [1, 3, 2]);
+
+        match read(&mut reader, 3) {
+            ReadStreamPktLineResult::Ok(PktLine::Data(payload)) => assert_eq!(payload, b"a\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match read(&mut reader, 3) {
+            ReadStreamPktLineResult::Ok(PktLine::Data(payload)) => {
+                assert_eq!(payload, b"foobar\n")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match read(&mut reader, 3) {
+            ReadStreamPktLineResult::Ok(PktLine::Flush) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_pkt_line_malformed() {
+        let _ = env_logger::try_init();
+
+        for prefix in ["00x6", "+006", "0003"] {
+            match read(&mut ChunkedCursor::new(prefix, 1), 1024) {
+                ReadStreamPktLineResult::Err(ReadStreamPktLineError::InvalidLength(got)) => {
+                    assert_eq!(&got, prefix.as_bytes())
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        match read(&mut ChunkedCursor::new("fff1", 1), 1024) {
+            ReadStreamPktLineResult::Err(ReadStreamPktLineError::TooLarge(0xfff1)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! I/O-free coroutine to read an array of fixed-size records.
+
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamRecordArrayError {
+    /// The total size of the records overflows `usize`.
+    #[error("Size of {1} records of {0} bytes overflows")]
+    Overflow(usize, usize),
+
+    /// The total size of the records exceeds the maximum size.
+    #[error("Record array of {0} bytes exceeds the maximum of {1} bytes")]
+    TooLarge(usize, usize),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains the records, in reading order.
+pub type ReadStreamRecordArrayResult = CoroutineResult<Vec<Vec<u8>>, ReadStreamRecordArrayError>;
+
+/// I/O-free coroutine to read an array of fixed-size records.
+///
+/// The records are read at once with a single [`ReadStreamExact`],
+/// then split at record boundaries. The total size is validated on
+/// the first resume, before any read.
+#[derive(Debug)]
+pub struct ReadStreamRecordArray {
+    /// The read buffer capacity.
+    capacity: usize,
+
+    /// The size of each record.
+    record_size: usize,
+
+    /// The amount of records.
+    count: usize,
+
+    /// The maximum total size.
+    max: usize,
+
+    /// The inner read exact coroutine, once the total size has been
+    /// validated.
+    read: Option<ReadStreamExact>,
+}
+
+impl ReadStreamRecordArray {
+    /// The default maximum total size.
+    pub const DEFAULT_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+    /// Creates a new coroutine to read the given amount of records of
+    /// the given size using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(record_size: usize, count: usize) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, record_size, count)
+    }
+
+    /// Creates a new coroutine to read the given amount of records of
+    /// the given size using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, record_size: usize, count: usize) -> Self {
+        trace!(
+            "init coroutine to read {count} records of {record_size} bytes (capacity: {capacity})"
+        );
+        Self {
+            capacity,
+            record_size,
+            count,
+            max: Self::DEFAULT_MAX_SIZE,
+            read: None,
+        }
+    }
+
+    /// Limits the total size of the records to the given maximum.
+    pub fn with_max(mut self, max: usize) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamRecordArrayResult {
+        let read = match &mut self.read {
+            Some(read) => read,
+            None => {
+                let total = match self.record_size.checked_mul(self.count) {
+                    Some(total) => total,
+                    None => {
+                        let err =
+                            ReadStreamRecordArrayError::Overflow(self.record_size, self.count);
+                        return ReadStreamRecordArrayResult::Err(err);
+                    }
+                };
+
+                if total > self.max {
+                    let err = ReadStreamRecordArrayError::TooLarge(total, self.max);
+                    return ReadStreamRecordArrayResult::Err(err);
+                }
+
+                self.read
+                    .insert(ReadStreamExact::with_capacity(self.capacity, total))
+            }
+        };
+
+        let bytes = match read.resume(arg) {
+            ReadStreamExactResult::Ok(bytes) => bytes,
+            ReadStreamExactResult::Io(io) => return ReadStreamRecordArrayResult::Io(io),
+            ReadStreamExactResult::Err(err) => return ReadStreamRecordArrayResult::Err(err.into()),
+        };
+
+        let size = self.record_size;
+        let records = (0..self.count)
+            .map(|i| bytes[i * size..(i + 1) * size].to_vec())
+            .collect();
+
+        debug!("read {} records of {size} bytes", self.count);
+        ReadStreamRecordArrayResult::Ok(records)
+    }
+}
+
+impl Coroutine for ReadStreamRecordArray {
+    type Output = Vec<Vec<u8>>;
+    type Error = ReadStreamRecordArrayError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamRecordArrayResult {
+        ReadStreamRecordArray::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_record_array::{ReadStreamRecordArrayError, ReadStreamRecordArrayResult},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamRecordArray;
+
+    fn read(mut read: ReadStreamRecordArray, input: &[u8]) -> ReadStreamRecordArrayResult {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_record_array() {
+        let _ = env_logger::try_init();
+
+        // records straddle the 5-byte reads
+        let read = ReadStreamRecordArray::with_capacity(5, 4, 3);
+
+        match self::read(read, b"aaaabbbbccccdd") {
+            ReadStreamRecordArrayResult::Ok(records) => {
+                assert_eq!(records, [b"aaaa", b"bbbb", b"cccc"]);
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_record_array_too_large() {
+        let _ = env_logger::try_init();
+
+        let read = ReadStreamRecordArray::new(4, 3).with_max(11);
+
+        match self::read(read, b"aaaabbbbcccc") {
+            ReadStreamRecordArrayResult::Err(ReadStreamRecordArrayError::TooLarge(12, 11)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let read = ReadStreamRecordArray::new(usize::MAX, 2);
+
+        match self::read(read, b"") {
+            ReadStreamRecordArrayResult::Err(ReadStreamRecordArrayError::Overflow(_, 2)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
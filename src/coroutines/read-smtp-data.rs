@@ -0,0 +1,237 @@
+//! I/O-free coroutine to read an SMTP DATA message body.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use memchr::memmem;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// The end of data terminator, preceded by the line ending of the
+/// last line of the message.
+const TERMINATOR: &[u8] = b"\r\n.\r\n";
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamSmtpDataError {
+    /// The coroutine reached the End Of File before the end of data
+    /// terminator.
+    ///
+    /// Contains the partial raw bytes read so far.
+    #[error("Unexpected EOF before SMTP end of data")]
+    UnexpectedEof(Vec<u8>),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamSmtpDataResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the unstuffed message body, without the end of data
+    /// terminator.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamSmtpDataError),
+}
+
+/// I/O-free coroutine to read an SMTP DATA message body.
+///
+/// The coroutine reads until the `<CRLF>.<CRLF>` end of data
+/// terminator, then removes the leading dot of lines starting with a
+/// dot (dot-unstuffing), see [RFC 5321 section 4.5.2].
+///
+/// Unstuffing happens once the terminator has been found, so dots
+/// straddling chunk boundaries are correctly handled.
+///
+/// Bytes read past the terminator are kept and can be retrieved with
+/// [`Self::take_leftover`].
+///
+/// [RFC 5321 section 4.5.2]: https://www.rfc-editor.org/rfc/rfc5321#section-4.5.2
+#[derive(Debug)]
+pub struct ReadStreamSmtpData {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing the raw read bytes.
+    buffer: Vec<u8>,
+
+    /// The amount of bytes of the buffer already scanned.
+    scanned: usize,
+}
+
+impl ReadStreamSmtpData {
+    /// Creates a new coroutine to read an SMTP DATA message body
+    /// using a buffer with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read an SMTP DATA message body
+    /// using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read SMTP data (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            scanned: 0,
+        }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the end of data terminator.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the end of data terminator.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        self.scanned = 0;
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamSmtpDataResult {
+        loop {
+            if arg.is_none() {
+                if let Some((body_len, end)) = self.find_end_of_data() {
+                    let leftover = self.buffer.split_off(end);
+                    let mut raw = mem::replace(&mut self.buffer, leftover);
+                    raw.truncate(body_len);
+                    self.scanned = 0;
+                    debug!("read SMTP data of {body_len} raw bytes");
+                    break ReadStreamSmtpDataResult::Ok(unstuff(&raw));
+                }
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamSmtpDataResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamSmtpDataResult::Io(io),
+                ReadStreamResult::Eof => {
+                    self.scanned = 0;
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamSmtpDataError::UnexpectedEof(buffer);
+                    break ReadStreamSmtpDataResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+
+    /// Finds the end of data terminator in the inner buffer.
+    ///
+    /// Returns the length of the raw body, last line ending included,
+    /// and the position right after the terminator.
+    fn find_end_of_data(&mut self) -> Option<(usize, usize)> {
+        // an empty body only consists of the dot line
+        if self.buffer.starts_with(&TERMINATOR[2..]) {
+            return Some((0, TERMINATOR.len() - 2));
+        }
+
+        // the terminator may straddle the previously scanned bytes
+        let start = self.scanned.saturating_sub(TERMINATOR.len() - 1);
+        self.scanned = self.buffer.len();
+
+        let i = start + memmem::find(&self.buffer[start..], TERMINATOR)?;
+        Some((i + 2, i + TERMINATOR.len()))
+    }
+}
+
+impl Default for ReadStreamSmtpData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Removes the leading dot of lines starting with a dot.
+fn unstuff(raw: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(raw.len());
+    let mut line_start = true;
+
+    for &byte in raw {
+        if line_start && byte == b'.' {
+            line_start = false;
+            continue;
+        }
+
+        line_start = byte == b'\n';
+        body.push(byte);
+    }
+
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_smtp_data::ReadStreamSmtpDataResult,
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamSmtpData;
+
+    impl_io_result!(ReadStreamSmtpDataResult);
+
+    fn read(mut data: ReadStreamSmtpData, input: &[u8]) -> (ReadStreamSmtpData, Vec<u8>) {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        let body = match resume_until_done(&mut reader, |arg| data.resume(arg)) {
+            ReadStreamSmtpDataResult::Ok(body) => body,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        (data, body)
+    }
+
+    #[test]
+    fn read_smtp_data_dot_stuffed() {
+        let _ = env_logger::try_init();
+
+        let input = b"Subject: dots\r\n\r\n..leading dot\r\n...\r\nend.\r\n.\r\nQUIT\r\n";
+
+        // a capacity of 2 makes the stuffing dots straddle reads
+        let (data, body) = read(ReadStreamSmtpData::with_capacity(2), input);
+
+        assert_eq!(body, b"Subject: dots\r\n\r\n.leading dot\r\n..\r\nend.\r\n");
+        assert!(data.leftover().is_empty());
+    }
+
+    #[test]
+    fn read_smtp_data_split_terminator() {
+        let _ = env_logger::try_init();
+
+        let input = b"hello\r\n.\r\nQUIT\r\n";
+
+        for capacity in 1..input.len() {
+            let (mut data, body) = read(ReadStreamSmtpData::with_capacity(capacity), input);
+            assert_eq!(body, b"hello\r\n", "capacity {capacity}");
+
+            let mut rest = data.take_leftover();
+            rest.extend(&input[rest.len() + 10..]);
+            assert_eq!(rest, b"QUIT\r\n", "capacity {capacity}");
+        }
+
+        let (_, body) = read(ReadStreamSmtpData::new(), b".\r\n");
+        assert!(body.is_empty());
+    }
+}
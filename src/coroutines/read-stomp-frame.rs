@@ -0,0 +1,477 @@
+//! I/O-free coroutine to read a STOMP frame.
+
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    read_line::{ReadStreamLine, ReadStreamLineError, ReadStreamLineResult},
+    read_until::{ReadStreamUntil, ReadStreamUntilError, ReadStreamUntilResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamStompFrameError {
+    /// A header line could not be parsed.
+    #[error("Invalid STOMP header {0:?}")]
+    InvalidHeader(String),
+
+    /// The `content-length` header could not be parsed.
+    #[error("Invalid STOMP content-length {0:?}")]
+    InvalidContentLength(String),
+
+    /// The `content-length` exceeds the maximum body size.
+    #[error("STOMP body of {0} bytes exceeds the maximum of {1} bytes")]
+    TooLarge(usize, usize),
+
+    /// The `content-length`-delimited body is not followed by a NULL
+    /// byte.
+    #[error("Invalid STOMP frame terminator {0:#04x}")]
+    InvalidTerminator(u8),
+
+    /// Error from the [`ReadStreamLine`] coroutine.
+    #[error(transparent)]
+    ReadLine(#[from] ReadStreamLineError),
+
+    /// Error from the [`ReadStreamUntil`] coroutine.
+    #[error(transparent)]
+    ReadUntil(#[from] ReadStreamUntilError),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamStompFrameResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok(StompFrame),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamStompFrameError),
+}
+
+/// The STOMP frame returned by the coroutine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StompFrame {
+    /// The command, for example `MESSAGE`.
+    pub command: String,
+
+    /// The decoded headers, in order of appearance.
+    pub headers: Vec<(String, String)>,
+
+    /// The body, without the NULL terminator.
+    pub body: Vec<u8>,
+}
+
+impl StompFrame {
+    /// Returns the value of the first header matching the given
+    /// name.
+    ///
+    /// Header names are case-sensitive, and only the first occurrence
+    /// of a repeated header is used, as defined by the specification.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, val)| val.as_str())
+    }
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the command line, skipping heart-beats.
+    Command,
+
+    /// Reading header lines until an empty one.
+    Headers,
+
+    /// Reading a body until the NULL byte.
+    Null(ReadStreamUntil),
+
+    /// Reading a `content-length`-delimited body and its NULL byte.
+    Length(ReadStreamExact),
+}
+
+/// I/O-free coroutine to read a STOMP 1.2 frame, see the [STOMP
+/// specification].
+///
+/// The command line is read first, then header lines until an empty
+/// one. The body is read until the NULL byte, or exactly
+/// `content-length` bytes followed by the NULL byte if that header is
+/// present. Empty lines preceding the command are heart-beats and
+/// are skipped.
+///
+/// Header values are unescaped, except for `CONNECT` and `CONNECTED`
+/// frames. Bytes read past the frame are kept and can be retrieved
+/// with [`Self::take_leftover`].
+///
+/// Bodies are limited to [`Self::DEFAULT_MAX_BODY`] bytes by default,
+/// see [`Self::with_max_body`].
+///
+/// [STOMP specification]: https://stomp.github.io/stomp-specification-1.2.html
+#[derive(Debug)]
+pub struct ReadStreamStompFrame {
+    /// The inner read line coroutine, used for the command and the
+    /// headers.
+    read: ReadStreamLine,
+
+    /// The buffer capacity, used for body readers.
+    capacity: usize,
+
+    /// The maximum body size.
+    max_body: usize,
+
+    /// The frame being built.
+    frame: StompFrame,
+
+    /// The bytes read past the frame.
+    leftover: Vec<u8>,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamStompFrame {
+    /// The default maximum body size.
+    pub const DEFAULT_MAX_BODY: usize = 1024 * 1024;
+
+    /// Creates a new coroutine to read a STOMP frame using a buffer
+    /// with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a STOMP frame using a buffer
+    /// with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read STOMP frame (capacity: {capacity})");
+        Self {
+            read: ReadStreamLine::with_capacity(capacity),
+            capacity,
+            max_body: Self::DEFAULT_MAX_BODY,
+            frame: StompFrame::default(),
+            leftover: Vec::new(),
+            state: State::Command,
+        }
+    }
+
+    /// Limits the body size to the given maximum, NULL terminator
+    /// excluded.
+    ///
+    /// A `content-length` exceeding the maximum fails with
+    /// [`ReadStreamStompFrameError::TooLarge`], whereas a body read
+    /// until the NULL byte fails with
+    /// [`ReadStreamUntilError::ScanLimitExceeded`].
+    pub fn with_max_body(mut self, max: usize) -> Self {
+        self.max_body = max;
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.read.extend(bytes);
+    }
+
+    /// Returns the bytes read past the frame.
+    pub fn leftover(&self) -> &[u8] {
+        &self.leftover
+    }
+
+    /// Takes the bytes read past the frame.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.leftover)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamStompFrameResult {
+        loop {
+            match &mut self.state {
+                State::Command => {
+                    let line = match self.read.resume(arg.take()) {
+                        ReadStreamLineResult::Ok(line) => line,
+                        ReadStreamLineResult::Io(io) => break ReadStreamStompFrameResult::Io(io),
+                        ReadStreamLineResult::Err(err) => {
+                            break ReadStreamStompFrameResult::Err(err.into())
+                        }
+                    };
+
+                    if line.is_empty() {
+                        trace!("skip STOMP heart-beat");
+                        continue;
+                    }
+
+                    debug!("read STOMP {line} frame");
+                    self.frame.command = line;
+                    self.state = State::Headers;
+                }
+                State::Headers => {
+                    let line = match self.read.resume(arg.take()) {
+                        ReadStreamLineResult::Ok(line) => line,
+                        ReadStreamLineResult::Io(io) => break ReadStreamStompFrameResult::Io(io),
+                        ReadStreamLineResult::Err(err) => {
+                            break ReadStreamStompFrameResult::Err(err.into())
+                        }
+                    };
+
+                    let result = if line.is_empty() {
+                        self.start_body()
+                    } else {
+                        self.parse_header(&line)
+                    };
+
+                    if let Err(err) = result {
+                        break ReadStreamStompFrameResult::Err(err);
+                    }
+                }
+                State::Null(read) => {
+                    let mut body = match read.resume(arg.take()) {
+                        ReadStreamUntilResult::Ok(body) => body,
+                        ReadStreamUntilResult::Io(io) => break ReadStreamStompFrameResult::Io(io),
+                        ReadStreamUntilResult::Err(err) => {
+                            break ReadStreamStompFrameResult::Err(err.into())
+                        }
+                    };
+
+                    body.pop();
+                    self.leftover = read.take_leftover();
+                    break self.finish(body);
+                }
+                State::Length(read) => {
+                    let mut body = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(body) => body,
+                        ReadStreamExactResult::Io(io) => break ReadStreamStompFrameResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamStompFrameResult::Err(err.into())
+                        }
+                    };
+
+                    match body.pop() {
+                        Some(0) => break self.finish(body),
+                        byte => {
+                            let err =
+                                ReadStreamStompFrameError::InvalidTerminator(byte.unwrap_or(0));
+                            break ReadStreamStompFrameResult::Err(err);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parses the given header line, then adds it to the frame.
+    fn parse_header(&mut self, line: &str) -> Result<(), ReadStreamStompFrameError> {
+        let Some((key, val)) = line.split_once(':') else {
+            return Err(ReadStreamStompFrameError::InvalidHeader(line.to_owned()));
+        };
+
+        // CONNECT and CONNECTED frames do not escape headers, for
+        // backward compatibility with STOMP 1.0
+        let header = match self.frame.command.as_str() {
+            "CONNECT" | "CONNECTED" => (key.to_owned(), val.to_owned()),
+            _ => match (unescape(key), unescape(val)) {
+                (Some(key), Some(val)) => (key, val),
+                _ => return Err(ReadStreamStompFrameError::InvalidHeader(line.to_owned())),
+            },
+        };
+
+        self.frame.headers.push(header);
+        Ok(())
+    }
+
+    /// Determines the body framing from the headers.
+    ///
+    /// Bytes read past the headers are carried over to the body
+    /// reader.
+    fn start_body(&mut self) -> Result<(), ReadStreamStompFrameError> {
+        let mut buffer = self.read.take_leftover();
+
+        let Some(len) = self.frame.header("content-length") else {
+            trace!("read STOMP body until NULL byte");
+            let max_scan = self.max_body.saturating_add(1);
+            let mut read = ReadStreamUntil::with_capacity(self.capacity, 0).with_max_scan(max_scan);
+            read.extend(buffer);
+            self.state = State::Null(read);
+            return Ok(());
+        };
+
+        // the body is followed by the NULL byte
+        let Some((len, size)) = len
+            .parse::<usize>()
+            .ok()
+            .and_then(|len| Some((len, len.checked_add(1)?)))
+        else {
+            let len = len.to_owned();
+            return Err(ReadStreamStompFrameError::InvalidContentLength(len));
+        };
+
+        if len > self.max_body {
+            return Err(ReadStreamStompFrameError::TooLarge(len, self.max_body));
+        }
+
+        trace!("read STOMP body of {len} bytes");
+        self.leftover = buffer.split_off(buffer.len().min(size));
+
+        let mut read = ReadStreamExact::with_capacity(self.capacity, size);
+        read.extend(buffer);
+        self.state = State::Length(read);
+        Ok(())
+    }
+
+    /// Returns the frame with the given body, and resets the state.
+    fn finish(&mut self, body: Vec<u8>) -> ReadStreamStompFrameResult {
+        let mut frame = mem::take(&mut self.frame);
+        frame.body = body;
+        self.state = State::Command;
+        debug!("read STOMP frame body of {} bytes", frame.body.len());
+        ReadStreamStompFrameResult::Ok(frame)
+    }
+}
+
+impl Default for ReadStreamStompFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes the escape sequences of the given header name or value.
+///
+/// Returns [`None`] on undefined escape sequences, which are fatal
+/// protocol errors.
+fn unescape(escaped: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        let c = match chars.next()? {
+            'r' => '\r',
+            'n' => '\n',
+            'c' => ':',
+            '\\' => '\\',
+            _ => return None,
+        };
+
+        unescaped.push(c);
+    }
+
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_stomp_frame::{ReadStreamStompFrameError, ReadStreamStompFrameResult, StompFrame},
+            read_until::ReadStreamUntilError,
+        },
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamStompFrame;
+
+    impl_io_result!(ReadStreamStompFrameResult);
+
+    fn read(input: &[u8], capacity: usize) -> (StompFrame, Vec<u8>) {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+
+        let mut read = ReadStreamStompFrame::with_capacity(capacity);
+        let frame = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamStompFrameResult::Ok(frame) => frame,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        (frame, read.take_leftover())
+    }
+
+    #[test]
+    fn read_stomp_frame_content_length() {
+        let _ = env_logger::try_init();
+
+        // the body contains a NULL byte, which is allowed with
+        // content-length
+        let input = b"\nMESSAGE\r\ndestination:/queue/a\r\ncontent-length:5\r\n\r\nab\0cd\0\nSEND";
+
+        for capacity in [3, 16, 1024] {
+            let (frame, leftover) = read(input, capacity);
+
+            assert_eq!(frame.command, "MESSAGE");
+            assert_eq!(frame.header("destination"), Some("/queue/a"));
+            assert_eq!(frame.body, b"ab\0cd");
+            assert!(b"\nSEND".starts_with(&leftover));
+        }
+    }
+
+    #[test]
+    fn read_stomp_frame_null_terminated() {
+        let _ = env_logger::try_init();
+
+        let input = b"SEND\nfoo:a\\cb\nfoo:ignored\n\nhello world\0\n";
+
+        for capacity in [3, 16, 1024] {
+            let (frame, leftover) = read(input, capacity);
+
+            assert_eq!(frame.command, "SEND");
+            assert_eq!(frame.header("foo"), Some("a:b"));
+            assert_eq!(frame.headers.len(), 2);
+            assert_eq!(frame.body, b"hello world");
+            assert!(b"\n".starts_with(&leftover));
+        }
+    }
+
+    #[test]
+    fn read_stomp_frame_too_large() {
+        let _ = env_logger::try_init();
+
+        let result = |input: &[u8], max: usize| {
+            let mut read = ReadStreamStompFrame::new().with_max_body(max);
+            read.extend(input.iter().copied());
+            read.resume(None)
+        };
+
+        // the length overflows once the NULL byte is counted
+        let input = b"SEND\ncontent-length:18446744073709551615\n\n";
+
+        match result(input, usize::MAX) {
+            ReadStreamStompFrameResult::Err(ReadStreamStompFrameError::InvalidContentLength(
+                len,
+            )) => assert_eq!(len, "18446744073709551615"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match result(b"SEND\ncontent-length:6\n\nhello!\0", 5) {
+            ReadStreamStompFrameResult::Err(ReadStreamStompFrameError::TooLarge(6, 5)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match result(b"SEND\n\nhello\0", 5) {
+            ReadStreamStompFrameResult::Ok(frame) => assert_eq!(frame.body, b"hello"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match result(b"SEND\n\nhello!\0", 5) {
+            ReadStreamStompFrameResult::Err(ReadStreamStompFrameError::ReadUntil(
+                ReadStreamUntilError::ScanLimitExceeded(6, _),
+            )) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
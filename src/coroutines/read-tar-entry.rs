@@ -0,0 +1,405 @@
+//! I/O-free coroutine to read a tar archive entry.
+
+use alloc::{string::String, vec::Vec};
+use core::{mem, ops::Range};
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+    read_padded_field::{parse_octal, trim_padding},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamTarEntryError {
+    /// A numeric header field is not a valid octal integer.
+    #[error("Invalid tar header {0} field")]
+    InvalidField(&'static str),
+
+    /// The header checksum does not match the header bytes.
+    #[error("Invalid tar header checksum: expected {0}, got {1}")]
+    InvalidChecksum(u64, u64),
+
+    /// An all-zero block is not followed by a second one.
+    #[error("Invalid tar end of archive: expected a second zero block")]
+    InvalidEndOfArchive,
+
+    /// The entry size exceeds the maximum allowed size.
+    #[error("Tar entry of {0} bytes exceeds the maximum of {1} bytes")]
+    TooLarge(u64, usize),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains the entry header and its data, or [`None`] once the end
+/// of the archive is reached.
+pub type ReadStreamTarEntryResult =
+    CoroutineResult<Option<(TarHeader, Vec<u8>)>, ReadStreamTarEntryError>;
+
+/// The relevant fields of a tar header.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TarHeader {
+    /// The entry name, prefixed by the ustar prefix field if any.
+    pub name: String,
+
+    /// The file mode.
+    pub mode: u64,
+
+    /// The size of the entry data.
+    pub size: u64,
+
+    /// The entry type, for example `b'0'` for regular files and
+    /// `b'5'` for directories.
+    pub typeflag: u8,
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State {
+    /// Reading the header block.
+    Header(ReadStreamExact),
+
+    /// Reading the second all-zero block of the end of archive.
+    End(ReadStreamExact),
+
+    /// Reading the entry data, padded to the block size.
+    Data(TarHeader, ReadStreamExact),
+}
+
+/// I/O-free coroutine to read a tar archive entry, see the [ustar
+/// format].
+///
+/// The 512-byte header block is read first and its relevant fields
+/// are parsed, then the entry data is read up to the next block
+/// boundary and the padding is discarded. Two consecutive all-zero
+/// blocks mark the end of the archive.
+///
+/// The data of the entry is kept in memory, its size is limited to
+/// [`Self::DEFAULT_MAX_SIZE`] by default, see [`Self::with_max_size`].
+///
+/// [ustar format]: https://pubs.opengroup.org/onlinepubs/9699919799/utilities/pax.html#tag_20_92_13_06
+#[derive(Debug)]
+pub struct ReadStreamTarEntry {
+    /// The read buffer capacity.
+    capacity: usize,
+
+    /// The maximum size of the entry data.
+    max_size: usize,
+
+    /// The current state.
+    state: State,
+}
+
+impl ReadStreamTarEntry {
+    /// The size of tar blocks.
+    pub const BLOCK_SIZE: usize = 512;
+
+    /// The default maximum size of the entry data.
+    pub const DEFAULT_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+    /// Creates a new coroutine to read a tar entry using a buffer
+    /// with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a tar entry using a buffer
+    /// with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read tar entry (capacity: {capacity})");
+        let read = ReadStreamExact::with_capacity(capacity, Self::BLOCK_SIZE);
+        Self {
+            capacity,
+            max_size: Self::DEFAULT_MAX_SIZE,
+            state: State::Header(read),
+        }
+    }
+
+    /// Sets the maximum size of the entry data.
+    ///
+    /// The limit is enforced before allocating the data. Entries
+    /// exceeding it fail with [`ReadStreamTarEntryError::TooLarge`].
+    pub fn with_max_size(mut self, max: usize) -> Self {
+        self.max_size = max;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamTarEntryResult {
+        loop {
+            match &mut self.state {
+                State::Header(read) => {
+                    let block = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(block) => block,
+                        ReadStreamExactResult::Io(io) => break ReadStreamTarEntryResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamTarEntryResult::Err(err.into())
+                        }
+                    };
+
+                    if block.iter().all(|b| *b == 0) {
+                        trace!("read first end of archive block");
+                        let read = ReadStreamExact::with_capacity(self.capacity, Self::BLOCK_SIZE);
+                        self.state = State::End(read);
+                        continue;
+                    }
+
+                    let header = match parse_header(&block) {
+                        Ok(header) => header,
+                        Err(err) => break ReadStreamTarEntryResult::Err(err),
+                    };
+
+                    if header.size > self.max_size as u64 {
+                        let err = ReadStreamTarEntryError::TooLarge(header.size, self.max_size);
+                        break ReadStreamTarEntryResult::Err(err);
+                    }
+
+                    // data is padded to the next block boundary
+                    let padded = usize::try_from(header.size)
+                        .ok()
+                        .and_then(|size| size.checked_add(Self::BLOCK_SIZE - 1))
+                        .map(|size| size / Self::BLOCK_SIZE * Self::BLOCK_SIZE);
+
+                    let Some(padded) = padded else {
+                        let err = ReadStreamTarEntryError::InvalidField("size");
+                        break ReadStreamTarEntryResult::Err(err);
+                    };
+
+                    debug!("read tar header of {} ({} bytes)", header.name, header.size);
+                    let read = ReadStreamExact::with_capacity(self.capacity, padded);
+                    self.state = State::Data(header, read);
+                }
+                State::End(read) => {
+                    let block = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(block) => block,
+                        ReadStreamExactResult::Io(io) => break ReadStreamTarEntryResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamTarEntryResult::Err(err.into())
+                        }
+                    };
+
+                    if block.iter().any(|b| *b != 0) {
+                        let err = ReadStreamTarEntryError::InvalidEndOfArchive;
+                        break ReadStreamTarEntryResult::Err(err);
+                    }
+
+                    debug!("read tar end of archive");
+                    break ReadStreamTarEntryResult::Ok(None);
+                }
+                State::Data(header, read) => {
+                    let mut data = match read.resume(arg.take()) {
+                        ReadStreamExactResult::Ok(data) => data,
+                        ReadStreamExactResult::Io(io) => break ReadStreamTarEntryResult::Io(io),
+                        ReadStreamExactResult::Err(err) => {
+                            break ReadStreamTarEntryResult::Err(err.into())
+                        }
+                    };
+
+                    // the size has been checked against usize before
+                    data.truncate(header.size as usize);
+                    let header = mem::take(header);
+                    break ReadStreamTarEntryResult::Ok(Some((header, data)));
+                }
+            }
+        }
+    }
+}
+
+impl Coroutine for ReadStreamTarEntry {
+    type Output = Option<(TarHeader, Vec<u8>)>;
+    type Error = ReadStreamTarEntryError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamTarEntryResult {
+        ReadStreamTarEntry::resume(self, arg)
+    }
+}
+
+impl Default for ReadStreamTarEntry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses the relevant fields of the given header block, after
+/// verifying its checksum.
+fn parse_header(block: &[u8]) -> Result<TarHeader, ReadStreamTarEntryError> {
+    let field = |name, range: Range<usize>| {
+        parse_octal(&block[range]).ok_or(ReadStreamTarEntryError::InvalidField(name))
+    };
+
+    // the checksum is computed with its own field filled with spaces
+    let expected = field("checksum", 148..156)?;
+    let got = block
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if (148..156).contains(&i) {
+                32
+            } else {
+                u64::from(*b)
+            }
+        })
+        .sum();
+
+    if expected != got {
+        return Err(ReadStreamTarEntryError::InvalidChecksum(expected, got));
+    }
+
+    let mut name = Vec::new();
+
+    // ustar archives may split long names into a prefix
+    if &block[257..262] == b"ustar" {
+        let prefix = trim_padding(&block[345..500], &[0]);
+
+        if !prefix.is_empty() {
+            name.extend(prefix);
+            name.push(b'/');
+        }
+    }
+
+    name.extend(trim_padding(&block[..100], &[0]));
+
+    Ok(TarHeader {
+        name: String::from_utf8_lossy(&name).into_owned(),
+        mode: field("mode", 100..108)?,
+        size: field("size", 124..136)?,
+        typeflag: block[156],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_tar_entry::{ReadStreamTarEntryError, ReadStreamTarEntryResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamTarEntry;
+
+    fn header(name: &str, size: usize) -> Vec<u8> {
+        let mut block = vec![0; 512];
+        block[..name.len()].copy_from_slice(name.as_bytes());
+        block[100..108].copy_from_slice(b"0000644\0");
+        block[124..136].copy_from_slice(format!("{size:011o}\0").as_bytes());
+        block[148..156].copy_from_slice(b"        ");
+        block[156] = b'0';
+        block[257..263].copy_from_slice(b"ustar\0");
+
+        let checksum: u32 = block.iter().map(|b| *b as u32).sum();
+        block[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+        block
+    }
+
+    fn read(cursor: &mut ChunkedCursor, capacity: usize) -> ReadStreamTarEntryResult {
+        let mut read = ReadStreamTarEntry::with_capacity(capacity);
+        resume_until_done(cursor, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_tar_entry() {
+        let _ = env_logger::try_init();
+
+        let mut archive = header("hello.txt", 13);
+        archive.extend(b"Hello, world!");
+        archive.resize(1024, 0);
+        archive.extend(header("empty", 0));
+        archive.extend([0; 1024]);
+
+        for capacity in [100, 512, 4096] {
+            let mut reader = ChunkedCursor::with_pattern(archive.as_slice(), [1, 3, 2]);
+
+            match read(&mut reader, capacity) {
+                ReadStreamTarEntryResult::Ok(Some((header, data))) => {
+                    assert_eq!(header.name, "hello.txt");
+                    assert_eq!(header.mode, 0o644);
+                    assert_eq!(header.size, 13);
+                    assert_eq!(header.typeflag, b'0');
+                    assert_eq!(data, b"Hello, world!");
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+
+            // the padding has been discarded
+            match read(&mut reader, capacity) {
+                ReadStreamTarEntryResult::Ok(Some((header, data))) => {
+                    assert_eq!(header.name, "empty");
+                    assert!(data.is_empty());
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+
+            match read(&mut reader, capacity) {
+                ReadStreamTarEntryResult::Ok(None) => (),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn read_tar_end_of_archive() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern([0; 1024].as_slice(), [1, 3, 2]);
+
+        match read(&mut reader, 512) {
+            ReadStreamTarEntryResult::Ok(None) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut archive = vec![0; 512];
+        archive.extend(header("late", 0));
+        let mut reader = ChunkedCursor::with_pattern(archive.as_slice(), [1, 3, 2]);
+
+        match read(&mut reader, 512) {
+            ReadStreamTarEntryResult::Err(ReadStreamTarEntryError::InvalidEndOfArchive) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut block = header("corrupted", 0);
+        block[0] = b'C';
+        let mut reader = ChunkedCursor::with_pattern(block.as_slice(), [1, 3, 2]);
+
+        match read(&mut reader, 512) {
+            ReadStreamTarEntryResult::Err(ReadStreamTarEntryError::InvalidChecksum(..)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_tar_entry_too_large() {
+        let _ = env_logger::try_init();
+
+        // fails before allocating the announced data
+        let mut read = ReadStreamTarEntry::with_capacity(512).with_max_size(12);
+
+        let result = match read.resume(None) {
+            ReadStreamTarEntryResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                init_read_buffer(&mut buffer).copy_from_slice(&header("hello.txt", 13));
+                let output = StreamOutput {
+                    buffer,
+                    bytes_count: 512,
+                };
+                read.resume(Some(StreamIo::Read(Ok(output))))
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        match result {
+            ReadStreamTarEntryResult::Err(ReadStreamTarEntryError::TooLarge(13, 12)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -1,14 +1,23 @@
 //! I/O-free coroutine to read bytes into a buffer until it reaches
 //! EOF.
 
-use std::mem;
+use alloc::{sync::Arc, vec::Vec};
+use core::mem;
+
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 use log::trace;
 use thiserror::Error;
 
 use crate::io::StreamIo;
 
-use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+use super::{
+    cancel::Cancel,
+    observer::StreamObserver,
+    read::{ReadBudget, ReadStream, ReadStreamError, ReadStreamResult},
+    Coroutine, CoroutineResult,
+};
 
 /// Errors that can occur during the coroutine progression.
 #[derive(Clone, Debug, Error)]
@@ -16,22 +25,29 @@ pub enum ReadStreamToEndError {
     /// Error from the [`Read`] coroutine.
     #[error(transparent)]
     Read(#[from] ReadStreamError),
-}
-
-/// Output emitted after a coroutine finishes its progression.
-#[derive(Clone, Debug)]
-pub enum ReadStreamToEndResult {
-    /// The coroutine has successfully terminated its progression.
-    Ok(Vec<u8>),
 
-    /// A stream I/O needs to be performed to make the coroutine
-    /// progress.
-    Io(StreamIo),
+    /// The accumulated bytes would exceed the maximum amount of bytes.
+    ///
+    /// Contains the maximum amount of bytes and the bytes read so
+    /// far, truncated to that maximum.
+    #[error("Read limit exceeded: more than {0} bytes")]
+    LimitExceeded(usize, Vec<u8>),
 
-    /// An error occured during the coroutine progression.
-    Err(ReadStreamToEndError),
+    /// The deadline of the whole operation has been exceeded.
+    ///
+    /// Contains the partial bytes read so far, moved out of the
+    /// coroutine.
+    ///
+    /// Only deadlines set with `with_deadline` can be exceeded, which
+    /// requires the `std` feature. The variant is always defined, so
+    /// that the error enum does not depend on features.
+    #[error("Deadline exceeded after reading {} bytes", .0.len())]
+    DeadlineExceeded(Vec<u8>),
 }
 
+/// Output emitted after a coroutine finishes its progression.
+pub type ReadStreamToEndResult = CoroutineResult<Vec<u8>, ReadStreamToEndError>;
+
 /// I/O-free coroutine to read bytes into a buffer until it reaches
 /// EOF.
 #[derive(Debug)]
@@ -41,6 +57,13 @@ pub struct ReadStreamToEnd {
 
     /// The buffer containing the read bytes.
     buffer: Vec<u8>,
+
+    /// The maximum amount of bytes to accumulate, if any.
+    max: Option<usize>,
+
+    /// The deadline of the whole operation, if any.
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
 }
 
 impl ReadStreamToEnd {
@@ -58,7 +81,71 @@ impl ReadStreamToEnd {
         trace!("init coroutine to read until EOF (capacity: {capacity})");
         let read = ReadStream::with_capacity(capacity);
         let buffer = Vec::with_capacity(capacity);
-        Self { read, buffer }
+        let max = None;
+        Self {
+            read,
+            buffer,
+            max,
+            #[cfg(feature = "std")]
+            deadline: None,
+        }
+    }
+
+    /// Creates a new coroutine to read at most `max` bytes using a
+    /// buffer with the given capacity.
+    ///
+    /// The coroutine fails with [`ReadStreamToEndError::LimitExceeded`]
+    /// as soon as the stream contains more than `max` bytes. This
+    /// prevents unbounded memory growth when reading from untrusted
+    /// peers.
+    pub fn with_limit(capacity: usize, max: usize) -> Self {
+        trace!("init coroutine to read until EOF (capacity: {capacity}, max: {max})");
+        let read = ReadStream::with_capacity(capacity);
+        let buffer = Vec::with_capacity(capacity.min(max));
+        let max = Some(max);
+        Self {
+            read,
+            buffer,
+            max,
+            #[cfg(feature = "std")]
+            deadline: None,
+        }
+    }
+
+    /// Limits the amount of bytes read by the coroutine with the
+    /// given shared budget.
+    pub fn with_budget(mut self, budget: ReadBudget) -> Self {
+        self.read = self.read.with_budget(budget);
+        self
+    }
+
+    /// Notifies the given observer of each successful read.
+    pub fn with_observer(mut self, observer: Arc<dyn StreamObserver>) -> Self {
+        self.read = self.read.with_observer(observer);
+        self
+    }
+
+    /// Aborts the whole operation once the given deadline is
+    /// exceeded, with [`ReadStreamToEndError::DeadlineExceeded`].
+    ///
+    /// Unlike runtime timeouts, which cover a single I/O, the
+    /// deadline bounds the latency of the whole operation regardless
+    /// of the runtime driving it. The coroutine stays I/O-free: the
+    /// deadline is only compared with [`Instant::now`] before each
+    /// read request, which requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    ///
+    /// When cancelled, the bytes read so far are kept in the inner
+    /// buffer.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.read = self.read.with_cancel(cancel);
+        self
     }
 
     /// Extends the inner buffer with the given bytes slice.
@@ -66,9 +153,56 @@ impl ReadStreamToEnd {
         self.buffer.extend(bytes);
     }
 
+    /// Returns the bytes accumulated so far.
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Consumes the coroutine and returns the bytes accumulated so far.
+    ///
+    /// This salvages the partial state of an abandoned operation, for
+    /// example when another `tokio::select!` branch wins. Bytes of an
+    /// in-flight read request are owned by the runtime, hence not
+    /// returned. The coroutine is consumed, so it cannot be resumed
+    /// anymore.
+    pub fn into_partial(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Resets the coroutine, so that it can be reused to read another
+    /// stream.
+    ///
+    /// The inner read buffer is kept as is, as well as the configured
+    /// capacity, budget and cancel handle. Since the accumulated bytes
+    /// are moved out of the coroutine on success, the accumulation
+    /// buffer is cleared and reserves the configured capacity again.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.buffer.reserve(self.read.capacity());
+    }
+
+    /// Resets the coroutine like [`Self::reset`], using the given
+    /// buffer as accumulation buffer.
+    ///
+    /// Giving back the buffer emitted by the previous cycle once
+    /// processed preserves its allocation, so that steady-state
+    /// processing allocates nothing.
+    pub fn reset_with(&mut self, mut buffer: Vec<u8>) {
+        buffer.clear();
+        self.buffer = buffer;
+        self.reset();
+    }
+
     /// Makes the coroutine progress.
     pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamToEndResult {
         loop {
+            #[cfg(feature = "std")]
+            if arg.is_none() {
+                if let Some(err) = self.check_deadline() {
+                    break ReadStreamToEndResult::Err(err);
+                }
+            }
+
             let output = match self.read.resume(arg.take()) {
                 ReadStreamResult::Ok(output) => output,
                 ReadStreamResult::Err(err) => break ReadStreamToEndResult::Err(err.into()),
@@ -79,10 +213,46 @@ impl ReadStreamToEnd {
                 }
             };
 
+            // checks the limit before extending the buffer, so that
+            // it never grows past the limit
+            if let Some(max) = self.max {
+                if self.buffer.len() + output.bytes_count > max {
+                    let n = max - self.buffer.len();
+                    self.buffer.extend(&output.bytes()[..n]);
+                    self.read.replace(output.buffer);
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamToEndError::LimitExceeded(max, buffer);
+                    break ReadStreamToEndResult::Err(err);
+                }
+            }
+
             self.buffer.extend(output.bytes());
             self.read.replace(output.buffer);
         }
     }
+
+    /// Returns the deadline exceeded error, moving out the bytes read
+    /// so far, if the deadline has been exceeded.
+    #[cfg(feature = "std")]
+    fn check_deadline(&mut self) -> Option<ReadStreamToEndError> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                let buffer = mem::take(&mut self.buffer);
+                trace!("deadline exceeded after reading {} bytes", buffer.len());
+                Some(ReadStreamToEndError::DeadlineExceeded(buffer))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Coroutine for ReadStreamToEnd {
+    type Output = Vec<u8>;
+    type Error = ReadStreamToEndError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamToEndResult {
+        ReadStreamToEnd::resume(self, arg)
+    }
 }
 
 impl Default for ReadStreamToEnd {
@@ -93,11 +263,12 @@ impl Default for ReadStreamToEnd {
 
 #[cfg(test)]
 mod tests {
-    use std::io::{BufReader, Read as _};
+    use std::io::Read as _;
 
     use crate::{
-        coroutines::read_to_end::ReadStreamToEndResult,
-        io::{StreamIo, StreamOutput},
+        coroutines::read_to_end::{ReadStreamToEndError, ReadStreamToEndResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::{resume_until_done, ChunkedCursor},
     };
 
     use super::ReadStreamToEnd;
@@ -106,16 +277,70 @@ mod tests {
     fn read_to_end() {
         let _ = env_logger::try_init();
 
-        let mut reader = BufReader::new("abcdef".as_bytes());
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamToEnd::with_capacity(4);
+        let output = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamToEndResult::Ok(output) => output,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(output, b"abcdef");
+    }
+
+    #[test]
+    fn read_to_end_reset() {
+        let _ = env_logger::try_init();
 
         let mut read = ReadStreamToEnd::with_capacity(4);
+        let mut ptrs = Vec::new();
+
+        let mut outputs = Vec::new();
+
+        for input in ["abcdef", "xy"] {
+            let mut reader = ChunkedCursor::with_pattern(input.as_bytes(), [1, 3, 2]);
+            let mut arg = None;
+
+            let output = loop {
+                match read.resume(arg.take()) {
+                    ReadStreamToEndResult::Ok(output) => break output,
+                    ReadStreamToEndResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                        ptrs.push(buffer.as_ptr());
+                        let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                        let output = StreamOutput {
+                            buffer,
+                            bytes_count,
+                        };
+                        arg = Some(StreamIo::Read(Ok(output)))
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            };
+
+            outputs.push(output);
+            read.reset();
+        }
+
+        assert_eq!(outputs, [b"abcdef".to_vec(), b"xy".to_vec()]);
+
+        // the same read buffer is used across cycles
+        assert!(ptrs.iter().all(|ptr| *ptr == ptrs[0]));
+    }
+
+    #[test]
+    fn read_to_end_limit() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdefghij".as_bytes(), [1, 3, 2]);
+
+        let mut read = ReadStreamToEnd::with_limit(4, 5);
         let mut arg = None;
 
-        let output = loop {
+        let err = loop {
             match read.resume(arg.take()) {
-                ReadStreamToEndResult::Ok(output) => break output,
+                ReadStreamToEndResult::Err(err) => break err,
                 ReadStreamToEndResult::Io(StreamIo::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
                     let output = StreamOutput {
                         buffer,
                         bytes_count,
@@ -126,6 +351,50 @@ mod tests {
             }
         };
 
-        assert_eq!(output, b"abcdef");
+        match err {
+            ReadStreamToEndError::LimitExceeded(max, partial) => {
+                assert_eq!(max, 5);
+                assert_eq!(partial, b"abcde");
+            }
+            other => unreachable!("Unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_to_end_reset_with() {
+        let _ = env_logger::try_init();
+
+        let mut read = ReadStreamToEnd::with_capacity(4);
+        let mut ptrs = Vec::new();
+        let mut buffer = Vec::with_capacity(64);
+
+        for input in ["abcdef", "ghijkl"] {
+            read.reset_with(buffer);
+
+            let mut reader = ChunkedCursor::with_pattern(input.as_bytes(), [1, 3, 2]);
+            let mut arg = None;
+
+            buffer = loop {
+                match read.resume(arg.take()) {
+                    ReadStreamToEndResult::Ok(output) => break output,
+                    ReadStreamToEndResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                        let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                        let output = StreamOutput {
+                            buffer,
+                            bytes_count,
+                        };
+                        arg = Some(StreamIo::Read(Ok(output)))
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            };
+
+            assert_eq!(buffer, input.as_bytes());
+            ptrs.push(buffer.as_ptr());
+        }
+
+        // the accumulation buffer is not reallocated across cycles
+        assert_eq!(ptrs[0], ptrs[1]);
+        assert_eq!(buffer.capacity(), 64);
     }
 }
@@ -0,0 +1,190 @@
+//! I/O-free coroutine to read UTF-8 text until it reaches EOF.
+
+use alloc::{string::String, vec::Vec};
+use core::{mem, str};
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_to_end::{ReadStreamToEnd, ReadStreamToEndError, ReadStreamToEndResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamToStringError {
+    /// The read bytes are not valid UTF-8.
+    ///
+    /// Contains the byte offset of the first invalid sequence and the
+    /// bytes read so far.
+    #[error("Invalid UTF-8 sequence at byte {0}")]
+    InvalidUtf8(usize, Vec<u8>),
+
+    /// Error from the [`ReadStreamToEnd`] coroutine.
+    #[error(transparent)]
+    ReadToEnd(#[from] ReadStreamToEndError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+pub type ReadStreamToStringResult = CoroutineResult<String, ReadStreamToStringError>;
+
+/// I/O-free coroutine to read UTF-8 text until it reaches EOF.
+///
+/// Bytes are validated as they are read, so that a non-UTF-8 stream
+/// fails as soon as an invalid sequence is received, without
+/// buffering the rest of the stream. A multi-byte character split
+/// across two reads is not considered invalid until EOF.
+#[derive(Debug)]
+pub struct ReadStreamToString {
+    /// The inner read to end coroutine.
+    read: ReadStreamToEnd,
+
+    /// The amount of leading bytes known to be valid UTF-8.
+    valid: usize,
+}
+
+impl ReadStreamToString {
+    /// Creates a new coroutine to read text using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read text using a buffer with the
+    /// given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read UTF-8 until EOF (capacity: {capacity})");
+        let read = ReadStreamToEnd::with_capacity(capacity);
+        Self { read, valid: 0 }
+    }
+
+    /// Creates a new coroutine to read at most `max` bytes of text
+    /// using a buffer with the given capacity.
+    ///
+    /// See [`ReadStreamToEnd::with_limit`].
+    pub fn with_limit(capacity: usize, max: usize) -> Self {
+        trace!("init coroutine to read UTF-8 until EOF (capacity: {capacity}, max: {max})");
+        let read = ReadStreamToEnd::with_limit(capacity, max);
+        Self { read, valid: 0 }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamToStringResult {
+        let bytes = match self.read.resume(arg) {
+            ReadStreamToEndResult::Ok(bytes) => bytes,
+            ReadStreamToEndResult::Err(err) => return ReadStreamToStringResult::Err(err.into()),
+            ReadStreamToEndResult::Io(io) => {
+                let bytes = &self.read.bytes()[self.valid..];
+
+                match str::from_utf8(bytes) {
+                    Ok(_) => self.valid += bytes.len(),
+                    // the sequence may be completed by the next read
+                    Err(err) if err.error_len().is_none() => self.valid += err.valid_up_to(),
+                    Err(err) => {
+                        let offset = self.valid + err.valid_up_to();
+                        let bytes = mem::take(&mut self.read).into_partial();
+                        let err = ReadStreamToStringError::InvalidUtf8(offset, bytes);
+                        return ReadStreamToStringResult::Err(err);
+                    }
+                }
+
+                return ReadStreamToStringResult::Io(io);
+            }
+        };
+
+        match String::from_utf8(bytes) {
+            Ok(string) => {
+                debug!("read {} bytes of UTF-8 text", string.len());
+                ReadStreamToStringResult::Ok(string)
+            }
+            Err(err) => {
+                let offset = err.utf8_error().valid_up_to();
+                let err = ReadStreamToStringError::InvalidUtf8(offset, err.into_bytes());
+                ReadStreamToStringResult::Err(err)
+            }
+        }
+    }
+}
+
+impl Coroutine for ReadStreamToString {
+    type Output = String;
+    type Error = ReadStreamToStringError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamToStringResult {
+        ReadStreamToString::resume(self, arg)
+    }
+}
+
+impl Default for ReadStreamToString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_to_string::{ReadStreamToStringError, ReadStreamToStringResult},
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamToString;
+
+    fn read(mut read: ReadStreamToString, input: &[u8]) -> (usize, ReadStreamToStringResult) {
+        let mut cursor = ChunkedCursor::with_pattern(input, []);
+        let mut reads = 0;
+
+        let result = resume_until_done(&mut cursor, |arg| {
+            let result = read.resume(arg);
+            reads += matches!(result, ReadStreamToStringResult::Io(_)) as usize;
+            result
+        });
+
+        (reads, result)
+    }
+
+    #[test]
+    fn read_to_string() {
+        let _ = env_logger::try_init();
+
+        // the 2-byte é is split across the first two reads
+        let input = "abcé, ok".as_bytes();
+
+        for capacity in [4, 1024] {
+            match read(ReadStreamToString::with_capacity(capacity), input).1 {
+                ReadStreamToStringResult::Ok(string) => assert_eq!(string, "abcé, ok"),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn read_to_string_invalid() {
+        let _ = env_logger::try_init();
+
+        let mut input = b"abc\xffdef".to_vec();
+        input.extend([b'x'; 64]);
+
+        // fails fast, without reading the rest of the stream
+        match read(ReadStreamToString::with_capacity(4), &input) {
+            (1, ReadStreamToStringResult::Err(ReadStreamToStringError::InvalidUtf8(3, bytes))) => {
+                assert_eq!(bytes, b"abc\xff")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // an incomplete sequence is invalid at EOF only
+        match read(ReadStreamToString::with_capacity(4), b"abc\xc3").1 {
+            ReadStreamToStringResult::Err(ReadStreamToStringError::InvalidUtf8(3, bytes)) => {
+                assert_eq!(bytes, b"abc\xc3")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! I/O-free coroutine to read bytes until a caller-provided
+//! predicate detects the end of the message.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamUntilPredicateError {
+    /// The coroutine reached the End Of File before the predicate
+    /// detected the end of the message.
+    ///
+    /// Contains the partial bytes read so far.
+    #[error("Unexpected EOF before end of message")]
+    UnexpectedEof(Vec<u8>),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamUntilPredicateResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the bytes up to the offset returned by the predicate.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamUntilPredicateError),
+}
+
+/// I/O-free coroutine to read bytes until a caller-provided predicate
+/// detects the end of the message.
+///
+/// After each read, the predicate is called with all the bytes
+/// accumulated so far. It returns `None` if more bytes are needed, or
+/// the offset of the logical end of the message otherwise. Offsets
+/// greater than the amount of accumulated bytes are bounded to it.
+///
+/// Bytes past the offset are kept and can be retrieved with
+/// [`Self::take_leftover`].
+#[derive(Debug)]
+pub struct ReadStreamUntilPredicate<F> {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing the accumulated bytes.
+    buffer: Vec<u8>,
+
+    /// The end of message detector.
+    predicate: F,
+
+    /// Whether the buffer changed since the last predicate call.
+    unchecked: bool,
+}
+
+impl<F> ReadStreamUntilPredicate<F>
+where
+    F: FnMut(&[u8]) -> Option<usize>,
+{
+    /// Creates a new coroutine to read bytes until the given
+    /// predicate matches using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(predicate: F) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, predicate)
+    }
+
+    /// Creates a new coroutine to read bytes until the given
+    /// predicate matches using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, predicate: F) -> Self {
+        trace!("init coroutine to read bytes until predicate (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            predicate,
+            unchecked: false,
+        }
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+        self.unchecked = true;
+    }
+
+    /// Returns the bytes read past the end of the message.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the end of the message.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamUntilPredicateResult {
+        loop {
+            if self.unchecked {
+                self.unchecked = false;
+
+                if let Some(offset) = (self.predicate)(&self.buffer) {
+                    let offset = offset.min(self.buffer.len());
+                    let leftover = self.buffer.split_off(offset);
+                    let message = mem::replace(&mut self.buffer, leftover);
+                    debug!("predicate matched after {offset} bytes");
+                    break ReadStreamUntilPredicateResult::Ok(message);
+                }
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => {
+                    break ReadStreamUntilPredicateResult::Err(err.into())
+                }
+                ReadStreamResult::Io(io) => break ReadStreamUntilPredicateResult::Io(io),
+                ReadStreamResult::Eof => {
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamUntilPredicateError::UnexpectedEof(buffer);
+                    break ReadStreamUntilPredicateResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.unchecked = true;
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_until_predicate::ReadStreamUntilPredicateResult,
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamUntilPredicate;
+
+    impl_io_result!(ReadStreamUntilPredicateResult);
+
+    #[test]
+    fn read_until_smtp_end_of_data() {
+        let _ = env_logger::try_init();
+
+        let mut reader =
+            ChunkedCursor::with_pattern("Subject: hi\r\n\r\n..dot\r\n.\r\nQUIT\r\n".as_bytes(), []);
+
+        // the end of data is a lone dot line, which can span reads
+        let mut calls = 0;
+        let end_of_data = |bytes: &[u8]| {
+            calls += 1;
+            memchr::memmem::find(bytes, b"\r\n.\r\n").map(|i| i + 5)
+        };
+
+        let mut read = ReadStreamUntilPredicate::with_capacity(4, end_of_data);
+        let message = match resume_until_done(&mut reader, |arg| read.resume(arg)) {
+            ReadStreamUntilPredicateResult::Ok(message) => message,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(message, b"Subject: hi\r\n\r\n..dot\r\n.\r\n");
+        assert_eq!(read.leftover(), b"QUI");
+        assert_eq!(calls, 7);
+    }
+}
@@ -0,0 +1,514 @@
+//! I/O-free coroutine to read bytes into a buffer until it reaches a
+//! given delimiter.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+    Coroutine, CoroutineResult,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamUntilError {
+    /// The coroutine reached the End Of File before the delimiter.
+    ///
+    /// Contains the delimiter and the partial bytes read so far.
+    #[error("Unexpected EOF before delimiter {0:#04x}")]
+    UnexpectedEof(u8, Vec<u8>),
+
+    /// The delimiter was not found within the scan limit.
+    ///
+    /// Contains the scan limit and the bytes read so far.
+    #[error("Delimiter not found within {0} bytes")]
+    ScanLimitExceeded(usize, Vec<u8>),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamUntilResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the read bytes, delimiter included.
+    Ok(Vec<u8>),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamUntilError),
+}
+
+/// I/O-free coroutine to read bytes into a buffer until it reaches a
+/// given delimiter.
+///
+/// Bytes read past the delimiter are kept and can be retrieved with
+/// [`Self::take_leftover`].
+///
+/// By default, bytes are accumulated until the delimiter is found.
+/// See [`Self::with_max_scan`] to bound the accumulation buffer.
+#[derive(Debug)]
+pub struct ReadStreamUntil {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing the read bytes.
+    buffer: Vec<u8>,
+
+    /// The amount of bytes of the buffer already scanned.
+    scanned: usize,
+
+    /// The delimiter to read until.
+    delimiter: u8,
+
+    /// The maximum amount of bytes to scan, if any.
+    max_scan: Option<usize>,
+}
+
+impl ReadStreamUntil {
+    /// Creates a new coroutine to read bytes until the given
+    /// delimiter using a buffer with [`ReadStream::DEFAULT_CAPACITY`]
+    /// capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(delimiter: u8) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, delimiter)
+    }
+
+    /// Creates a new coroutine to read bytes until the given
+    /// delimiter using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, delimiter: u8) -> Self {
+        trace!("init coroutine to read until {delimiter:#04x} (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            scanned: 0,
+            delimiter,
+            max_scan: None,
+        }
+    }
+
+    /// Fails with [`ReadStreamUntilError::ScanLimitExceeded`] when
+    /// the delimiter is not found within the given amount of bytes.
+    ///
+    /// The limit applies to the returned bytes, delimiter included,
+    /// and counts all the buffered bytes, whatever the amount of
+    /// reads. The buffer never holds more than the limit plus the
+    /// capacity of one read.
+    pub fn with_max_scan(mut self, limit: usize) -> Self {
+        self.max_scan = Some(limit);
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the delimiter.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the delimiter.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        self.scanned = 0;
+        mem::take(&mut self.buffer)
+    }
+
+    /// Consumes the coroutine and returns the bytes read so far.
+    ///
+    /// This salvages the partial state of an abandoned operation, for
+    /// example when another `tokio::select!` branch wins. Bytes of an
+    /// in-flight read request are owned by the runtime, hence not
+    /// returned. The coroutine is consumed, so it cannot be resumed
+    /// anymore.
+    pub fn into_partial(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamUntilResult {
+        loop {
+            if arg.is_none() {
+                let max = self.max_scan.unwrap_or(usize::MAX);
+                let end = memchr::memchr(self.delimiter, &self.buffer[self.scanned..])
+                    .map(|n| self.scanned + n + 1);
+
+                if let Some(end) = end.filter(|end| *end <= max) {
+                    let leftover = self.buffer.split_off(end);
+                    let bytes = mem::replace(&mut self.buffer, leftover);
+                    self.scanned = 0;
+                    debug!("found delimiter after {} bytes", bytes.len());
+                    break ReadStreamUntilResult::Ok(bytes);
+                }
+
+                if end.is_some() || self.buffer.len() >= max {
+                    self.scanned = 0;
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamUntilError::ScanLimitExceeded(max, buffer);
+                    break ReadStreamUntilResult::Err(err);
+                }
+
+                self.scanned = self.buffer.len();
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamUntilResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamUntilResult::Io(io),
+                ReadStreamResult::Eof => {
+                    self.scanned = 0;
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamUntilError::UnexpectedEof(self.delimiter, buffer);
+                    break ReadStreamUntilResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+/// Errors that can occur during the [`ReadStreamUntilPattern`]
+/// coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamUntilPatternError {
+    /// The coroutine reached the End Of File before the pattern.
+    ///
+    /// Contains the partial bytes read so far.
+    #[error("Unexpected EOF before pattern")]
+    UnexpectedEof(Vec<u8>),
+
+    /// The pattern was not found within the scan limit.
+    ///
+    /// Contains the scan limit and the bytes read so far.
+    #[error("Pattern not found within {0} bytes")]
+    ScanLimitExceeded(usize, Vec<u8>),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a [`ReadStreamUntilPattern`] coroutine
+/// finishes its progression.
+///
+/// Contains the read bytes, pattern included.
+pub type ReadStreamUntilPatternResult = CoroutineResult<Vec<u8>, ReadStreamUntilPatternError>;
+
+/// I/O-free coroutine to read bytes into a buffer until it reaches a
+/// given multi-byte pattern.
+///
+/// Same as [`ReadStreamUntil`], except that the delimiter is a
+/// sequence of bytes. The pattern can straddle read boundaries and
+/// can overlap itself, like `aaab` after `aaa`: the last
+/// `pattern.len() - 1` bytes of the buffer are scanned again after
+/// each read, so that no match is missed regardless of chunking.
+///
+/// Bytes read past the pattern are kept and can be retrieved with
+/// [`Self::take_leftover`].
+///
+/// See [`Self::with_max_scan`] to bound the accumulation buffer.
+#[derive(Debug)]
+pub struct ReadStreamUntilPattern {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing the read bytes.
+    buffer: Vec<u8>,
+
+    /// The amount of bytes of the buffer already scanned.
+    scanned: usize,
+
+    /// The pattern to read until.
+    pattern: Vec<u8>,
+
+    /// The maximum amount of bytes to scan, if any.
+    max_scan: Option<usize>,
+}
+
+impl ReadStreamUntilPattern {
+    /// Creates a new coroutine to read bytes until the given pattern
+    /// using a buffer with [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(pattern: impl Into<Vec<u8>>) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, pattern)
+    }
+
+    /// Creates a new coroutine to read bytes until the given pattern
+    /// using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize, pattern: impl Into<Vec<u8>>) -> Self {
+        let pattern = pattern.into();
+        let len = pattern.len();
+        trace!("init coroutine to read until {len}-byte pattern (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            scanned: 0,
+            pattern,
+            max_scan: None,
+        }
+    }
+
+    /// Fails with [`ReadStreamUntilPatternError::ScanLimitExceeded`]
+    /// when the pattern is not found within the given amount of
+    /// bytes.
+    ///
+    /// Same as [`ReadStreamUntil::with_max_scan`], the limit applies
+    /// to the returned bytes, pattern included.
+    pub fn with_max_scan(mut self, limit: usize) -> Self {
+        self.max_scan = Some(limit);
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the pattern.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the pattern.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        self.scanned = 0;
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamUntilPatternResult {
+        loop {
+            if arg.is_none() {
+                let max = self.max_scan.unwrap_or(usize::MAX);
+                let end = memchr::memmem::find(&self.buffer[self.scanned..], &self.pattern)
+                    .map(|n| self.scanned + n + self.pattern.len());
+
+                if let Some(end) = end.filter(|end| *end <= max) {
+                    let leftover = self.buffer.split_off(end);
+                    let bytes = mem::replace(&mut self.buffer, leftover);
+                    self.scanned = 0;
+                    debug!("found pattern after {} bytes", bytes.len());
+                    break ReadStreamUntilPatternResult::Ok(bytes);
+                }
+
+                if end.is_some() || self.buffer.len() >= max {
+                    self.scanned = 0;
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamUntilPatternError::ScanLimitExceeded(max, buffer);
+                    break ReadStreamUntilPatternResult::Err(err);
+                }
+
+                // keeps the bytes that may start a match spanning the
+                // next read
+                let overlap = self.pattern.len().saturating_sub(1);
+                self.scanned = self.buffer.len().saturating_sub(overlap);
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamUntilPatternResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamUntilPatternResult::Io(io),
+                ReadStreamResult::Eof => {
+                    self.scanned = 0;
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamUntilPatternError::UnexpectedEof(buffer);
+                    break ReadStreamUntilPatternResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+impl Coroutine for ReadStreamUntilPattern {
+    type Output = Vec<u8>;
+    type Error = ReadStreamUntilPatternError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamUntilPatternResult {
+        ReadStreamUntilPattern::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read::ReadStreamError,
+            read_until::{
+                ReadStreamUntilError, ReadStreamUntilPatternError, ReadStreamUntilPatternResult,
+                ReadStreamUntilResult,
+            },
+        },
+        io::{StreamIo, StreamOutput},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::{ReadStreamUntil, ReadStreamUntilPattern};
+
+    impl_io_result!(ReadStreamUntilResult);
+
+    fn read(mut until: ReadStreamUntil, input: &[u8]) -> (ReadStreamUntil, ReadStreamUntilResult) {
+        let mut reader = ChunkedCursor::with_pattern(input, []);
+        let result = resume_until_done(&mut reader, |arg| until.resume(arg));
+
+        (until, result)
+    }
+
+    #[test]
+    fn read_until_mid_chunk() {
+        let _ = env_logger::try_init();
+
+        let (until, result) = read(ReadStreamUntil::with_capacity(8, b'\n'), b"abc\ndefgh");
+
+        match result {
+            ReadStreamUntilResult::Ok(bytes) => assert_eq!(bytes, b"abc\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(until.leftover(), b"defg");
+    }
+
+    #[test]
+    fn read_until_spanning_reads() {
+        let _ = env_logger::try_init();
+
+        let (until, result) = read(ReadStreamUntil::with_capacity(3, b'\n'), b"abcde\nf");
+
+        match result {
+            ReadStreamUntilResult::Ok(bytes) => assert_eq!(bytes, b"abcde\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert!(until.leftover().is_empty());
+    }
+
+    #[test]
+    fn read_until_eof() {
+        let _ = env_logger::try_init();
+
+        let (_, result) = read(ReadStreamUntil::new(b'\n'), b"abc");
+
+        match result {
+            ReadStreamUntilResult::Err(ReadStreamUntilError::UnexpectedEof(b'\n', bytes)) => {
+                assert_eq!(bytes, b"abc")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_until_overlapping_pattern() {
+        let _ = env_logger::try_init();
+
+        // the first read ends with the partial match "aaa", then the
+        // match starts one byte before the read boundary
+        let mut reader = ChunkedCursor::with_pattern(&b"xaaaaabyz"[..], []);
+        let mut until = ReadStreamUntilPattern::with_capacity(4, *b"aaab");
+        let bytes = match resume_until_done(&mut reader, |arg| until.resume(arg)) {
+            ReadStreamUntilPatternResult::Ok(bytes) => bytes,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(bytes, b"xaaaaab");
+        assert_eq!(until.leftover(), b"y");
+    }
+
+    #[test]
+    fn read_until_extra_response() {
+        let _ = env_logger::try_init();
+
+        let (mut until, result) = read(ReadStreamUntil::new(b'\n'), b"a\nb\n");
+
+        match result {
+            ReadStreamUntilResult::Ok(bytes) => assert_eq!(bytes, b"a\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // the buffered line is not returned in place of the response
+        let output = StreamOutput {
+            buffer: b"c\n".to_vec(),
+            bytes_count: 2,
+        };
+
+        match until.resume(Some(StreamIo::Read(Ok(output)))) {
+            ReadStreamUntilResult::Err(ReadStreamUntilError::Read(
+                ReadStreamError::InvalidArgument("no response", _),
+            )) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match until.resume(None) {
+            ReadStreamUntilResult::Ok(bytes) => assert_eq!(bytes, b"b\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_until_max_scan() {
+        let _ = env_logger::try_init();
+
+        // the delimiter is the 8th byte, read in 3-byte chunks
+        let until = ReadStreamUntil::with_capacity(3, b'\n').with_max_scan(8);
+
+        match read(until, b"abcdefg\nhi").1 {
+            ReadStreamUntilResult::Ok(bytes) => assert_eq!(bytes, b"abcdefg\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let until = ReadStreamUntil::with_capacity(3, b'\n').with_max_scan(7);
+
+        match read(until, b"abcdefg\nhi").1 {
+            ReadStreamUntilResult::Err(ReadStreamUntilError::ScanLimitExceeded(7, bytes)) => {
+                assert_eq!(bytes, b"abcdefg\nh")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // fails as soon as the limit is reached, before EOF
+        let until = ReadStreamUntil::with_capacity(4, b'\n').with_max_scan(8);
+
+        match read(until, &[b'a'; 64]).1 {
+            ReadStreamUntilResult::Err(ReadStreamUntilError::ScanLimitExceeded(8, bytes)) => {
+                assert_eq!(bytes.len(), 8)
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut until = ReadStreamUntilPattern::new(*b"\r\n\r\n").with_max_scan(6);
+        until.extend(*b"ab\r\n\r\n");
+
+        match until.resume(None) {
+            ReadStreamUntilPatternResult::Ok(bytes) => assert_eq!(bytes, b"ab\r\n\r\n"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut until = ReadStreamUntilPattern::new(*b"\r\n\r\n").with_max_scan(6);
+        until.extend(*b"abc\r\n\r\n");
+
+        match until.resume(None) {
+            ReadStreamUntilPatternResult::Err(ReadStreamUntilPatternError::ScanLimitExceeded(
+                6,
+                _,
+            )) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
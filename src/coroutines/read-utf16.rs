@@ -0,0 +1,226 @@
+//! I/O-free coroutine to read a UTF-16 text payload.
+
+use alloc::{string::String, vec::Vec};
+use core::char;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read::ReadStream,
+    read_exact::{ReadStreamExact, ReadStreamExactError},
+    read_framed::Endianness,
+    read_to_end::{ReadStreamToEnd, ReadStreamToEndError},
+    Coroutine, CoroutineResult,
+};
+
+/// The byte order mark, as encoded in big endian.
+const BOM: [u8; 2] = [0xfe, 0xff];
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamUtf16Error {
+    /// The payload ends with an incomplete code unit.
+    ///
+    /// Contains the raw payload.
+    #[error("Invalid UTF-16 payload: odd length of {} bytes", .0.len())]
+    OddLength(Vec<u8>),
+
+    /// The payload contains unpaired surrogates.
+    ///
+    /// Contains the raw payload.
+    #[error("Invalid UTF-16 payload")]
+    InvalidUtf16(Vec<u8>),
+
+    /// Error from the [`ReadStreamToEnd`] coroutine.
+    #[error(transparent)]
+    ReadToEnd(#[from] ReadStreamToEndError),
+
+    /// Error from the [`ReadStreamExact`] coroutine.
+    #[error(transparent)]
+    ReadExact(#[from] ReadStreamExactError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+///
+/// Contains the decoded text, without byte order mark.
+pub type ReadStreamUtf16Result = CoroutineResult<String, ReadStreamUtf16Error>;
+
+/// The inner read coroutine.
+#[derive(Debug)]
+enum Read {
+    /// Reading until the End Of File.
+    ToEnd(ReadStreamToEnd),
+
+    /// Reading a given amount of bytes.
+    Exact(ReadStreamExact),
+}
+
+/// I/O-free coroutine to read a UTF-16 text payload.
+///
+/// The payload is read until the End Of File, or up to a given amount
+/// of bytes, see [`Self::with_len`]. The byte order is detected from
+/// the leading byte order mark, which is stripped. Without byte order
+/// mark, the payload is decoded using the configured byte order,
+/// which defaults to big endian, see [`Self::with_endianness`].
+///
+/// This complements [`ReadStreamLine`], which reads UTF-8 text.
+///
+/// [`ReadStreamLine`]: super::read_line::ReadStreamLine
+#[derive(Debug)]
+pub struct ReadStreamUtf16 {
+    /// The inner read coroutine.
+    read: Read,
+
+    /// The byte order used without byte order mark.
+    endianness: Endianness,
+}
+
+impl ReadStreamUtf16 {
+    /// Creates a new coroutine to read a UTF-16 payload until the End
+    /// Of File, using a buffer with [`ReadStream::DEFAULT_CAPACITY`]
+    /// capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a UTF-16 payload until the End
+    /// Of File, using a buffer with the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read UTF-16 payload (capacity: {capacity})");
+        Self {
+            read: Read::ToEnd(ReadStreamToEnd::with_capacity(capacity)),
+            endianness: Endianness::default(),
+        }
+    }
+
+    /// Creates a new coroutine to read a UTF-16 payload of the given
+    /// amount of bytes, byte order mark included, using a buffer with
+    /// the given capacity.
+    pub fn with_len(capacity: usize, len: usize) -> Self {
+        trace!("init coroutine to read {len}-byte UTF-16 payload (capacity: {capacity})");
+        Self {
+            read: Read::Exact(ReadStreamExact::with_capacity(capacity, len)),
+            endianness: Endianness::default(),
+        }
+    }
+
+    /// Decodes payloads without byte order mark using the given byte
+    /// order.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamUtf16Result {
+        let bytes = match &mut self.read {
+            Read::ToEnd(read) => match read.resume(arg) {
+                CoroutineResult::Ok(bytes) => bytes,
+                CoroutineResult::Io(io) => return ReadStreamUtf16Result::Io(io),
+                CoroutineResult::Err(err) => return ReadStreamUtf16Result::Err(err.into()),
+            },
+            Read::Exact(read) => match read.resume(arg) {
+                CoroutineResult::Ok(bytes) => bytes,
+                CoroutineResult::Io(io) => return ReadStreamUtf16Result::Io(io),
+                CoroutineResult::Err(err) => return ReadStreamUtf16Result::Err(err.into()),
+            },
+        };
+
+        if bytes.len() % 2 != 0 {
+            return ReadStreamUtf16Result::Err(ReadStreamUtf16Error::OddLength(bytes));
+        }
+
+        let (endianness, payload) = match bytes.get(..2) {
+            Some(bom) if bom == BOM => (Endianness::Big, &bytes[2..]),
+            Some([a, b]) if [*b, *a] == BOM => (Endianness::Little, &bytes[2..]),
+            _ => (self.endianness, &bytes[..]),
+        };
+
+        let units = payload.chunks(2).map(|unit| match endianness {
+            Endianness::Big => u16::from_be_bytes([unit[0], unit[1]]),
+            Endianness::Little => u16::from_le_bytes([unit[0], unit[1]]),
+        });
+
+        match char::decode_utf16(units).collect::<Result<String, _>>() {
+            Ok(text) => {
+                debug!(
+                    "decoded {endianness:?} endian UTF-16 payload of {} bytes",
+                    bytes.len()
+                );
+                ReadStreamUtf16Result::Ok(text)
+            }
+            Err(_) => ReadStreamUtf16Result::Err(ReadStreamUtf16Error::InvalidUtf16(bytes)),
+        }
+    }
+}
+
+impl Default for ReadStreamUtf16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coroutine for ReadStreamUtf16 {
+    type Output = String;
+    type Error = ReadStreamUtf16Error;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamUtf16Result {
+        ReadStreamUtf16::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_framed::Endianness,
+            read_utf16::{ReadStreamUtf16Error, ReadStreamUtf16Result},
+        },
+        runtimes::std::{resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamUtf16;
+
+    fn read(mut read: ReadStreamUtf16, input: &[u8]) -> ReadStreamUtf16Result {
+        let mut reader = ChunkedCursor::with_pattern(input, [1, 3, 2]);
+        resume_until_done(&mut reader, |arg| read.resume(arg))
+    }
+
+    #[test]
+    fn read_utf16_le_bom() {
+        let _ = env_logger::try_init();
+
+        // the BOM takes precedence over the configured byte order
+        let read = ReadStreamUtf16::with_capacity(3).with_endianness(Endianness::Big);
+        let input = b"\xff\xfeh\0\xe9\0\x3d\xd8\x00\xde";
+
+        match self::read(read, input) {
+            ReadStreamUtf16Result::Ok(text) => assert_eq!(text, "hé😀"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_utf16_be() {
+        let _ = env_logger::try_init();
+
+        match read(ReadStreamUtf16::with_len(4, 6), b"\0h\0\xe9\0!tail") {
+            ReadStreamUtf16Result::Ok(text) => assert_eq!(text, "hé!"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let read = ReadStreamUtf16::new().with_endianness(Endianness::Little);
+
+        match self::read(read, b"h\0\xe9") {
+            ReadStreamUtf16Result::Err(ReadStreamUtf16Error::OddLength(bytes)) => {
+                assert_eq!(bytes, b"h\0\xe9")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! I/O-free coroutine to read a variable-length integer.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::read::{ReadStream, ReadStreamError, ReadStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadStreamVarintError {
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF while reading varint")]
+    UnexpectedEof(Vec<u8>),
+
+    /// The varint is encoded on more bytes than allowed.
+    #[error("Varint exceeds {0} bytes")]
+    TooLong(usize),
+
+    /// The varint does not fit into 64 bits.
+    #[error("Varint overflows 64 bits")]
+    Overflow,
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum ReadStreamVarintResult {
+    /// The coroutine has successfully terminated its progression.
+    Ok(u64),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(ReadStreamVarintError),
+}
+
+/// I/O-free coroutine to read a variable-length integer.
+///
+/// The integer is encoded in base 128, least significant group
+/// first, where the most significant bit of each byte indicates that
+/// more bytes follow (LEB128, as used by Protocol Buffers or MQTT).
+///
+/// Bytes read past the varint are kept and can be retrieved with
+/// [`Self::take_leftover`].
+#[derive(Debug)]
+pub struct ReadStreamVarint {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The buffer containing read bytes not yet consumed.
+    buffer: Vec<u8>,
+
+    /// The maximum amount of bytes the varint can be encoded on.
+    max_bytes: usize,
+}
+
+impl ReadStreamVarint {
+    /// The maximum amount of bytes needed to encode a 64-bit varint.
+    pub const MAX_BYTES: usize = 10;
+
+    /// Creates a new coroutine to read a varint using a buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new coroutine to read a varint using a buffer with
+    /// the given capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        trace!("init coroutine to read varint (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity),
+            buffer: Vec::new(),
+            max_bytes: Self::MAX_BYTES,
+        }
+    }
+
+    /// Limits the amount of bytes the varint can be encoded on.
+    ///
+    /// The limit cannot exceed [`Self::MAX_BYTES`].
+    pub fn with_max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = max.min(Self::MAX_BYTES);
+        self
+    }
+
+    /// Extends the inner buffer with the given bytes slice.
+    pub fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the bytes read past the varint.
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Takes the bytes read past the varint.
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        mem::take(&mut self.buffer)
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> ReadStreamVarintResult {
+        loop {
+            if arg.is_none() {
+                match self.decode() {
+                    Ok(Some(n)) => break ReadStreamVarintResult::Ok(n),
+                    Ok(None) => (),
+                    Err(err) => break ReadStreamVarintResult::Err(err),
+                }
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Err(err) => break ReadStreamVarintResult::Err(err.into()),
+                ReadStreamResult::Io(io) => break ReadStreamVarintResult::Io(io),
+                ReadStreamResult::Eof => {
+                    let buffer = mem::take(&mut self.buffer);
+                    let err = ReadStreamVarintError::UnexpectedEof(buffer);
+                    break ReadStreamVarintResult::Err(err);
+                }
+            };
+
+            self.buffer.extend(output.bytes());
+            self.read.replace(output.buffer);
+        }
+    }
+
+    /// Tries to decode a varint from the inner buffer.
+    ///
+    /// Returns `None` if more bytes are needed.
+    fn decode(&mut self) -> Result<Option<u64>, ReadStreamVarintError> {
+        let Some(end) = self.buffer.iter().position(|byte| byte & 0x80 == 0) else {
+            if self.buffer.len() >= self.max_bytes {
+                return Err(ReadStreamVarintError::TooLong(self.max_bytes));
+            }
+
+            return Ok(None);
+        };
+
+        if end >= self.max_bytes {
+            return Err(ReadStreamVarintError::TooLong(self.max_bytes));
+        }
+
+        let mut n = 0u64;
+
+        for (i, byte) in self.buffer.drain(..=end).enumerate() {
+            let group = u64::from(byte & 0x7f);
+            let shift = 7 * i as u32;
+
+            if shift == 63 && group > 1 {
+                return Err(ReadStreamVarintError::Overflow);
+            }
+
+            n |= group << shift;
+        }
+
+        debug!("read varint {n} on {} bytes", end + 1);
+        Ok(Some(n))
+    }
+}
+
+impl Default for ReadStreamVarint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::read_varint::{ReadStreamVarintError, ReadStreamVarintResult},
+        runtimes::std::{impl_io_result, resume_until_done, ChunkedCursor},
+    };
+
+    use super::ReadStreamVarint;
+
+    impl_io_result!(ReadStreamVarintResult);
+
+    fn read(
+        mut varint: ReadStreamVarint,
+        input: &[u8],
+    ) -> (ReadStreamVarint, ReadStreamVarintResult) {
+        let mut reader = ChunkedCursor::with_pattern(input, []);
+        let result = resume_until_done(&mut reader, |arg| varint.resume(arg));
+
+        (varint, result)
+    }
+
+    #[test]
+    fn read_varint() {
+        let _ = env_logger::try_init();
+
+        let (varint, result) = read(ReadStreamVarint::with_capacity(1), &[0xac, 0x02, 0xff]);
+
+        match result {
+            ReadStreamVarintResult::Ok(300) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert!(varint.leftover().is_empty());
+
+        let (varint, result) = read(ReadStreamVarint::new(), &[0x01, 0xff]);
+
+        match result {
+            ReadStreamVarintResult::Ok(1) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(varint.leftover(), &[0xff]);
+    }
+
+    #[test]
+    fn read_varint_too_long() {
+        let _ = env_logger::try_init();
+
+        let varint = ReadStreamVarint::new().with_max_bytes(2);
+        let (_, result) = read(varint, &[0xff, 0xff, 0x01]);
+
+        match result {
+            ReadStreamVarintResult::Err(ReadStreamVarintError::TooLong(2)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -1,12 +1,18 @@
 //! I/O-free coroutine to read bytes into a buffer.
 
-use std::mem;
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    mem,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use log::{debug, trace};
 use thiserror::Error;
 
 use crate::io::{StreamIo, StreamOutput};
 
+use super::{cancel::Cancel, observer::StreamObserver, Coroutine, CoroutineResult};
+
 /// Errors that can occur during the coroutine progression.
 #[derive(Clone, Debug, Error)]
 pub enum ReadStreamError {
@@ -14,9 +20,26 @@ pub enum ReadStreamError {
     ///
     /// Occurs when the coroutine receives an I/O response from
     /// another coroutine, which should not happen if the runtime maps
-    /// correctly the arguments.
+    /// correctly the arguments, or when it receives a response while
+    /// not awaiting one.
     #[error("Invalid argument: expected {0}, got {1:?}")]
     InvalidArgument(&'static str, StreamIo),
+
+    /// The coroutine has been cancelled.
+    #[error("Read cancelled")]
+    Cancelled,
+
+    /// The shared read budget has been exhausted.
+    #[error("Read budget of {0} bytes exceeded")]
+    BudgetExceeded(usize),
+
+    /// The read buffer is empty.
+    ///
+    /// Occurs with a zero capacity or a zero next read limit: reading
+    /// into an empty buffer always returns 0 bytes, which would be
+    /// mistaken for the End Of File.
+    #[error("Cannot read into an empty buffer")]
+    EmptyBuffer,
 }
 
 /// Output emitted after a coroutine finishes its progression.
@@ -31,17 +54,147 @@ pub enum ReadStreamResult {
 
     /// The coroutine reached the End Of File.
     ///
-    /// Only the consumer can determine if its an error or not.
+    /// Only the consumer can determine if its an error or not. The
+    /// End Of File is reported by a read of 0 bytes, so it never
+    /// carries bytes: the ones of previous reads have all been
+    /// emitted with [`Self::Ok`].
     Eof,
 
     /// An error occured during the coroutine progression.
     Err(ReadStreamError),
 }
 
+/// Shared counter limiting the total amount of bytes that can be
+/// read.
+///
+/// A budget can be cloned and shared across multiple read
+/// coroutines, for example all the coroutines driven over the same
+/// connection. Each read is limited to the remaining budget, like
+/// with [`ReadStream::limit_next_read`], so that no byte read is ever
+/// discarded. Once the budget is exhausted, reads fail with
+/// [`ReadStreamError::BudgetExceeded`].
+#[derive(Clone, Debug)]
+pub struct ReadBudget {
+    remaining: Arc<AtomicUsize>,
+    ceiling: usize,
+}
+
+impl ReadBudget {
+    /// Creates a new budget allowing to read at most the given amount
+    /// of bytes.
+    pub fn new(ceiling: usize) -> Self {
+        let remaining = Arc::new(AtomicUsize::new(ceiling));
+        Self { remaining, ceiling }
+    }
+
+    /// Returns the total amount of bytes allowed by the budget.
+    pub fn ceiling(&self) -> usize {
+        self.ceiling
+    }
+
+    /// Returns the amount of bytes that can still be read.
+    pub fn remaining(&self) -> usize {
+        self.remaining.load(Ordering::SeqCst)
+    }
+
+    /// Consumes the given amount of bytes from the budget.
+    ///
+    /// Returns `false` if the budget has been exceeded, in which case
+    /// the budget is fully exhausted.
+    pub fn consume(&self, n: usize) -> bool {
+        let sub = |remaining: usize| remaining.checked_sub(n);
+        let consumed = self
+            .remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, sub);
+
+        if consumed.is_err() {
+            self.remaining.store(0, Ordering::SeqCst);
+        }
+
+        consumed.is_ok()
+    }
+
+    /// Consumes the given amount of bytes from the budget, at most
+    /// the remaining ones.
+    ///
+    /// Coroutines sharing the budget may read concurrently more than
+    /// what remains, in which case the budget is fully exhausted.
+    fn consume_saturating(&self, n: usize) {
+        let sub = |remaining: usize| Some(remaining.saturating_sub(n));
+        let _ = self
+            .remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, sub);
+    }
+}
+
+/// Read capacity adapting to the observed read sizes.
+///
+/// The capacity follows twice the exponential moving average of the
+/// amount of bytes returned by reads, within the given bounds. A
+/// connection whose reads are limited by the MTU ends up with a
+/// buffer close to twice the MTU, whereas reads filling the buffer
+/// make it grow back toward the maximum.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdaptiveCapacity {
+    min: usize,
+    max: usize,
+    average: Option<usize>,
+}
+
+impl AdaptiveCapacity {
+    /// Creates a new adaptive capacity bounded by the given minimum
+    /// and maximum.
+    pub fn new(min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+        Self {
+            min,
+            max,
+            average: None,
+        }
+    }
+
+    /// Returns the moving average of the observed read sizes, if any.
+    pub fn average(&self) -> Option<usize> {
+        self.average
+    }
+
+    /// Observes a read of the given amount of bytes, and returns the
+    /// adapted capacity.
+    ///
+    /// The average starts from half the given current capacity, so
+    /// that the capacity converges progressively.
+    fn observe(&mut self, n: usize, capacity: usize) -> usize {
+        let average = self.average.unwrap_or(capacity / 2);
+        let average = (average.saturating_mul(3).saturating_add(n)) / 4;
+        self.average = Some(average);
+        average.saturating_mul(2).clamp(self.min, self.max)
+    }
+}
+
 /// I/O-free coroutine to read bytes into a buffer.
+///
+/// The read buffer is allocated and zeroed once, then reused across
+/// reads when given back with [`Self::replace`]. Bytes past
+/// [`StreamOutput::bytes_count`] are never zeroed again: they may
+/// contain stale bytes from previous reads, which is why consumers
+/// should only rely on [`StreamOutput::bytes`].
+///
+/// With the `nightly` cfg, the read buffer is never zeroed: the
+/// runtime receives an empty buffer with enough capacity, and reads
+/// straight into it, see [`StreamIo::Read`]. Only reads limited below
+/// the buffer capacity, by [`Self::limit_next_read`] or by a
+/// [`ReadBudget`], still get a zeroed buffer of the limited length.
 #[derive(Debug)]
 pub struct ReadStream {
     buffer: Vec<u8>,
+    capacity: usize,
+    next_read_limit: Option<usize>,
+    budget: Option<ReadBudget>,
+    cancel: Option<Cancel>,
+    observer: Option<Arc<dyn StreamObserver>>,
+    adaptive: Option<AdaptiveCapacity>,
+    awaiting: bool,
 }
 
 impl ReadStream {
@@ -58,38 +211,185 @@ impl ReadStream {
 
     /// Creates a new coroutine to read bytes using a buffer with the
     /// given capacity.
+    ///
+    /// The capacity should be at least 1: reading with a zero
+    /// capacity fails with [`ReadStreamError::EmptyBuffer`].
     pub fn with_capacity(capacity: usize) -> Self {
         trace!("init coroutine to read bytes (capacity: {capacity})");
-        let buffer = vec![0; capacity];
-        Self { buffer }
+        Self {
+            buffer: Vec::new(),
+            capacity,
+            next_read_limit: None,
+            budget: None,
+            cancel: None,
+            observer: None,
+            adaptive: None,
+            awaiting: false,
+        }
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Limits the amount of bytes read by the coroutine with the
+    /// given shared budget.
+    pub fn with_budget(mut self, budget: ReadBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Notifies the given observer of each successful read.
+    pub fn with_observer(mut self, observer: Arc<dyn StreamObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Adapts the buffer capacity to the observed read sizes.
+    ///
+    /// The capacity given at construction is the starting point, see
+    /// [`AdaptiveCapacity`]. The allocation of a buffer given back
+    /// with [`Self::replace`] is shrunk once it exceeds twice the
+    /// adapted capacity.
+    pub fn with_adaptive_capacity(mut self, adaptive: AdaptiveCapacity) -> Self {
+        self.adaptive = Some(adaptive);
+        self
     }
 
     /// Returns the buffer capacity.
     pub fn capacity(&self) -> usize {
-        self.buffer.capacity()
+        self.capacity
     }
 
     /// Shortens the buffer to the given length.
+    ///
+    /// The capacity is permanently shrunk, see
+    /// [`Self::limit_next_read`] to cap a single read instead.
     pub fn truncate(&mut self, len: usize) {
-        self.buffer.truncate(len);
-        self.buffer.shrink_to(len);
+        if len < self.capacity {
+            self.capacity = len;
+            self.buffer.truncate(len);
+            self.buffer.shrink_to(len);
+        }
+    }
+
+    /// Reads at most the given amount of bytes on the next read.
+    ///
+    /// The buffer handed to the runtime is shortened to the given
+    /// length, whereas its allocation is kept as is: the buffer grows
+    /// back to the coroutine capacity when given back with
+    /// [`Self::replace`].
+    pub fn limit_next_read(&mut self, max: usize) {
+        self.next_read_limit = Some(max);
     }
 
     /// Replaces the inner buffer with the given one.
+    ///
+    /// The buffer is resized to the coroutine capacity. Only the
+    /// bytes added by the resize are zeroed, existing bytes are kept
+    /// as is. With the `nightly` cfg, the buffer is cleared instead,
+    /// only its allocation is kept.
     pub fn replace(&mut self, mut buffer: Vec<u8>) {
-        buffer.fill(0);
+        #[cfg(not(nightly))]
+        buffer.resize(self.capacity, 0);
+        #[cfg(nightly)]
+        buffer.clear();
+
+        if self.adaptive.is_some() && buffer.capacity() / 2 > self.capacity {
+            buffer.shrink_to(self.capacity);
+        }
+
         self.buffer = buffer;
     }
 
+    /// Copies the read bytes of the given output into a right-sized
+    /// vec, then gives the output buffer back to the coroutine.
+    ///
+    /// This emits the data while keeping the allocation of the read
+    /// buffer for the next read, which suits hot loops. See
+    /// [`Self::replace`] to give back a buffer without copying.
+    pub fn recycle(&mut self, output: StreamOutput) -> Vec<u8> {
+        let bytes = output.bytes().to_vec();
+        self.replace(output.buffer);
+        bytes
+    }
+
+    /// Takes the inner buffer, prepared to read at most the given
+    /// amount of bytes.
+    #[cfg(not(nightly))]
+    fn take_buffer(&mut self, len: usize) -> Vec<u8> {
+        let mut buffer = mem::take(&mut self.buffer);
+
+        if buffer.is_empty() {
+            buffer.resize(self.capacity, 0);
+        }
+
+        buffer.truncate(len);
+        buffer
+    }
+
+    /// Takes the inner buffer, prepared to read at most the given
+    /// amount of bytes.
+    ///
+    /// The buffer is left empty when its capacity matches the given
+    /// length, otherwise it is zeroed up to the given length.
+    #[cfg(nightly)]
+    fn take_buffer(&mut self, len: usize) -> Vec<u8> {
+        let mut buffer = mem::take(&mut self.buffer);
+        buffer.clear();
+        buffer.reserve_exact(self.capacity);
+
+        if buffer.capacity() > len {
+            buffer.resize(len, 0);
+        }
+
+        buffer
+    }
+
     /// Makes the read progress.
     pub fn resume(&mut self, arg: Option<StreamIo>) -> ReadStreamResult {
         let Some(arg) = arg else {
-            let mut buffer = vec![0; self.buffer.capacity()];
-            mem::swap(&mut buffer, &mut self.buffer);
+            if let Some(cancel) = &self.cancel {
+                if cancel.is_cancelled() {
+                    return ReadStreamResult::Err(ReadStreamError::Cancelled);
+                }
+            }
+
+            let mut len = self.capacity;
+
+            if let Some(max) = self.next_read_limit.take() {
+                len = len.min(max);
+            }
+
+            if let Some(budget) = &self.budget {
+                let remaining = budget.remaining();
+
+                if remaining == 0 {
+                    return ReadStreamResult::Err(ReadStreamError::BudgetExceeded(
+                        budget.ceiling(),
+                    ));
+                }
+
+                len = len.min(remaining);
+            }
+
+            if len == 0 {
+                return ReadStreamResult::Err(ReadStreamError::EmptyBuffer);
+            }
+
+            let buffer = self.take_buffer(len);
+
             trace!("wants I/O to read bytes");
+            self.awaiting = true;
             return ReadStreamResult::Io(StreamIo::Read(Err(buffer)));
         };
 
+        if !self.awaiting {
+            return ReadStreamResult::Err(ReadStreamError::InvalidArgument("no response", arg));
+        }
+
         trace!("resume after reading bytes");
 
         let StreamIo::Read(io) = arg else {
@@ -101,16 +401,55 @@ impl ReadStream {
             Err(buffer) => return ReadStreamResult::Io(StreamIo::Read(Err(buffer))),
         };
 
+        self.awaiting = false;
+
         match output.bytes_count {
-            0 => ReadStreamResult::Eof,
+            0 => {
+                // keeps the buffer for the next read
+                self.replace(output.buffer);
+                ReadStreamResult::Eof
+            }
             n => {
-                debug!("read {n}/{} bytes", output.buffer.capacity());
+                debug!("read {n}/{} bytes", self.capacity);
+
+                if let Some(budget) = &self.budget {
+                    budget.consume_saturating(n);
+                }
+
+                if let Some(observer) = &self.observer {
+                    observer.on_read(n);
+                }
+
+                if let Some(adaptive) = &mut self.adaptive {
+                    let capacity = adaptive.observe(n, self.capacity);
+
+                    if capacity != self.capacity {
+                        debug!("adapt read capacity from {} to {capacity}", self.capacity);
+                        self.capacity = capacity;
+                    }
+                }
+
                 ReadStreamResult::Ok(output)
             }
         }
     }
 }
 
+/// The End Of File is emitted as a successful [`None`] output.
+impl Coroutine for ReadStream {
+    type Output = Option<StreamOutput>;
+    type Error = ReadStreamError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error> {
+        match ReadStream::resume(self, arg) {
+            ReadStreamResult::Ok(output) => CoroutineResult::Ok(Some(output)),
+            ReadStreamResult::Io(io) => CoroutineResult::Io(io),
+            ReadStreamResult::Eof => CoroutineResult::Ok(None),
+            ReadStreamResult::Err(err) => CoroutineResult::Err(err),
+        }
+    }
+}
+
 impl Default for ReadStream {
     fn default() -> Self {
         Self::new()
@@ -119,11 +458,12 @@ impl Default for ReadStream {
 
 #[cfg(test)]
 mod tests {
-    use std::io::{BufReader, Read as _};
+    use std::io::Read as _;
 
     use crate::{
-        coroutines::read::ReadStreamResult,
-        io::{StreamIo, StreamOutput},
+        coroutines::read::{AdaptiveCapacity, ReadBudget, ReadStreamError, ReadStreamResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+        runtimes::std::ChunkedCursor,
     };
 
     use super::ReadStream;
@@ -132,7 +472,7 @@ mod tests {
     fn read() {
         let _ = env_logger::try_init();
 
-        let mut reader = BufReader::new("abcdef".as_bytes());
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), []);
 
         let mut read = ReadStream::with_capacity(4);
         let mut arg = None;
@@ -141,7 +481,7 @@ mod tests {
             match read.resume(arg.take()) {
                 ReadStreamResult::Ok(output) => break output,
                 ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
                     let output = StreamOutput {
                         buffer,
                         bytes_count,
@@ -160,7 +500,7 @@ mod tests {
             match read.resume(arg.take()) {
                 ReadStreamResult::Ok(output) => break output,
                 ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
                     let output = StreamOutput {
                         buffer,
                         bytes_count,
@@ -179,6 +519,72 @@ mod tests {
             match read.resume(arg.take()) {
                 ReadStreamResult::Eof => break,
                 ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn read_final_partial_read() {
+        let _ = env_logger::try_init();
+
+        let mut read = ReadStream::with_capacity(4);
+        let mut bytes = Vec::new();
+
+        // a final partial read of 1 byte immediately followed by EOF
+        for bytes_count in [4, 1, 0] {
+            let mut buffer = match read.resume(None) {
+                ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => buffer,
+                other => unreachable!("Unexpected result: {other:?}"),
+            };
+
+            init_read_buffer(&mut buffer)[..bytes_count].fill(b'a' + bytes.len() as u8);
+
+            let output = StreamOutput {
+                buffer,
+                bytes_count,
+            };
+
+            match read.resume(Some(StreamIo::Read(Ok(output)))) {
+                ReadStreamResult::Ok(output) => bytes.extend(read.recycle(output)),
+                // EOF is reported on its own read, which carries no byte
+                ReadStreamResult::Eof => assert_eq!(bytes_count, 0),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(bytes, b"aaaae");
+    }
+
+    #[test]
+    fn read_reuses_buffer() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), []);
+
+        let mut read = ReadStream::with_capacity(4);
+        let mut arg = None;
+        let mut ptr = None;
+        let mut outputs = Vec::new();
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => {
+                    outputs.push(output.bytes().to_vec());
+                    read.replace(output.buffer);
+                }
+                ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    // the same allocation is handed back on every read
+                    assert_eq!(init_read_buffer(&mut buffer).len(), 4);
+                    assert_eq!(*ptr.get_or_insert(buffer.as_ptr()), buffer.as_ptr());
+
                     let bytes_count = reader.read(&mut buffer).unwrap();
                     let output = StreamOutput {
                         buffer,
@@ -186,8 +592,262 @@ mod tests {
                     };
                     arg = Some(StreamIo::Read(Ok(output)))
                 }
+                ReadStreamResult::Eof => break,
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        // stale bytes from the first read are not exposed
+        assert_eq!(outputs, [b"abcd".to_vec(), b"ef".to_vec()]);
+    }
+
+    #[test]
+    fn read_budget_exceeded() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdefgh".as_bytes(), []);
+        let budget = ReadBudget::new(6);
+
+        let mut read = ReadStream::with_capacity(4).with_budget(budget.clone());
+        let mut arg = None;
+
+        let output = loop {
+            match read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => break output,
+                ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(output.bytes(), b"abcd");
+        assert_eq!(budget.remaining(), 2);
+
+        // the next read is clamped to the remaining budget, then reads
+        // fail once the budget is exhausted
+        let mut read = ReadStream::with_capacity(4).with_budget(budget.clone());
+        let mut outputs = Vec::new();
+
+        loop {
+            match read.resume(arg.take()) {
+                ReadStreamResult::Err(ReadStreamError::BudgetExceeded(6)) => break,
+                ReadStreamResult::Ok(output) => {
+                    outputs.push(output.bytes().to_vec());
+                    read.replace(output.buffer);
+                }
+                ReadStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(outputs, [b"ef"]);
+        assert_eq!(budget.remaining(), 0);
+
+        // no byte read has been discarded
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"gh");
+    }
+
+    #[test]
+    fn read_zero_capacity() {
+        let _ = env_logger::try_init();
+
+        let mut read = ReadStream::with_capacity(0);
+
+        match read.resume(None) {
+            ReadStreamResult::Err(ReadStreamError::EmptyBuffer) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        let mut read = ReadStream::with_capacity(4);
+        read.limit_next_read(0);
+
+        match read.resume(None) {
+            ReadStreamResult::Err(ReadStreamError::EmptyBuffer) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn read_recycle() {
+        let _ = env_logger::try_init();
+
+        let mut reader = ChunkedCursor::with_pattern("abcdef".as_bytes(), []);
+        let mut read = ReadStream::with_capacity(64);
+        let mut ptrs = Vec::new();
+
+        for expected in [&b"abcdef"[..], b""] {
+            let mut buffer = match read.resume(None) {
+                ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => buffer,
                 other => unreachable!("Unexpected result: {other:?}"),
+            };
+
+            ptrs.push(buffer.as_ptr());
+            assert_eq!(buffer.capacity(), 64);
+
+            let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+
+            if bytes_count == 0 {
+                break;
             }
+
+            let output = StreamOutput {
+                buffer,
+                bytes_count,
+            };
+
+            let output = match read.resume(Some(StreamIo::Read(Ok(output)))) {
+                ReadStreamResult::Ok(output) => output,
+                other => unreachable!("Unexpected result: {other:?}"),
+            };
+
+            let bytes = read.recycle(output);
+            assert_eq!(bytes, expected);
+            assert_eq!(bytes.capacity(), bytes.len());
+        }
+
+        // the recycled buffer is handed out again
+        assert_eq!(ptrs[0], ptrs[1]);
+    }
+
+    #[test]
+    fn read_adaptive_capacity() {
+        let _ = env_logger::try_init();
+
+        let adaptive = AdaptiveCapacity::new(64, 1024);
+        let mut read = ReadStream::with_capacity(1024).with_adaptive_capacity(adaptive);
+        let mut lens = Vec::new();
+        let mut arg = None;
+
+        // simulates MTU-limited reads of 100 bytes
+        for _ in 0..16 {
+            let mut buffer = match read.resume(arg.take()) {
+                ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => buffer,
+                other => unreachable!("Unexpected result: {other:?}"),
+            };
+
+            lens.push(init_read_buffer(&mut buffer).len());
+
+            let output = StreamOutput {
+                buffer,
+                bytes_count: 100,
+            };
+
+            let output = match read.resume(Some(StreamIo::Read(Ok(output)))) {
+                ReadStreamResult::Ok(output) => output,
+                other => unreachable!("Unexpected result: {other:?}"),
+            };
+
+            read.replace(output.buffer);
+        }
+
+        assert_eq!(lens[0], 1024);
+        assert!(lens.windows(2).all(|lens| lens[1] <= lens[0]));
+
+        // converges toward twice the average read size
+        let last = *lens.last().unwrap();
+        assert!(last < 256, "requested {last} bytes");
+        assert!(last >= 200);
+    }
+
+    #[test]
+    fn read_extra_response() {
+        let _ = env_logger::try_init();
+
+        let response = || {
+            let output = StreamOutput {
+                buffer: b"abc".to_vec(),
+                bytes_count: 3,
+            };
+            StreamIo::Read(Ok(output))
+        };
+
+        // before the first request
+        let mut read = ReadStream::with_capacity(8);
+
+        match read.resume(Some(response())) {
+            ReadStreamResult::Err(ReadStreamError::InvalidArgument("no response", _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // while awaiting a response
+        let buffer = match read.resume(None) {
+            ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => buffer,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        let output = StreamOutput {
+            buffer,
+            bytes_count: 0,
+        };
+
+        match read.resume(Some(StreamIo::Read(Ok(output)))) {
+            ReadStreamResult::Eof => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // after the termination
+        match read.resume(Some(response())) {
+            ReadStreamResult::Err(ReadStreamError::InvalidArgument("no response", _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[cfg(nightly)]
+    #[test]
+    fn read_uninitialized_buffer() {
+        let _ = env_logger::try_init();
+
+        let mut read = ReadStream::with_capacity(4);
+
+        // the buffer is not zeroed, only allocated
+        let mut buffer = match read.resume(None) {
+            ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => buffer,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert!(buffer.is_empty());
+        assert!(buffer.capacity() >= 4);
+
+        buffer.extend(b"ab");
+
+        let output = StreamOutput {
+            buffer,
+            bytes_count: 2,
+        };
+
+        match read.resume(Some(StreamIo::Read(Ok(output)))) {
+            ReadStreamResult::Ok(output) => assert_eq!(read.recycle(output), b"ab"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // the buffer given back is cleared, not zeroed
+        match read.resume(None) {
+            ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => assert!(buffer.is_empty()),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // a limited read gets a zeroed buffer of the limited length
+        let mut read = ReadStream::with_capacity(4);
+        read.limit_next_read(2);
+
+        match read.resume(None) {
+            ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => assert_eq!(buffer, [0; 2]),
+            other => unreachable!("Unexpected result: {other:?}"),
         }
     }
 }
@@ -0,0 +1,127 @@
+//! I/O-free coroutine to shut down the write half of a stream.
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{Coroutine, CoroutineResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ShutdownStreamError {
+    /// The coroutine received an invalid argument.
+    ///
+    /// Occurs when the coroutine receives an I/O response from
+    /// another coroutine, which should not happen if the runtime maps
+    /// correctly the arguments, or when it receives a response while
+    /// not awaiting one.
+    #[error("Invalid argument: expected {0}, got {1:?}")]
+    InvalidArgument(&'static str, StreamIo),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+pub type ShutdownStreamResult = CoroutineResult<(), ShutdownStreamError>;
+
+/// I/O-free coroutine to shut down the write half of a stream.
+///
+/// Some protocols signal the end of a message by closing the write
+/// half of the connection, like HTTP/1.0 request bodies. Once shut
+/// down, the peer reaches the End Of File while the stream can still
+/// be read.
+///
+/// Not all streams support shutdown: runtimes that cannot shut down
+/// the stream fail with an unsupported error, see
+/// [`StreamIo::Shutdown`].
+#[derive(Debug, Default)]
+pub struct ShutdownStream {
+    /// Whether a shutdown request has been emitted.
+    awaiting: bool,
+}
+
+impl ShutdownStream {
+    /// Creates a new coroutine to shut down a stream.
+    pub fn new() -> Self {
+        trace!("init coroutine to shut down stream");
+        Self { awaiting: false }
+    }
+
+    /// Makes the shutdown progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> ShutdownStreamResult {
+        let Some(arg) = arg else {
+            trace!("wants I/O to shut down stream");
+            self.awaiting = true;
+            return ShutdownStreamResult::Io(StreamIo::Shutdown(false));
+        };
+
+        if !self.awaiting {
+            return ShutdownStreamResult::Err(ShutdownStreamError::InvalidArgument(
+                "no response",
+                arg,
+            ));
+        }
+
+        match arg {
+            StreamIo::Shutdown(true) => {
+                self.awaiting = false;
+                debug!("shut down stream");
+                ShutdownStreamResult::Ok(())
+            }
+            StreamIo::Shutdown(false) => ShutdownStreamResult::Io(StreamIo::Shutdown(false)),
+            arg => {
+                let err = ShutdownStreamError::InvalidArgument("shutdown output", arg);
+                ShutdownStreamResult::Err(err)
+            }
+        }
+    }
+}
+
+impl Coroutine for ShutdownStream {
+    type Output = ();
+    type Error = ShutdownStreamError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> ShutdownStreamResult {
+        ShutdownStream::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            shutdown::ShutdownStreamResult,
+            write::{WriteStream, WriteStreamResult},
+        },
+        io::StreamIo,
+    };
+
+    use super::{ShutdownStream, ShutdownStreamError};
+
+    #[test]
+    fn shutdown_invalid_argument() {
+        let _ = env_logger::try_init();
+
+        let mut shutdown = ShutdownStream::new();
+
+        match shutdown.resume(None) {
+            ShutdownStreamResult::Io(StreamIo::Shutdown(false)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // responses of other coroutines are rejected
+        let io = match WriteStream::new(b"abc".to_vec()).resume(None) {
+            WriteStreamResult::Io(io) => io,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        match shutdown.resume(Some(io)) {
+            ShutdownStreamResult::Err(ShutdownStreamError::InvalidArgument(..)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        match shutdown.resume(Some(StreamIo::Shutdown(true))) {
+            ShutdownStreamResult::Ok(()) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! I/O-free coroutine to discard a given amount of bytes.
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    cancel::Cancel,
+    read::{ReadStream, ReadStreamError, ReadStreamResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum SkipStreamError {
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF, skipped only {0}/{1} bytes")]
+    UnexpectedEof(usize, usize),
+
+    /// Error from the [`ReadStream`] coroutine.
+    #[error(transparent)]
+    Read(#[from] ReadStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum SkipStreamResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the amount of skipped bytes.
+    Ok(usize),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(SkipStreamError),
+}
+
+/// I/O-free coroutine to discard a given amount of bytes, like
+/// padding or ignored fields.
+///
+/// Read bytes are dropped straight away, and the same read buffer is
+/// reused across reads, so that skipping allocates only once. Reads
+/// are limited to the remaining bytes, so that no byte is skipped
+/// past the given amount.
+#[derive(Debug)]
+pub struct SkipStream {
+    /// The inner read coroutine.
+    read: ReadStream,
+
+    /// The amount of bytes skipped so far.
+    skipped: usize,
+
+    /// The amount of bytes to skip.
+    count: usize,
+}
+
+impl SkipStream {
+    /// Creates a new coroutine to skip the given amount of bytes
+    /// using a buffer with [`ReadStream::DEFAULT_CAPACITY`] capacity
+    /// at most.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(count: usize) -> Self {
+        Self::with_capacity(ReadStream::DEFAULT_CAPACITY, count)
+    }
+
+    /// Creates a new coroutine to skip the given amount of bytes
+    /// using a buffer with the given capacity at most.
+    pub fn with_capacity(capacity: usize, count: usize) -> Self {
+        trace!("init coroutine to skip {count} bytes (capacity: {capacity})");
+        Self {
+            read: ReadStream::with_capacity(capacity.min(count)),
+            skipped: 0,
+            count,
+        }
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.read = self.read.with_cancel(cancel);
+        self
+    }
+
+    /// Returns the amount of bytes skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<StreamIo>) -> SkipStreamResult {
+        loop {
+            if arg.is_none() && self.skipped >= self.count {
+                debug!("skipped {} bytes", self.skipped);
+                break SkipStreamResult::Ok(self.skipped);
+            }
+
+            let remaining = self.count.saturating_sub(self.skipped);
+
+            if remaining < self.read.capacity() {
+                self.read.limit_next_read(remaining);
+            }
+
+            let output = match self.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => output,
+                ReadStreamResult::Io(io) => break SkipStreamResult::Io(io),
+                ReadStreamResult::Err(err) => break SkipStreamResult::Err(err.into()),
+                ReadStreamResult::Eof => {
+                    let err = SkipStreamError::UnexpectedEof(self.skipped, self.count);
+                    break SkipStreamResult::Err(err);
+                }
+            };
+
+            self.skipped += output.bytes_count;
+            self.read.replace(output.buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use crate::{
+        coroutines::skip::{SkipStreamError, SkipStreamResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+    };
+
+    use super::SkipStream;
+
+    fn skip(mut skip: SkipStream, reader: &mut &[u8]) -> (SkipStreamResult, usize) {
+        let mut arg = None;
+        let mut ptrs = Vec::new();
+
+        let result = loop {
+            match skip.resume(arg.take()) {
+                SkipStreamResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    ptrs.push(buffer.as_ptr());
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                result => break result,
+            }
+        };
+
+        // the same buffer is reused across reads
+        ptrs.dedup();
+        assert_eq!(ptrs.len(), 1);
+
+        (result, skip.skipped())
+    }
+
+    #[test]
+    fn skip_single_read() {
+        let _ = env_logger::try_init();
+
+        let mut reader = &b"\0\0\0payload"[..];
+
+        match skip(SkipStream::new(3), &mut reader).0 {
+            SkipStreamResult::Ok(n) => assert_eq!(n, 3),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(reader, b"payload");
+    }
+
+    #[test]
+    fn skip_several_reads() {
+        let _ = env_logger::try_init();
+
+        let mut reader = &b"0123456789payload"[..];
+
+        match skip(SkipStream::with_capacity(4, 10), &mut reader).0 {
+            SkipStreamResult::Ok(n) => assert_eq!(n, 10),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(reader, b"payload");
+
+        let mut reader = &b"01234"[..];
+
+        match skip(SkipStream::with_capacity(4, 10), &mut reader) {
+            (SkipStreamResult::Err(SkipStreamError::UnexpectedEof(5, 10)), 5) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
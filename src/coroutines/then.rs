@@ -0,0 +1,199 @@
+//! I/O-free coroutine chaining two coroutines sequentially.
+
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{read_to_end::ReadStreamToEnd, write::WriteStream, Coroutine, CoroutineResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ThenError<E1, E2> {
+    /// The coroutine was resumed after its termination.
+    #[error("Coroutine resumed after termination")]
+    Terminated,
+
+    /// Error from the first coroutine.
+    #[error(transparent)]
+    First(E1),
+
+    /// Error from the second coroutine.
+    #[error(transparent)]
+    Second(E2),
+}
+
+/// The coroutine state.
+#[derive(Debug)]
+enum State<O> {
+    /// Running the first coroutine.
+    First,
+
+    /// Running the second coroutine.
+    ///
+    /// Contains the output of the first coroutine.
+    Second(O),
+
+    /// Both coroutines terminated.
+    Done,
+}
+
+/// I/O-free coroutine chaining two coroutines sequentially.
+///
+/// The first coroutine runs to completion, then the second one starts
+/// with a [`None`] argument. Each argument is routed to the active
+/// coroutine, so that responses always reach the coroutine that
+/// emitted the matching request. Both outputs are emitted together
+/// once the second coroutine terminates.
+///
+/// See [`Coroutine::then`].
+#[derive(Debug)]
+pub struct Then<A: Coroutine, B> {
+    /// The first coroutine.
+    first: A,
+
+    /// The second coroutine.
+    second: B,
+
+    /// The current state.
+    state: State<A::Output>,
+}
+
+impl<A: Coroutine, B: Coroutine> Then<A, B> {
+    /// Chains the given coroutines.
+    pub fn new(first: A, second: B) -> Self {
+        trace!("init coroutine chaining two coroutines");
+        Self {
+            first,
+            second,
+            state: State::First,
+        }
+    }
+
+    /// Returns the inner coroutines.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl Then<WriteStream, ReadStreamToEnd> {
+    /// Chains a write with a read to end, for example to send a
+    /// request then read its response until the peer closes the
+    /// stream.
+    pub fn write_then_read(write: WriteStream, read: ReadStreamToEnd) -> Self {
+        Self::new(write, read)
+    }
+}
+
+impl<A: Coroutine, B: Coroutine> Coroutine for Then<A, B> {
+    type Output = (A::Output, B::Output);
+    type Error = ThenError<A::Error, B::Error>;
+
+    fn resume(&mut self, mut arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error> {
+        match self.state {
+            State::First => match self.first.resume(arg.take()) {
+                CoroutineResult::Ok(output) => {
+                    debug!("first coroutine terminated, starting the second one");
+                    self.state = State::Second(output);
+                }
+                CoroutineResult::Io(io) => return CoroutineResult::Io(io),
+                CoroutineResult::Err(err) => return CoroutineResult::Err(ThenError::First(err)),
+            },
+            State::Second(_) => (),
+            State::Done => return CoroutineResult::Err(ThenError::Terminated),
+        }
+
+        let output = match self.second.resume(arg) {
+            CoroutineResult::Ok(output) => output,
+            CoroutineResult::Io(io) => return CoroutineResult::Io(io),
+            CoroutineResult::Err(err) => return CoroutineResult::Err(ThenError::Second(err)),
+        };
+
+        match mem::replace(&mut self.state, State::Done) {
+            State::Second(first) => CoroutineResult::Ok((first, output)),
+            State::First | State::Done => unreachable!("the second coroutine runs last"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read as _, Write as _};
+
+    use crate::{
+        coroutines::{
+            read_exact::{ReadStreamExact, ReadStreamExactError},
+            read_to_end::ReadStreamToEnd,
+            write::WriteStream,
+            Coroutine, CoroutineResult,
+        },
+        io::{init_read_buffer, StreamIo, StreamOutput},
+    };
+
+    use super::{Then, ThenError};
+
+    #[test]
+    fn write_then_read() {
+        let _ = env_logger::try_init();
+
+        // the duplex mock reads from the cursor and writes into the vec
+        let mut reader = Cursor::new(b"PONG\r\n".to_vec());
+        let mut writer = Vec::new();
+
+        let write = WriteStream::new(b"PING\r\n".to_vec());
+        let read = ReadStreamToEnd::with_capacity(4);
+        let mut ping = Then::write_then_read(write, read);
+        let mut arg = None;
+
+        let (written, pong) = loop {
+            match ping.resume(arg.take()) {
+                CoroutineResult::Ok(output) => break output,
+                CoroutineResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // nothing is read before the request is written
+                    assert_eq!(reader.position(), 0);
+
+                    let bytes_count = writer.write(&buffer).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                CoroutineResult::Io(StreamIo::Read(Err(mut buffer))) => {
+                    let bytes_count = reader.read(init_read_buffer(&mut buffer)).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Read(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(written.bytes(), b"PING\r\n");
+        assert_eq!(writer, b"PING\r\n");
+        assert_eq!(pong, b"PONG\r\n");
+
+        match ping.resume(None) {
+            CoroutineResult::Err(ThenError::Terminated) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn then_second_error() {
+        let _ = env_logger::try_init();
+
+        let mut then = ReadStreamExact::new(4).then(ReadStreamExact::new(4));
+
+        match then.feed_all(b"PINGPO") {
+            Err(ThenError::Second(ReadStreamExactError::UnexpectedEof(2, 4, bytes))) => {
+                assert_eq!(bytes, b"PO")
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
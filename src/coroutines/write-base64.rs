@@ -0,0 +1,211 @@
+//! I/O-free coroutine to base64-encode bytes and write them into a
+//! stream.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use base64::Engine;
+use log::trace;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::{
+    read_base64_line::Base64Alphabet,
+    write::{WriteStream, WriteStreamError, WriteStreamResult},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum WriteStreamBase64Error {
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF, wrote only {0}/{1} bytes")]
+    UnexpectedEof(usize, usize),
+
+    /// Error from the [`WriteStream`] coroutine.
+    #[error(transparent)]
+    Write(#[from] WriteStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum WriteStreamBase64Result {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the total amount of encoded bytes written, line
+    /// endings included.
+    Ok(usize),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(WriteStreamBase64Error),
+}
+
+/// I/O-free coroutine to base64-encode bytes and write them into a
+/// stream.
+///
+/// The payload is encoded on the first resume, using the standard
+/// alphabet and no line wrapping by default.
+#[derive(Debug)]
+pub struct WriteStreamBase64 {
+    /// The payload to encode.
+    payload: Vec<u8>,
+
+    /// The base64 alphabet.
+    alphabet: Base64Alphabet,
+
+    /// The column at which lines are wrapped, if any.
+    wrap: Option<usize>,
+
+    /// The inner write coroutine, once the payload has been encoded.
+    write: Option<WriteStream>,
+
+    /// The total amount of bytes to write.
+    total: usize,
+}
+
+impl WriteStreamBase64 {
+    /// The line width of MIME base64 bodies, see [RFC 2045 section
+    /// 6.8].
+    ///
+    /// [RFC 2045 section 6.8]: https://www.rfc-editor.org/rfc/rfc2045#section-6.8
+    pub const MIME_LINE_WIDTH: usize = 76;
+
+    /// Creates a new coroutine to base64-encode and write the given
+    /// payload.
+    pub fn new(payload: impl Into<Vec<u8>>) -> Self {
+        let payload = payload.into();
+        trace!("init coroutine to write base64 ({} bytes)", payload.len());
+        Self {
+            payload,
+            alphabet: Base64Alphabet::default(),
+            wrap: None,
+            write: None,
+            total: 0,
+        }
+    }
+
+    /// Encodes the payload using the given alphabet.
+    pub fn with_alphabet(mut self, alphabet: Base64Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Wraps lines at the given column width with CRLF.
+    ///
+    /// See [`Self::MIME_LINE_WIDTH`] for MIME bodies. A width of 0
+    /// disables line wrapping.
+    pub fn with_wrap(mut self, width: usize) -> Self {
+        self.wrap = if width == 0 { None } else { Some(width) };
+        self
+    }
+
+    /// Encodes the given payload.
+    pub fn encode(payload: &[u8], alphabet: Base64Alphabet, wrap: Option<usize>) -> Vec<u8> {
+        let encoded = alphabet.engine(true).encode(payload).into_bytes();
+
+        let Some(width) = wrap else {
+            return encoded;
+        };
+
+        let mut bytes = Vec::with_capacity(encoded.len() + encoded.len() / width * 2);
+
+        for (i, line) in encoded.chunks(width).enumerate() {
+            if i > 0 {
+                bytes.extend(b"\r\n");
+            }
+
+            bytes.extend(line);
+        }
+
+        bytes
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamBase64Result {
+        let write = match &mut self.write {
+            Some(write) => write,
+            None => {
+                let payload = mem::take(&mut self.payload);
+                let bytes = Self::encode(&payload, self.alphabet, self.wrap);
+                self.total = bytes.len();
+                self.write.insert(WriteStream::new(bytes))
+            }
+        };
+
+        match write.resume(arg) {
+            WriteStreamResult::Ok(_) => WriteStreamBase64Result::Ok(self.total),
+            WriteStreamResult::Io(io) => WriteStreamBase64Result::Io(io),
+            WriteStreamResult::Err(err) => WriteStreamBase64Result::Err(err.into()),
+            WriteStreamResult::Eof | WriteStreamResult::Backpressure(_) => {
+                let err = WriteStreamBase64Error::UnexpectedEof(write.written(), self.total);
+                WriteStreamBase64Result::Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use crate::{
+        coroutines::{read_base64_line::Base64Alphabet, write_base64::WriteStreamBase64Result},
+        io::{StreamIo, StreamOutput},
+    };
+
+    use super::WriteStreamBase64;
+
+    fn write(mut write: WriteStreamBase64) -> Vec<u8> {
+        let mut writer = Vec::new();
+        let mut arg = None;
+
+        let bytes_count = loop {
+            match write.resume(arg.take()) {
+                WriteStreamBase64Result::Ok(bytes_count) => break bytes_count,
+                WriteStreamBase64Result::Io(StreamIo::Write(Err(buffer))) => {
+                    // simulates partial writes of 10 bytes max
+                    let bytes_count = writer.write(&buffer[..buffer.len().min(10)]).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(bytes_count, writer.len());
+        writer
+    }
+
+    #[test]
+    fn write_base64_unwrapped() {
+        let _ = env_logger::try_init();
+
+        let encoded = write(WriteStreamBase64::new(b"\0user\0password".to_vec()));
+        assert_eq!(encoded, b"AHVzZXIAcGFzc3dvcmQ=");
+
+        let write =
+            WriteStreamBase64::new([0xfb, 0xff, 0xbf]).with_alphabet(Base64Alphabet::UrlSafe);
+        assert_eq!(self::write(write), b"-_-_");
+    }
+
+    #[test]
+    fn write_base64_wrapped() {
+        let _ = env_logger::try_init();
+
+        let write =
+            WriteStreamBase64::new([b'a'; 60]).with_wrap(WriteStreamBase64::MIME_LINE_WIDTH);
+        let encoded = self::write(write);
+
+        let mut expected = "YWFh".repeat(19);
+        expected.push_str("\r\nYWFh");
+
+        assert_eq!(encoded, expected.as_bytes());
+    }
+}
@@ -0,0 +1,257 @@
+//! I/O-free coroutine to write a body prefixed by its length, where
+//! the body is pushed incrementally before being written.
+
+use alloc::{vec, vec::Vec};
+use core::mem;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::write::{WriteStream, WriteStreamError, WriteStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum WriteStreamDeferredLengthError {
+    /// The coroutine has been resumed before being finalized.
+    #[error("Cannot write deferred length body: coroutine not finalized")]
+    NotFinalized,
+
+    /// The body length does not fit into the length prefix.
+    #[error("Body length {0} does not fit into a {1}-byte length prefix")]
+    LengthOverflow(usize, usize),
+
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF, wrote only {0}/{1} bytes")]
+    UnexpectedEof(usize, usize),
+
+    /// Error from the [`WriteStream`] coroutine.
+    #[error(transparent)]
+    Write(#[from] WriteStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum WriteStreamDeferredLengthResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the total amount of bytes written, length prefix
+    /// included.
+    Ok(usize),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(WriteStreamDeferredLengthError),
+}
+
+/// The width of a length prefix.
+///
+/// Length prefixes are encoded in big endian (network byte order).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PrefixWidth {
+    /// 1-byte length prefix.
+    U8,
+
+    /// 2-byte length prefix.
+    U16,
+
+    /// 4-byte length prefix.
+    #[default]
+    U32,
+
+    /// 8-byte length prefix.
+    U64,
+}
+
+impl PrefixWidth {
+    /// Returns the width in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+            Self::U64 => 8,
+        }
+    }
+
+    /// Encodes the given length, or returns `None` if it does not fit
+    /// into the prefix.
+    pub fn encode(&self, len: usize) -> Option<Vec<u8>> {
+        let bytes = match self {
+            Self::U8 => u8::try_from(len).ok()?.to_be_bytes().to_vec(),
+            Self::U16 => u16::try_from(len).ok()?.to_be_bytes().to_vec(),
+            Self::U32 => u32::try_from(len).ok()?.to_be_bytes().to_vec(),
+            Self::U64 => u64::try_from(len).ok()?.to_be_bytes().to_vec(),
+        };
+
+        Some(bytes)
+    }
+}
+
+/// I/O-free coroutine to write a body prefixed by its length, where
+/// the body is pushed incrementally before being written.
+///
+/// Body chunks are pushed with [`Self::push`], then
+/// [`Self::finalize`] computes the length prefix. Only then the
+/// coroutine can be resumed.
+#[derive(Debug)]
+pub struct WriteStreamDeferredLength {
+    /// The width of the length prefix.
+    width: PrefixWidth,
+
+    /// The buffer containing the reserved length prefix followed by
+    /// the body.
+    buffer: Vec<u8>,
+
+    /// The inner write coroutine, available once finalized.
+    write: Option<WriteStream>,
+
+    /// The total amount of bytes to write.
+    total: usize,
+}
+
+impl WriteStreamDeferredLength {
+    /// Creates a new coroutine with an empty body and the given
+    /// length prefix width.
+    pub fn new(width: PrefixWidth) -> Self {
+        trace!("init coroutine to write deferred length body ({width:?})");
+        Self {
+            width,
+            buffer: vec![0; width.size()],
+            write: None,
+            total: 0,
+        }
+    }
+
+    /// Pushes the given bytes at the end of the body.
+    ///
+    /// Pushing bytes after finalization has no effect.
+    pub fn push(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Returns the current body length.
+    pub fn body_len(&self) -> usize {
+        self.buffer.len().saturating_sub(self.width.size())
+    }
+
+    /// Computes the length prefix and prepares the coroutine for
+    /// writing.
+    pub fn finalize(&mut self) -> Result<(), WriteStreamDeferredLengthError> {
+        if self.write.is_some() {
+            return Ok(());
+        }
+
+        let len = self.body_len();
+
+        let Some(prefix) = self.width.encode(len) else {
+            let err = WriteStreamDeferredLengthError::LengthOverflow(len, self.width.size());
+            return Err(err);
+        };
+
+        debug!("finalize body of {len} bytes");
+
+        let mut buffer = mem::take(&mut self.buffer);
+        buffer[..prefix.len()].copy_from_slice(&prefix);
+
+        self.total = buffer.len();
+        self.write = Some(WriteStream::new(buffer));
+        Ok(())
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamDeferredLengthResult {
+        let Some(write) = &mut self.write else {
+            let err = WriteStreamDeferredLengthError::NotFinalized;
+            return WriteStreamDeferredLengthResult::Err(err);
+        };
+
+        match write.resume(arg) {
+            WriteStreamResult::Ok(_) => WriteStreamDeferredLengthResult::Ok(self.total),
+            WriteStreamResult::Io(io) => WriteStreamDeferredLengthResult::Io(io),
+            WriteStreamResult::Err(err) => WriteStreamDeferredLengthResult::Err(err.into()),
+            WriteStreamResult::Eof | WriteStreamResult::Backpressure(_) => {
+                let err =
+                    WriteStreamDeferredLengthError::UnexpectedEof(write.written(), self.total);
+                WriteStreamDeferredLengthResult::Err(err)
+            }
+        }
+    }
+}
+
+impl Default for WriteStreamDeferredLength {
+    fn default() -> Self {
+        Self::new(PrefixWidth::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use crate::{
+        coroutines::write_deferred_length::{
+            PrefixWidth, WriteStreamDeferredLengthError, WriteStreamDeferredLengthResult,
+        },
+        io::{StreamIo, StreamOutput},
+    };
+
+    use super::WriteStreamDeferredLength;
+
+    #[test]
+    fn write_deferred_length() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+
+        let mut write = WriteStreamDeferredLength::new(PrefixWidth::U16);
+
+        let mut arg = None;
+
+        match write.resume(arg.take()) {
+            WriteStreamDeferredLengthResult::Err(WriteStreamDeferredLengthError::NotFinalized) => {}
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        write.push(*b"hello");
+        write.push(*b" ");
+        write.push(*b"world");
+        write.finalize().unwrap();
+
+        let bytes_count = loop {
+            match write.resume(arg.take()) {
+                WriteStreamDeferredLengthResult::Ok(bytes_count) => break bytes_count,
+                WriteStreamDeferredLengthResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // simulates partial writes of 5 bytes max
+                    let bytes_count = writer.write(&buffer[..buffer.len().min(5)]).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(bytes_count, 13);
+        assert_eq!(writer, b"\x00\x0bhello world");
+    }
+
+    #[test]
+    fn write_deferred_length_overflow() {
+        let _ = env_logger::try_init();
+
+        let mut write = WriteStreamDeferredLength::new(PrefixWidth::U8);
+        write.push(vec![0; 256]);
+
+        match write.finalize() {
+            Err(WriteStreamDeferredLengthError::LengthOverflow(256, 1)) => {}
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! I/O-free coroutine to write an HTTP/1.1 request.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use log::trace;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::write::{WriteStream, WriteStreamError, WriteStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum WriteStreamHttpRequestError {
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF, wrote only {0}/{1} bytes")]
+    UnexpectedEof(usize, usize),
+
+    /// A part of the request contains a forbidden character, which
+    /// would allow request splitting or header injection.
+    ///
+    /// Contains the name of the part and its value.
+    #[error("Invalid HTTP request {0} {1:?}")]
+    InvalidRequest(&'static str, String),
+
+    /// Error from the [`WriteStream`] coroutine.
+    #[error(transparent)]
+    Write(#[from] WriteStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum WriteStreamHttpRequestResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the total amount of bytes written.
+    Ok(usize),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(WriteStreamHttpRequestError),
+}
+
+/// The HTTP request written by the coroutine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HttpRequest {
+    /// The request method, like `GET` or `POST`.
+    pub method: String,
+
+    /// The request target, like `/index.html`.
+    pub path: String,
+
+    /// The headers, in order of appearance.
+    ///
+    /// Header names are written as given, without normalizing their
+    /// case: HTTP/1.1 header names are case-insensitive.
+    pub headers: Vec<(String, String)>,
+
+    /// The optional body.
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    /// Creates a new request without headers nor body.
+    pub fn new(method: impl ToString, path: impl ToString) -> Self {
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// Adds the given header.
+    pub fn header(mut self, key: impl ToString, val: impl ToString) -> Self {
+        self.headers.push((key.to_string(), val.to_string()));
+        self
+    }
+
+    /// Sets the body.
+    pub fn body(mut self, body: impl IntoIterator<Item = u8>) -> Self {
+        self.body = Some(body.into_iter().collect());
+        self
+    }
+
+    /// Serializes the request into the HTTP/1.1 wire format.
+    ///
+    /// A `Content-Length` header is automatically added if a body is
+    /// set and the header is not already present.
+    ///
+    /// Fails with [`WriteStreamHttpRequestError::InvalidRequest`] if
+    /// any part of the head contains CR or LF, if the method or the
+    /// path contains a space, or if a header name contains a colon.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, WriteStreamHttpRequestError> {
+        validate("method", &self.method, &['\r', '\n', ' '])?;
+        validate("path", &self.path, &['\r', '\n', ' '])?;
+
+        for (key, val) in &self.headers {
+            validate("header name", key, &['\r', '\n', ':'])?;
+            validate("header value", val, &['\r', '\n'])?;
+        }
+
+        let mut bytes = format!("{} {} HTTP/1.1\r\n", self.method, self.path).into_bytes();
+
+        for (key, val) in &self.headers {
+            bytes.extend(format!("{key}: {val}\r\n").into_bytes());
+        }
+
+        if let Some(body) = &self.body {
+            let has_len = self
+                .headers
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case("Content-Length"));
+
+            if !has_len {
+                bytes.extend(format!("Content-Length: {}\r\n", body.len()).into_bytes());
+            }
+        }
+
+        bytes.extend(b"\r\n");
+
+        if let Some(body) = &self.body {
+            bytes.extend(body);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Ensures that the given part of the request does not contain any of
+/// the given forbidden characters.
+fn validate(
+    part: &'static str,
+    value: &str,
+    forbidden: &[char],
+) -> Result<(), WriteStreamHttpRequestError> {
+    if value.contains(forbidden) {
+        let err = WriteStreamHttpRequestError::InvalidRequest(part, value.to_string());
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// I/O-free coroutine to write an HTTP/1.1 request.
+#[derive(Debug)]
+pub struct WriteStreamHttpRequest {
+    /// The inner write coroutine.
+    write: WriteStream,
+
+    /// The total amount of bytes to write.
+    total: usize,
+
+    /// The validation error of the request, if any.
+    invalid: Option<WriteStreamHttpRequestError>,
+}
+
+impl WriteStreamHttpRequest {
+    /// Creates a new coroutine to write the given request.
+    ///
+    /// An invalid request is reported on every resume, before
+    /// writing anything, see [`HttpRequest::to_bytes`].
+    pub fn new(request: &HttpRequest) -> Self {
+        let (bytes, invalid) = match request.to_bytes() {
+            Ok(bytes) => (bytes, None),
+            Err(err) => (Vec::new(), Some(err)),
+        };
+
+        let total = bytes.len();
+        trace!("init coroutine to write HTTP request ({total} bytes)");
+        let write = WriteStream::new(bytes);
+
+        Self {
+            write,
+            total,
+            invalid,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamHttpRequestResult {
+        if let Some(err) = &self.invalid {
+            return WriteStreamHttpRequestResult::Err(err.clone());
+        }
+
+        match self.write.resume(arg) {
+            WriteStreamResult::Ok(_) => WriteStreamHttpRequestResult::Ok(self.total),
+            WriteStreamResult::Io(io) => WriteStreamHttpRequestResult::Io(io),
+            WriteStreamResult::Err(err) => WriteStreamHttpRequestResult::Err(err.into()),
+            WriteStreamResult::Eof | WriteStreamResult::Backpressure(_) => {
+                let err =
+                    WriteStreamHttpRequestError::UnexpectedEof(self.write.written(), self.total);
+                WriteStreamHttpRequestResult::Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use crate::{
+        coroutines::write_http_request::{
+            HttpRequest, WriteStreamHttpRequestError, WriteStreamHttpRequestResult,
+        },
+        io::{StreamIo, StreamOutput},
+    };
+
+    use super::WriteStreamHttpRequest;
+
+    fn write(request: &HttpRequest) -> Vec<u8> {
+        let mut writer = Vec::new();
+
+        let mut write = WriteStreamHttpRequest::new(request);
+        let mut arg = None;
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamHttpRequestResult::Ok(_) => break writer,
+                WriteStreamHttpRequestResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // simulates partial writes of 8 bytes max
+                    let bytes_count = writer.write(&buffer[..buffer.len().min(8)]).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn write_get() {
+        let _ = env_logger::try_init();
+
+        let request = HttpRequest::new("GET", "/index.html")
+            .header("Host", "localhost")
+            .header("Accept", "*/*");
+
+        let expected = b"GET /index.html HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\n\r\n";
+
+        assert_eq!(write(&request), expected);
+    }
+
+    #[test]
+    fn write_post() {
+        let _ = env_logger::try_init();
+
+        let request = HttpRequest::new("POST", "/submit")
+            .header("Host", "localhost")
+            .body(*b"key=value");
+
+        let expected =
+            b"POST /submit HTTP/1.1\r\nHost: localhost\r\nContent-Length: 9\r\n\r\nkey=value";
+
+        assert_eq!(write(&request), expected);
+    }
+
+    #[test]
+    fn write_header_case() {
+        let _ = env_logger::try_init();
+
+        // names are kept as given, the body length is not duplicated
+        let request = HttpRequest::new("POST", "/submit")
+            .header("x-custom", "a")
+            .header("content-length", "9")
+            .body(*b"key=value");
+
+        let expected =
+            b"POST /submit HTTP/1.1\r\nx-custom: a\r\ncontent-length: 9\r\n\r\nkey=value";
+
+        assert_eq!(write(&request), expected);
+    }
+
+    #[test]
+    fn write_injection() {
+        let _ = env_logger::try_init();
+
+        let requests = [
+            (HttpRequest::new("GET", "/ HTTP/1.1\r\nHost: evil"), "path"),
+            (HttpRequest::new("GET\n", "/"), "method"),
+            (
+                HttpRequest::new("GET", "/").header("Host:", "evil"),
+                "header name",
+            ),
+            (
+                HttpRequest::new("GET", "/").header("Host", "a\r\nX-Injected: 1"),
+                "header value",
+            ),
+        ];
+
+        for (request, part) in requests {
+            let mut write = WriteStreamHttpRequest::new(&request);
+
+            // the error is reported again on the next resumes
+            for _ in 0..2 {
+                match write.resume(None) {
+                    WriteStreamHttpRequestResult::Err(
+                        WriteStreamHttpRequestError::InvalidRequest(invalid, _),
+                    ) => assert_eq!(invalid, part),
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            }
+        }
+    }
+}
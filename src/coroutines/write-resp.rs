@@ -0,0 +1,144 @@
+//! I/O-free coroutine to write a command serialized as a RESP array
+//! of bulk strings.
+
+use alloc::{format, vec::Vec};
+
+use log::trace;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::write::{WriteStream, WriteStreamError, WriteStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum WriteStreamRespError {
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF, wrote only {0}/{1} bytes")]
+    UnexpectedEof(usize, usize),
+
+    /// Error from the [`WriteStream`] coroutine.
+    #[error(transparent)]
+    Write(#[from] WriteStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum WriteStreamRespResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the total amount of bytes written.
+    Ok(usize),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(WriteStreamRespError),
+}
+
+/// I/O-free coroutine to write a command serialized as a RESP array
+/// of bulk strings.
+///
+/// This is the standard format used by clients to send requests to
+/// a Redis server: `*<N>\r\n` followed by `$<len>\r\n<arg>\r\n` for
+/// each argument.
+#[derive(Debug)]
+pub struct WriteStreamResp {
+    /// The inner write coroutine.
+    write: WriteStream,
+
+    /// The total amount of bytes to write.
+    total: usize,
+}
+
+impl WriteStreamResp {
+    /// Creates a new coroutine to write the given command arguments.
+    pub fn new<A: AsRef<[u8]>>(args: impl IntoIterator<Item = A>) -> Self {
+        let bytes = Self::serialize(args);
+        let total = bytes.len();
+        trace!("init coroutine to write RESP command ({total} bytes)");
+        let write = WriteStream::new(bytes);
+        Self { write, total }
+    }
+
+    /// Serializes the given arguments as a RESP array of bulk
+    /// strings.
+    pub fn serialize<A: AsRef<[u8]>>(args: impl IntoIterator<Item = A>) -> Vec<u8> {
+        let args: Vec<A> = args.into_iter().collect();
+        let mut bytes = format!("*{}\r\n", args.len()).into_bytes();
+
+        for arg in args {
+            let arg = arg.as_ref();
+            bytes.extend(format!("${}\r\n", arg.len()).into_bytes());
+            bytes.extend(arg);
+            bytes.extend(b"\r\n");
+        }
+
+        bytes
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamRespResult {
+        match self.write.resume(arg) {
+            WriteStreamResult::Ok(_) => WriteStreamRespResult::Ok(self.total),
+            WriteStreamResult::Io(io) => WriteStreamRespResult::Io(io),
+            WriteStreamResult::Err(err) => WriteStreamRespResult::Err(err.into()),
+            WriteStreamResult::Eof | WriteStreamResult::Backpressure(_) => {
+                let err = WriteStreamRespError::UnexpectedEof(self.write.written(), self.total);
+                WriteStreamRespResult::Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use crate::{
+        coroutines::write_resp::WriteStreamRespResult,
+        io::{StreamIo, StreamOutput},
+    };
+
+    use super::WriteStreamResp;
+
+    #[test]
+    fn write_resp() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+
+        let mut write = WriteStreamResp::new(["SET", "key", "value"]);
+        let mut arg = None;
+
+        let bytes_count = loop {
+            match write.resume(arg.take()) {
+                WriteStreamRespResult::Ok(bytes_count) => break bytes_count,
+                WriteStreamRespResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // simulates partial writes of 3 bytes max
+                    let bytes_count = writer.write(&buffer[..buffer.len().min(3)]).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        let expected = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+
+        assert_eq!(bytes_count, expected.len());
+        assert_eq!(writer, expected);
+    }
+
+    #[test]
+    fn serialize_owned_args() {
+        let args = vec![b"GET".to_vec(), b"key".to_vec()];
+        let bytes = WriteStreamResp::serialize(args);
+        assert_eq!(bytes, b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n");
+    }
+}
@@ -0,0 +1,155 @@
+//! I/O-free coroutine to write an SMTP DATA message body.
+
+use alloc::vec::Vec;
+
+use log::trace;
+use thiserror::Error;
+
+use crate::io::StreamIo;
+
+use super::write::{WriteStream, WriteStreamError, WriteStreamResult};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum WriteStreamSmtpDataError {
+    /// The coroutine unexpectedly reached the End Of File.
+    #[error("Unexpected EOF, wrote only {0}/{1} bytes")]
+    UnexpectedEof(usize, usize),
+
+    /// Error from the [`WriteStream`] coroutine.
+    #[error(transparent)]
+    Write(#[from] WriteStreamError),
+}
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum WriteStreamSmtpDataResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the total amount of bytes written, stuffing dots and
+    /// terminator included.
+    Ok(usize),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// An error occured during the coroutine progression.
+    Err(WriteStreamSmtpDataError),
+}
+
+/// I/O-free coroutine to write an SMTP DATA message body.
+///
+/// Line endings are normalized to CRLF, lines starting with a dot
+/// get an extra leading dot (dot-stuffing) and the `<CRLF>.<CRLF>` end
+/// of data terminator is appended, see [RFC 5321 section 4.5.2].
+///
+/// [RFC 5321 section 4.5.2]: https://www.rfc-editor.org/rfc/rfc5321#section-4.5.2
+#[derive(Debug)]
+pub struct WriteStreamSmtpData {
+    /// The inner write coroutine.
+    write: WriteStream,
+
+    /// The total amount of bytes to write.
+    total: usize,
+}
+
+impl WriteStreamSmtpData {
+    /// Creates a new coroutine to write the given message body.
+    pub fn new(body: impl AsRef<[u8]>) -> Self {
+        let bytes = Self::encode(body);
+        let total = bytes.len();
+        trace!("init coroutine to write SMTP data ({total} bytes)");
+        let write = WriteStream::new(bytes);
+        Self { write, total }
+    }
+
+    /// Encodes the given message body for the SMTP DATA command.
+    pub fn encode(body: impl AsRef<[u8]>) -> Vec<u8> {
+        let body = body.as_ref();
+        let mut bytes = Vec::with_capacity(body.len() + 5);
+        let mut line_start = true;
+
+        for (i, &byte) in body.iter().enumerate() {
+            if line_start && byte == b'.' {
+                bytes.push(b'.');
+            }
+
+            if byte == b'\n' && (i == 0 || body[i - 1] != b'\r') {
+                bytes.push(b'\r');
+            }
+
+            line_start = byte == b'\n';
+            bytes.push(byte);
+        }
+
+        if !bytes.is_empty() && !bytes.ends_with(b"\r\n") {
+            bytes.extend(b"\r\n");
+        }
+
+        bytes.extend(b".\r\n");
+        bytes
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamSmtpDataResult {
+        match self.write.resume(arg) {
+            WriteStreamResult::Ok(_) => WriteStreamSmtpDataResult::Ok(self.total),
+            WriteStreamResult::Io(io) => WriteStreamSmtpDataResult::Io(io),
+            WriteStreamResult::Err(err) => WriteStreamSmtpDataResult::Err(err.into()),
+            WriteStreamResult::Eof | WriteStreamResult::Backpressure(_) => {
+                let err = WriteStreamSmtpDataError::UnexpectedEof(self.write.written(), self.total);
+                WriteStreamSmtpDataResult::Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use crate::{
+        coroutines::write_smtp_data::WriteStreamSmtpDataResult,
+        io::{StreamIo, StreamOutput},
+    };
+
+    use super::WriteStreamSmtpData;
+
+    #[test]
+    fn write_smtp_data() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+
+        let mut write = WriteStreamSmtpData::new("Subject: dots\n\n.leading dot\r\n..\nend");
+        let mut arg = None;
+
+        let bytes_count = loop {
+            match write.resume(arg.take()) {
+                WriteStreamSmtpDataResult::Ok(bytes_count) => break bytes_count,
+                WriteStreamSmtpDataResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // simulates partial writes of 3 bytes max
+                    let bytes_count = writer.write(&buffer[..buffer.len().min(3)]).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        let expected = b"Subject: dots\r\n\r\n..leading dot\r\n...\r\nend\r\n.\r\n";
+
+        assert_eq!(bytes_count, expected.len());
+        assert_eq!(writer, expected);
+    }
+
+    #[test]
+    fn encode_empty_body() {
+        assert_eq!(WriteStreamSmtpData::encode(""), b".\r\n");
+        assert_eq!(WriteStreamSmtpData::encode("a\r\n"), b"a\r\n.\r\n");
+    }
+}
@@ -0,0 +1,258 @@
+//! I/O-free coroutine to write several buffers into a stream without
+//! concatenating them.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use log::{debug, trace};
+
+use crate::io::StreamIo;
+
+use super::{cancel::Cancel, write::WriteStreamError, Coroutine, CoroutineResult};
+
+/// Output emitted after a coroutine finishes its progression.
+#[derive(Clone, Debug)]
+pub enum WriteStreamVectoredResult {
+    /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the total amount of bytes written.
+    Ok(usize),
+
+    /// A stream I/O needs to be performed to make the coroutine
+    /// progress.
+    Io(StreamIo),
+
+    /// The coroutine reached the End Of File.
+    ///
+    /// Only the consumer can determine if its an error or not.
+    Eof,
+
+    /// An error occured during the coroutine progression.
+    Err(WriteStreamError),
+}
+
+/// I/O-free coroutine to write several buffers into a stream without
+/// concatenating them.
+///
+/// The coroutine emits [`StreamIo::WriteVectored`] requests, so that
+/// runtimes can write all buffers at once. Runtimes are allowed to
+/// perform partial writes: written buffers are dropped, and the
+/// partially written one is advanced before emitting a write request
+/// for the remaining bytes.
+#[derive(Debug, Default)]
+pub struct WriteStreamVectored {
+    buffers: Vec<Vec<u8>>,
+    total: usize,
+    written: usize,
+    cancel: Option<Cancel>,
+    awaiting: bool,
+}
+
+impl WriteStreamVectored {
+    /// Creates a new coroutine to write the given buffers, in order.
+    pub fn new(mut buffers: Vec<Vec<u8>>) -> Self {
+        buffers.retain(|buffer| !buffer.is_empty());
+        let total = buffers.iter().map(Vec::len).sum();
+        trace!(
+            "init coroutine for writing {total} bytes from {} buffers",
+            buffers.len()
+        );
+        Self {
+            buffers,
+            total,
+            written: 0,
+            cancel: None,
+            awaiting: false,
+        }
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Returns the amount of bytes written so far.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Makes the write progress.
+    pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamVectoredResult {
+        let Some(arg) = arg else {
+            if self.written >= self.total {
+                return WriteStreamVectoredResult::Ok(self.written);
+            }
+
+            return self.want_write();
+        };
+
+        if !self.awaiting {
+            let err = WriteStreamError::InvalidArgument("no response", arg);
+            return WriteStreamVectoredResult::Err(err);
+        }
+
+        trace!("resume after writing vectored bytes");
+
+        let StreamIo::WriteVectored(io) = arg else {
+            let err = WriteStreamError::InvalidArgument("write vectored output", arg);
+            return WriteStreamVectoredResult::Err(err);
+        };
+
+        let output = match io {
+            Ok(output) => output,
+            Err(buffers) => {
+                return WriteStreamVectoredResult::Io(StreamIo::WriteVectored(Err(buffers)))
+            }
+        };
+
+        self.awaiting = false;
+
+        if output.bytes_count == 0 {
+            // keeps the buffers left to write
+            self.buffers = output.buffers;
+            return WriteStreamVectoredResult::Eof;
+        }
+
+        debug!("wrote {} bytes", output.bytes_count);
+
+        self.buffers = output.buffers;
+        self.advance(output.bytes_count);
+        self.written += output.bytes_count;
+
+        if self.written >= self.total {
+            return WriteStreamVectoredResult::Ok(self.written);
+        }
+
+        debug!("{} remaining bytes to write", self.total - self.written);
+        self.want_write()
+    }
+
+    /// Emits a write request for the remaining buffers.
+    fn want_write(&mut self) -> WriteStreamVectoredResult {
+        if let Some(cancel) = &self.cancel {
+            if cancel.is_cancelled() {
+                return WriteStreamVectoredResult::Err(WriteStreamError::Cancelled);
+            }
+        }
+
+        let buffers = mem::take(&mut self.buffers);
+        trace!("wants I/O to write {} buffers", buffers.len());
+        self.awaiting = true;
+        WriteStreamVectoredResult::Io(StreamIo::WriteVectored(Err(buffers)))
+    }
+
+    /// Drops the first written buffers, then advances the partially
+    /// written one.
+    fn advance(&mut self, mut n: usize) {
+        let mut written = 0;
+
+        for buffer in &self.buffers {
+            if buffer.len() > n {
+                break;
+            }
+
+            n -= buffer.len();
+            written += 1;
+        }
+
+        self.buffers.drain(..written);
+
+        if let Some(buffer) = self.buffers.first_mut() {
+            buffer.drain(..n);
+        }
+    }
+}
+
+/// The End Of File is emitted as a [`WriteStreamError::UnexpectedEof`]
+/// error, since the buffers could not all be written.
+impl Coroutine for WriteStreamVectored {
+    type Output = usize;
+    type Error = WriteStreamError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error> {
+        match WriteStreamVectored::resume(self, arg) {
+            WriteStreamVectoredResult::Ok(n) => CoroutineResult::Ok(n),
+            WriteStreamVectoredResult::Io(io) => CoroutineResult::Io(io),
+            WriteStreamVectoredResult::Eof => {
+                let remaining = self.total - self.written;
+                CoroutineResult::Err(WriteStreamError::UnexpectedEof(remaining))
+            }
+            WriteStreamVectoredResult::Err(err) => CoroutineResult::Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{IoSlice, Write};
+
+    use crate::{
+        coroutines::write_vectored::WriteStreamVectoredResult,
+        io::{StreamIo, StreamVectoredOutput},
+    };
+
+    use super::WriteStreamVectored;
+
+    /// Writer accepting 2 bytes at a time, across buffer boundaries.
+    struct TwoBytesWriter(Vec<u8>);
+
+    impl Write for TwoBytesWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.write_vectored(&[IoSlice::new(buf)])
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize> {
+            let bytes: Vec<u8> = bufs
+                .iter()
+                .flat_map(|buf| buf.iter())
+                .take(2)
+                .copied()
+                .collect();
+            self.0.extend(&bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_vectored() {
+        let _ = env_logger::try_init();
+
+        let mut writer = TwoBytesWriter(Vec::new());
+
+        let buffers = vec![
+            b"HDR".to_vec(),
+            Vec::new(),
+            b"\x05".to_vec(),
+            b"hello".to_vec(),
+        ];
+        let mut write = WriteStreamVectored::new(buffers);
+        let mut arg = None;
+        let mut requests = 0;
+
+        let written = loop {
+            match write.resume(arg.take()) {
+                WriteStreamVectoredResult::Ok(n) => break n,
+                WriteStreamVectoredResult::Io(StreamIo::WriteVectored(Err(buffers))) => {
+                    requests += 1;
+                    let slices: Vec<_> = buffers.iter().map(|b| IoSlice::new(b)).collect();
+                    let bytes_count = writer.write_vectored(&slices).unwrap();
+                    let output = StreamVectoredOutput {
+                        buffers,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::WriteVectored(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(written, 9);
+        assert_eq!(requests, 5);
+        assert_eq!(writer.0, b"HDR\x05hello");
+    }
+}
@@ -1,10 +1,15 @@
 //! I/O-free coroutine to write bytes into a stream.
 
+use alloc::{sync::Arc, vec::Vec};
+use core::mem;
+
 use log::{debug, trace};
 use thiserror::Error;
 
 use crate::io::{StreamIo, StreamOutput};
 
+use super::{cancel::Cancel, observer::StreamObserver, Coroutine, CoroutineResult};
+
 /// Errors that can occur during the coroutine progression.
 #[derive(Clone, Debug, Error)]
 pub enum WriteStreamError {
@@ -12,15 +17,36 @@ pub enum WriteStreamError {
     ///
     /// Occurs when the coroutine receives an I/O response from
     /// another coroutine, which should not happen if the runtime maps
-    /// correctly the arguments.
+    /// correctly the arguments, or when it receives a response while
+    /// not awaiting one.
     #[error("Invalid argument: expected {0}, got {1:?}")]
     InvalidArgument(&'static str, StreamIo),
+
+    /// The coroutine has been cancelled.
+    #[error("Write cancelled")]
+    Cancelled,
+
+    /// The payload is longer than the padded length.
+    ///
+    /// Contains the length of the payload and the padded length.
+    #[error("Payload of {0} bytes exceeds the padded length of {1} bytes")]
+    PaddingExceeded(usize, usize),
+
+    /// The stream reached the End Of File before all bytes were
+    /// written.
+    ///
+    /// Only emitted by the generic [`Coroutine`] implementations,
+    /// contains the amount of bytes left to write.
+    #[error("Unexpected EOF with {0} bytes left to write")]
+    UnexpectedEof(usize),
 }
 
 /// Output emitted after a coroutine finishes its progression.
 #[derive(Clone, Debug)]
 pub enum WriteStreamResult {
     /// The coroutine has successfully terminated its progression.
+    ///
+    /// Contains the original buffer, with all its bytes written.
     Ok(StreamOutput),
 
     /// A stream I/O needs to be performed to make the coroutine
@@ -29,53 +55,232 @@ pub enum WriteStreamResult {
 
     /// The coroutine reached the End Of File.
     ///
-    /// Only the consumer can determine if its an error or not.
+    /// Only the consumer can determine if its an error or not. The
+    /// bytes left to write are kept, see
+    /// [`WriteStream::unacknowledged`].
     Eof,
 
+    /// The write made no progress, because the sink is full.
+    ///
+    /// Only emitted when enabled with
+    /// [`WriteStream::with_backpressure`], instead of [`Self::Eof`].
+    /// Contains the amount of remaining bytes to write, which are kept
+    /// by the coroutine: resuming it without argument emits a write
+    /// request for them again, which lets producers back off before
+    /// retrying.
+    Backpressure(usize),
+
     /// An error occured during the coroutine progression.
     Err(WriteStreamError),
 }
 
 /// I/O-free coroutine to write bytes into a stream.
+///
+/// Runtimes are allowed to perform partial writes: the coroutine
+/// keeps emitting write requests for the remaining bytes until all of
+/// them are written. The buffer of the remaining bytes is kept across
+/// payloads, so that a coroutine reused with
+/// [`WriteStream::replace_buffer`] does not allocate once warmed up.
 #[derive(Debug, Default)]
 pub struct WriteStream {
     bytes: Vec<u8>,
+    written: usize,
+    spare: Vec<u8>,
+    cancel: Option<Cancel>,
+    backpressure: bool,
+    pending: Option<Vec<u8>>,
+    drained: Option<usize>,
+    undrained: Vec<u8>,
+    observer: Option<Arc<dyn StreamObserver>>,
+    padding: Option<(usize, u8)>,
+    awaiting: bool,
 }
 
 impl WriteStream {
     /// Creates a new coroutine to write the given bytes.
     pub fn new(bytes: Vec<u8>) -> Self {
         trace!("init coroutine for writing {} bytes", bytes.len());
-        Self { bytes }
-    }
-
-    // /// Replaces the inner bytes with the given one.
-    // pub fn replace(&mut self, bytes: impl IntoIterator<Item = u8>) {
-    //     *self = Self::new(bytes.into_iter()collect());
-    // }
-
-    // /// Adds the given bytes the to inner buffer.
-    // pub fn extend(&mut self, more_bytes: impl IntoIterator<Item = u8>) {
-    //     match &mut self.bytes {
-    //         Some(bytes) => {
-    //             let prev_len = bytes.len();
-    //             bytes.extend(more_bytes);
-    //             let next_len = bytes.len();
-    //             let n = next_len - prev_len;
-    //             trace!("prepare {prev_len}+{n} additional bytes to be written");
-    //         }
-    //         None => self.replace(more_bytes),
-    //     }
-    // }
+        Self {
+            bytes,
+            written: 0,
+            spare: Vec::new(),
+            cancel: None,
+            backpressure: false,
+            pending: None,
+            drained: None,
+            undrained: Vec::new(),
+            observer: None,
+            padding: None,
+            awaiting: false,
+        }
+    }
+
+    /// Makes the coroutine cancellable with the given shared handle.
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Reports writes of zero bytes as
+    /// [`WriteStreamResult::Backpressure`] instead of
+    /// [`WriteStreamResult::Eof`].
+    ///
+    /// This suits non-blocking sinks, for which a zero-byte write
+    /// means that the sink buffer is full rather than closed.
+    pub fn with_backpressure(mut self) -> Self {
+        self.backpressure = true;
+        self
+    }
+
+    /// Notifies the given observer of each successful write.
+    pub fn with_observer(mut self, observer: Arc<dyn StreamObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Pads the payload with the given byte up to the given total
+    /// length.
+    ///
+    /// The padding is applied before the first write request of each
+    /// payload, which suits fixed-length records. Payloads longer than
+    /// the total length fail with [`WriteStreamError::PaddingExceeded`].
+    pub fn with_padding(mut self, total_len: usize, pad: u8) -> Self {
+        self.padding = Some((total_len, pad));
+        self
+    }
+
+    /// Tracks the bytes confirmed written, so that they can be
+    /// drained with [`Self::drain_written`].
+    pub fn with_drain_written(mut self) -> Self {
+        self.drained = Some(0);
+        self
+    }
+
+    /// Returns the bytes confirmed written since the last call.
+    ///
+    /// This lets loggers record the exact outbound bytes
+    /// incrementally. Bytes confirmed by the last write, which
+    /// terminates the coroutine, can still be drained afterwards.
+    ///
+    /// Requires [`Self::with_drain_written`], otherwise no byte is
+    /// returned.
+    pub fn drain_written(&mut self) -> Vec<u8> {
+        let Some(drained) = &mut self.drained else {
+            return Vec::new();
+        };
+
+        let mut bytes = mem::take(&mut self.undrained);
+        let end = self.written.min(self.bytes.len());
+
+        if *drained < end {
+            bytes.extend_from_slice(&self.bytes[*drained..end]);
+            *drained = end;
+        }
+
+        bytes
+    }
+
+    /// Returns the amount of bytes written so far.
+    ///
+    /// Only bytes acknowledged by the runtime are counted. After an
+    /// I/O error, this is the offset at which a resumable upload can
+    /// restart, with a fresh coroutine writing the
+    /// [`Self::unacknowledged`] bytes.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns the bytes not yet acknowledged by the runtime.
+    ///
+    /// The bytes of the first write request are moved to the runtime,
+    /// so until it responds, only the bytes extended meanwhile are
+    /// returned. Once the first response has been received, the
+    /// coroutine holds all the bytes again, and this method returns
+    /// all the ones not yet written.
+    pub fn unacknowledged(&self) -> &[u8] {
+        &self.bytes[self.written.min(self.bytes.len())..]
+    }
+
+    /// Replaces the inner bytes with the given ones.
+    ///
+    /// The coroutine is reset, so that it can be reused to write
+    /// another payload.
+    pub fn replace(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.bytes.clear();
+        self.bytes.extend(bytes);
+        self.reset();
+        trace!("replace bytes to write with {} bytes", self.bytes.len());
+    }
+
+    /// Replaces the inner bytes with the given buffer, keeping its
+    /// allocation.
+    ///
+    /// Same as [`Self::replace`], except that giving back the buffer
+    /// of the [`WriteStreamResult::Ok`] output lets the next
+    /// [`Self::replace`] reuse it, so that writing many small payloads
+    /// with the same coroutine does not allocate.
+    pub fn replace_buffer(&mut self, buffer: Vec<u8>) {
+        self.bytes = buffer;
+        self.reset();
+        trace!("replace bytes to write with {} bytes", self.bytes.len());
+    }
+
+    /// Adds the given bytes to the inner buffer.
+    ///
+    /// Only bytes not yet handed to the runtime are extended: bytes
+    /// added while a write is in progress are written after the ones
+    /// of the current request. Once the coroutine terminated, this
+    /// method behaves like [`Self::replace`].
+    pub fn extend(&mut self, more_bytes: impl IntoIterator<Item = u8>) {
+        if self.written > 0 && self.written >= self.bytes.len() {
+            self.written = 0;
+            self.bytes.clear();
+            self.reset_drained();
+        }
+
+        let prev_len = self.bytes.len();
+        self.bytes.extend(more_bytes);
+        let n = self.bytes.len() - prev_len;
+        trace!("prepare {prev_len}+{n} additional bytes to be written");
+    }
 
     /// Makes the write progress.
     pub fn resume(&mut self, arg: Option<StreamIo>) -> WriteStreamResult {
         let Some(arg) = arg else {
-            let bytes = self.bytes.drain(..).collect();
+            if let Some(err) = self.check_cancelled() {
+                return WriteStreamResult::Err(err);
+            }
+
+            // retries the write that made no progress
+            if let Some(bytes) = self.pending.take() {
+                trace!("wants I/O to write bytes again after backpressure");
+                self.awaiting = true;
+                return WriteStreamResult::Io(StreamIo::Write(Err(bytes)));
+            }
+
+            if let Some((total_len, pad)) = self.padding {
+                let len = self.bytes.len();
+
+                if len > total_len {
+                    return WriteStreamResult::Err(WriteStreamError::PaddingExceeded(
+                        len, total_len,
+                    ));
+                }
+
+                trace!("pad {len} bytes to {total_len} bytes");
+                self.bytes.resize(total_len, pad);
+            }
+
+            let bytes = mem::take(&mut self.bytes);
             trace!("wants I/O to write bytes");
+            self.awaiting = true;
             return WriteStreamResult::Io(StreamIo::Write(Err(bytes)));
         };
 
+        if !self.awaiting {
+            return WriteStreamResult::Err(WriteStreamError::InvalidArgument("no response", arg));
+        }
+
         trace!("resume after writing bytes");
 
         let StreamIo::Write(io) = arg else {
@@ -87,12 +292,588 @@ impl WriteStream {
             Err(bytes) => return WriteStreamResult::Io(StreamIo::Write(Err(bytes))),
         };
 
-        match output.bytes_count {
-            0 => WriteStreamResult::Eof,
-            n => {
-                debug!("wrote {n} bytes");
-                WriteStreamResult::Ok(output)
+        self.awaiting = false;
+
+        if output.bytes_count == 0 {
+            if !self.backpressure {
+                if self.written == 0 {
+                    self.restore(output.buffer);
+                } else {
+                    self.spare = output.buffer;
+                }
+
+                return WriteStreamResult::Eof;
+            }
+
+            let remaining = output.buffer.len();
+            debug!("backpressure, {remaining} remaining bytes to write");
+            self.pending = Some(output.buffer);
+            return WriteStreamResult::Backpressure(remaining);
+        }
+
+        debug!("wrote {} bytes", output.bytes_count);
+
+        if let Some(observer) = &self.observer {
+            observer.on_write(output.bytes_count);
+        }
+
+        // the first output gives back the original buffer, whereas the
+        // next ones give back the remaining bytes buffer
+        let mut remaining = if self.written == 0 {
+            self.restore(output.buffer);
+            mem::take(&mut self.spare)
+        } else {
+            output.buffer
+        };
+
+        self.written += output.bytes_count;
+
+        if self.written >= self.bytes.len() {
+            // keeps the last confirmed bytes, which leave the
+            // coroutine with the output
+            if let Some(drained) = self.drained {
+                self.undrained
+                    .extend_from_slice(&self.bytes[drained.min(self.written)..]);
+            }
+
+            // keeps the buffer of the remaining bytes for the next
+            // payload
+            self.spare = remaining;
+
+            let output = StreamOutput {
+                buffer: mem::take(&mut self.bytes),
+                bytes_count: self.written,
+            };
+
+            return WriteStreamResult::Ok(output);
+        }
+
+        if let Some(err) = self.check_cancelled() {
+            return WriteStreamResult::Err(err);
+        }
+
+        debug!(
+            "{} remaining bytes to write",
+            self.bytes.len() - self.written
+        );
+        remaining.clear();
+        remaining.extend_from_slice(&self.bytes[self.written..]);
+        trace!("wants I/O to write remaining bytes");
+        self.awaiting = true;
+        WriteStreamResult::Io(StreamIo::Write(Err(remaining)))
+    }
+
+    /// Restores the original buffer given back by the first output.
+    ///
+    /// Bytes extended during the first write come after the original
+    /// ones.
+    fn restore(&mut self, buffer: Vec<u8>) {
+        let mut extended = mem::replace(&mut self.bytes, buffer);
+        self.bytes.append(&mut extended);
+    }
+
+    /// Resets the progression, so that the inner bytes are written
+    /// from the start.
+    fn reset(&mut self) {
+        self.written = 0;
+        self.pending = None;
+        self.awaiting = false;
+        self.reset_drained();
+    }
+
+    /// Resets the bytes drained so far, if tracked.
+    fn reset_drained(&mut self) {
+        if let Some(drained) = &mut self.drained {
+            *drained = 0;
+        }
+    }
+
+    /// Returns the cancelled error if the coroutine has been
+    /// cancelled.
+    fn check_cancelled(&self) -> Option<WriteStreamError> {
+        match &self.cancel {
+            Some(cancel) if cancel.is_cancelled() => Some(WriteStreamError::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// The End Of File is emitted as a [`WriteStreamError::UnexpectedEof`]
+/// error, since the bytes could not all be written.
+impl Coroutine for WriteStream {
+    type Output = StreamOutput;
+    type Error = WriteStreamError;
+
+    fn resume(&mut self, arg: Option<StreamIo>) -> CoroutineResult<Self::Output, Self::Error> {
+        match WriteStream::resume(self, arg) {
+            WriteStreamResult::Ok(output) => CoroutineResult::Ok(output),
+            WriteStreamResult::Io(io) => CoroutineResult::Io(io),
+            WriteStreamResult::Eof => {
+                let remaining = self.unacknowledged().len();
+                CoroutineResult::Err(WriteStreamError::UnexpectedEof(remaining))
+            }
+            WriteStreamResult::Err(err) => CoroutineResult::Err(err),
+            // generic drivers cannot back off, so the write is retried
+            WriteStreamResult::Backpressure(_) => Coroutine::resume(self, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::{StreamIo, StreamOutput};
+
+    use super::{WriteStream, WriteStreamError, WriteStreamResult};
+
+    #[test]
+    fn write_partial() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+
+        let mut write = WriteStream::new(b"hello world".to_vec());
+        let mut arg = None;
+
+        let output = loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(output) => break output,
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // simulates partial writes of 3 bytes max
+                    let bytes_count = buffer.len().min(3);
+                    writer.extend_from_slice(&buffer[..bytes_count]);
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(writer, b"hello world");
+        assert_eq!(output.bytes(), b"hello world");
+        assert_eq!(write.written(), 11);
+    }
+
+    #[test]
+    fn write_no_alloc() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+        let mut bytes = Vec::with_capacity(16);
+        let ptr = bytes.as_ptr();
+
+        // the same allocation goes back and forth between the
+        // coroutine and the runtime for many small writes
+        for message in ["a", "bc", "def", "ghij"] {
+            bytes.clear();
+            bytes.extend_from_slice(message.as_bytes());
+
+            let mut write = WriteStream::new(bytes);
+            let mut arg = None;
+
+            let output = loop {
+                match write.resume(arg.take()) {
+                    WriteStreamResult::Ok(output) => break output,
+                    WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                        assert_eq!(buffer.as_ptr(), ptr);
+                        writer.extend_from_slice(&buffer);
+                        let output = StreamOutput {
+                            bytes_count: buffer.len(),
+                            buffer,
+                        };
+                        arg = Some(StreamIo::Write(Ok(output)))
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            };
+
+            assert_eq!(output.buffer.as_ptr(), ptr);
+            bytes = output.buffer;
+        }
+
+        assert_eq!(writer, b"abcdefghij");
+    }
+
+    #[test]
+    fn write_partial_no_alloc() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+        let mut write = WriteStream::new(Vec::new());
+        let mut ptrs = Vec::new();
+
+        for message in ["abcdef", "ghijkl", "mnopqr"] {
+            write.replace(message.bytes());
+            let mut arg = None;
+            let mut payload_ptrs = Vec::new();
+
+            let output = loop {
+                match write.resume(arg.take()) {
+                    WriteStreamResult::Ok(output) => break output,
+                    WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                        payload_ptrs.push(buffer.as_ptr());
+
+                        // simulates partial writes of 2 bytes max
+                        let bytes_count = buffer.len().min(2);
+                        writer.extend_from_slice(&buffer[..bytes_count]);
+                        let output = StreamOutput {
+                            buffer,
+                            bytes_count,
+                        };
+                        arg = Some(StreamIo::Write(Ok(output)))
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            };
+
+            assert_eq!(output.bytes(), message.as_bytes());
+            write.replace_buffer(output.buffer);
+            ptrs.push(payload_ptrs);
+        }
+
+        // the buffers of the payload and of the remaining bytes are
+        // allocated by the first payload, then reused by the next ones
+        assert_eq!(ptrs[0].len(), 3);
+        assert_ne!(ptrs[0][0], ptrs[0][1]);
+        assert_eq!(ptrs[0][1], ptrs[0][2]);
+        assert!(ptrs.iter().all(|payload_ptrs| *payload_ptrs == ptrs[0]));
+        assert_eq!(writer, b"abcdefghijklmnopqr");
+    }
+
+    #[test]
+    fn write_replace() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+
+        let mut write = WriteStream::new(b"payload A".to_vec());
+        let mut arg = None;
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(output) => break assert_eq!(output.bytes(), b"payload A"),
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    writer.extend_from_slice(&buffer);
+                    let output = StreamOutput {
+                        bytes_count: buffer.len(),
+                        buffer,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        write.replace(*b"B");
+        write.extend(*b" and C");
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(output) => break assert_eq!(output.bytes(), b"B and C!"),
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // extends the payload while a write is in progress
+                    if write.written() == 0 {
+                        write.extend(*b"!");
+                    }
+
+                    // simulates partial writes of 3 bytes max
+                    let bytes_count = buffer.len().min(3);
+                    writer.extend_from_slice(&buffer[..bytes_count]);
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(writer, b"payload AB and C!");
+    }
+
+    #[test]
+    fn write_backpressure() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+        // the sink is full once, after the first partial write
+        let mut accepted = [3, 0].into_iter();
+
+        let mut write = WriteStream::new(b"hello world".to_vec()).with_backpressure();
+        let mut arg = None;
+        let mut backpressures = Vec::new();
+
+        let output = loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(output) => break output,
+                WriteStreamResult::Backpressure(remaining) => backpressures.push(remaining),
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    let bytes_count = accepted.next().unwrap_or(buffer.len());
+                    writer.extend_from_slice(&buffer[..bytes_count]);
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(backpressures, [8]);
+        assert_eq!(writer, b"hello world");
+        assert_eq!(output.bytes(), b"hello world");
+    }
+
+    #[test]
+    fn write_unacknowledged() {
+        let _ = env_logger::try_init();
+
+        let mut write = WriteStream::new(b"hello world".to_vec());
+
+        let buffer = match write.resume(None) {
+            WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => buffer,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        // the in-flight bytes are owned by the runtime
+        assert!(write.unacknowledged().is_empty());
+
+        let output = StreamOutput {
+            buffer,
+            bytes_count: 6,
+        };
+
+        match write.resume(Some(StreamIo::Write(Ok(output)))) {
+            WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => assert_eq!(buffer, b"world"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(write.unacknowledged(), b"world");
+    }
+
+    #[test]
+    fn write_eof() {
+        use crate::coroutines::{Coroutine, CoroutineResult};
+
+        let _ = env_logger::try_init();
+
+        let mut write = WriteStream::new(b"hello".to_vec());
+
+        let buffer = match Coroutine::resume(&mut write, None) {
+            CoroutineResult::Io(StreamIo::Write(Err(buffer))) => buffer,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        // the peer closed the stream before the first byte
+        let output = StreamOutput {
+            buffer,
+            bytes_count: 0,
+        };
+
+        match Coroutine::resume(&mut write, Some(StreamIo::Write(Ok(output)))) {
+            CoroutineResult::Err(WriteStreamError::UnexpectedEof(5)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // the bytes left to write are kept
+        assert_eq!(write.written(), 0);
+        assert_eq!(write.unacknowledged(), b"hello");
+    }
+
+    #[test]
+    fn write_drain_written() {
+        let _ = env_logger::try_init();
+
+        let mut write = WriteStream::new(b"hello world".to_vec()).with_drain_written();
+        let mut arg = None;
+        let mut drained = Vec::new();
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(_) => break,
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    drained.push(write.drain_written());
+
+                    // simulates partial writes of 4 bytes max
+                    let output = StreamOutput {
+                        bytes_count: buffer.len().min(4),
+                        buffer,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        drained.push(write.drain_written());
+
+        let expected: [&[u8]; 4] = [b"", b"hell", b"o wo", b"rld"];
+        assert_eq!(drained, expected);
+        assert!(write.drain_written().is_empty());
+    }
+
+    #[test]
+    fn write_padding() {
+        let _ = env_logger::try_init();
+
+        let mut writer = Vec::new();
+
+        let mut write = WriteStream::new(b"abc".to_vec()).with_padding(8, 0);
+        let mut arg = None;
+
+        let output = loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(output) => break output,
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    // simulates partial writes of 3 bytes max
+                    let bytes_count = buffer.len().min(3);
+                    writer.extend_from_slice(&buffer[..bytes_count]);
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(writer, b"abc\0\0\0\0\0");
+        assert_eq!(output.bytes_count, 8);
+
+        let mut write = WriteStream::new(b"too long".to_vec()).with_padding(4, 0);
+
+        match write.resume(None) {
+            WriteStreamResult::Err(WriteStreamError::PaddingExceeded(8, 4)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn write_resume_after_error() {
+        use std::io::{self, Write};
+
+        /// Writer accepting bytes up to a limit, then failing.
+        struct FailingWriter {
+            bytes: Vec<u8>,
+            limit: usize,
+        }
+
+        impl Write for FailingWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let n = buf.len().min(self.limit - self.bytes.len());
+
+                if n == 0 {
+                    return Err(io::Error::new(io::ErrorKind::BrokenPipe, "writer failed"));
+                }
+
+                self.bytes.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
             }
         }
+
+        let _ = env_logger::try_init();
+
+        // the writer fails after 5 bytes
+        let mut writer = FailingWriter {
+            bytes: Vec::new(),
+            limit: 5,
+        };
+
+        let mut write = WriteStream::new(b"hello world".to_vec());
+        let mut arg = None;
+
+        let err = loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    let bytes_count = match writer.write(&buffer) {
+                        Ok(bytes_count) => bytes_count,
+                        Err(err) => break err,
+                    };
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(write.written(), 5);
+        assert_eq!(writer.bytes, b"hello");
+
+        // resumes the upload from the written offset, with a fresh
+        // coroutine over a recovered writer
+        writer.limit = usize::MAX;
+        let mut write = WriteStream::new(write.unacknowledged().to_vec());
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(_) => break,
+                WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => {
+                    let bytes_count = writer.write(&buffer).unwrap();
+                    let output = StreamOutput {
+                        buffer,
+                        bytes_count,
+                    };
+                    arg = Some(StreamIo::Write(Ok(output)))
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(write.written(), 6);
+        assert_eq!(writer.bytes, b"hello world");
+    }
+
+    #[test]
+    fn write_extra_response() {
+        let _ = env_logger::try_init();
+
+        let response = |buffer: Vec<u8>| {
+            let output = StreamOutput {
+                bytes_count: buffer.len(),
+                buffer,
+            };
+            StreamIo::Write(Ok(output))
+        };
+
+        // before the first request, the bytes to write are kept
+        let mut write = WriteStream::new(b"abc".to_vec());
+
+        match write.resume(Some(response(b"xyz".to_vec()))) {
+            WriteStreamResult::Err(WriteStreamError::InvalidArgument("no response", _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(write.unacknowledged(), b"abc");
+
+        let buffer = match write.resume(None) {
+            WriteStreamResult::Io(StreamIo::Write(Err(buffer))) => buffer,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        match write.resume(Some(response(buffer))) {
+            WriteStreamResult::Ok(output) => assert_eq!(output.bytes(), b"abc"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        // after the termination
+        match write.resume(Some(response(b"abc".to_vec()))) {
+            WriteStreamResult::Err(WriteStreamError::InvalidArgument("no response", _)) => (),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(write.written(), 3);
     }
 }
@@ -1,6 +1,7 @@
 //! Filesystem I/O requests and responses.
 
-use std::fmt;
+use alloc::vec::Vec;
+use core::fmt;
 
 /// The stream I/O request and response enum, emitted by [coroutines]
 /// and processed by [runtimes].
@@ -8,15 +9,36 @@ use std::fmt;
 /// Represents all the possible I/O requests that a stream coroutine
 /// can emit. Runtimes should be able to handle all variants.
 ///
+/// Cloning is expensive: both requests and responses own their
+/// buffer, which gets copied.
+///
+/// With the `serde` feature, requests and responses can be
+/// serialized, for example to persist recorded sessions.
+///
 /// [coroutines]: crate::coroutines
 /// [runtimes]: crate::runtimes
 #[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum StreamIo {
     /// I/O request to read bytes.
     ///
     /// Input: read buffer as vec
     ///
     /// Output: [`StreamOutput`]
+    ///
+    /// The length of the read buffer is the maximum amount of bytes
+    /// to read. Runtimes read into it as is, then give the same buffer
+    /// back without resizing it. Coroutines keep the allocation across
+    /// reads and restore the length themselves, so that a shorter read
+    /// never shrinks the next ones, see
+    /// [`ReadStream::replace`](crate::coroutines::read::ReadStream::replace).
+    ///
+    /// With the `nightly` cfg, the read buffer can also be empty, in
+    /// which case its capacity is the maximum amount of bytes to read.
+    /// Runtimes read into its spare capacity without initializing it,
+    /// then give it back with its length set to the amount of bytes
+    /// read. Runtimes reading into slices initialize it first, see
+    /// [`init_read_buffer`].
     Read(Result<StreamOutput, Vec<u8>>),
 
     /// I/O request to write bytes.
@@ -25,24 +47,257 @@ pub enum StreamIo {
     ///
     /// Output: [`StreamOutput`]
     Write(Result<StreamOutput, Vec<u8>>),
+
+    /// I/O request to write bytes from several buffers at once, for
+    /// example with [`std::io::Write::write_vectored`].
+    ///
+    /// Input: write buffers as vec of vecs
+    ///
+    /// Output: [`StreamVectoredOutput`]
+    WriteVectored(Result<StreamVectoredOutput, Vec<Vec<u8>>>),
+
+    /// I/O request to flush buffered bytes, for example with
+    /// [`std::io::Write::flush`].
+    ///
+    /// Input: `false`
+    ///
+    /// Output: `true`
+    Flush(bool),
+
+    /// I/O request to shut down the write half of the stream, so that
+    /// the peer reaches the End Of File.
+    ///
+    /// Not all streams support shutdown: runtimes fail with an
+    /// unsupported error when they cannot shut down the stream.
+    ///
+    /// Input: `false`
+    ///
+    /// Output: `true`
+    Shutdown(bool),
 }
 
+/// The terse form only shows the kind of I/O, as embedded in
+/// coroutine errors. The alternate form `{:#?}` also shows the amount
+/// of bytes and a hex preview of the first [`StreamIo::PREVIEW_LEN`]
+/// bytes.
 impl fmt::Debug for StreamIo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return f.write_str(self.kind());
+        }
+
+        write!(f, "{self}")?;
+
         match self {
-            Self::Read(Ok(_)) => f.write_str("read output"),
-            Self::Read(Err(_)) => f.write_str("read input"),
+            Self::Read(Ok(output)) | Self::Write(Ok(output)) => {
+                write_preview(f, output.bytes_count, output.bytes().iter())
+            }
+            Self::Write(Err(buffer)) => write_preview(f, buffer.len(), buffer.iter()),
+            Self::WriteVectored(Ok(output)) => write_preview(
+                f,
+                output.bytes_count,
+                output.buffers.iter().flatten().take(output.bytes_count),
+            ),
+            Self::WriteVectored(Err(buffers)) => write_preview(
+                f,
+                buffers.iter().map(Vec::len).sum(),
+                buffers.iter().flatten(),
+            ),
+            // the read input buffer does not contain meaningful bytes
+            Self::Read(Err(_)) | Self::Flush(_) | Self::Shutdown(_) => Ok(()),
+        }
+    }
+}
+
+/// Writes the hex preview of the first [`StreamIo::PREVIEW_LEN`]
+/// bytes among the given amount of bytes.
+fn write_preview<'a>(
+    f: &mut fmt::Formatter<'_>,
+    len: usize,
+    bytes: impl Iterator<Item = &'a u8>,
+) -> fmt::Result {
+    f.write_str(":")?;
+
+    for byte in bytes.take(StreamIo::PREVIEW_LEN) {
+        write!(f, " {byte:02x}")?;
+    }
+
+    if len > StreamIo::PREVIEW_LEN {
+        f.write_str(" …")?;
+    }
+
+    Ok(())
+}
+
+/// Shows the kind of I/O with its amount of bytes.
+impl fmt::Display for StreamIo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = self.kind();
 
-            Self::Write(Ok(_)) => f.write_str("write output"),
-            Self::Write(Err(_)) => f.write_str("write input"),
+        match self {
+            Self::Read(Ok(output)) | Self::Write(Ok(output)) => {
+                write!(f, "{kind} ({} bytes)", output.bytes_count)
+            }
+            Self::Read(Err(buffer)) => write!(f, "{kind} ({} bytes buffer)", buffer.len()),
+            Self::Write(Err(buffer)) => write!(f, "{kind} ({} bytes pending)", buffer.len()),
+            Self::WriteVectored(Ok(output)) => {
+                write!(f, "{kind} ({} bytes)", output.bytes_count)
+            }
+            Self::WriteVectored(Err(buffers)) => {
+                let len: usize = buffers.iter().map(Vec::len).sum();
+                let n = buffers.len();
+                write!(f, "{kind} ({len} bytes pending in {n} buffers)")
+            }
+            Self::Flush(_) | Self::Shutdown(_) => f.write_str(kind),
         }
     }
 }
 
+impl StreamIo {
+    /// The amount of bytes shown by the alternate [`fmt::Debug`]
+    /// form.
+    pub const PREVIEW_LEN: usize = 16;
+
+    /// Returns `true` if the I/O is a read request or response.
+    pub fn is_read(&self) -> bool {
+        matches!(self, Self::Read(_))
+    }
+
+    /// Returns `true` if the I/O is a write request or response.
+    ///
+    /// Vectored writes are not included, see
+    /// [`StreamIo::WriteVectored`].
+    pub fn is_write(&self) -> bool {
+        matches!(self, Self::Write(_))
+    }
+
+    /// Returns the buffer to read bytes into, if the I/O is a read
+    /// request.
+    ///
+    /// An empty buffer is initialized first, see [`init_read_buffer`].
+    ///
+    /// ```
+    /// use io_stream::io::StreamIo;
+    ///
+    /// let mut io = StreamIo::Read(Err(vec![0; 4]));
+    /// io.as_read_buffer().unwrap()[..2].copy_from_slice(b"ab");
+    ///
+    /// assert!(StreamIo::Write(Err(vec![0; 4])).as_read_buffer().is_none());
+    /// ```
+    pub fn as_read_buffer(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            Self::Read(Err(buffer)) => {
+                init_read_buffer(buffer);
+                Some(buffer)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes to write, if the I/O is a write request.
+    ///
+    /// ```
+    /// use io_stream::io::StreamIo;
+    ///
+    /// let io = StreamIo::Write(Err(b"hello".to_vec()));
+    /// assert_eq!(io.as_write_bytes(), Some(&b"hello"[..]));
+    /// ```
+    pub fn as_write_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Write(Err(bytes)) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Turns a read request into its response, once the given amount
+    /// of bytes has been read into its buffer.
+    ///
+    /// Other I/O are returned unchanged. The amount of bytes should
+    /// not exceed the length of the buffer.
+    ///
+    /// ```
+    /// use std::io::Read;
+    ///
+    /// use io_stream::io::{StreamIo, StreamOutput};
+    ///
+    /// let mut reader = "hello".as_bytes();
+    /// let mut io = StreamIo::Read(Err(vec![0; 8]));
+    ///
+    /// let bytes_count = reader.read(io.as_read_buffer().unwrap()).unwrap();
+    /// let io = io.fulfill_read(bytes_count);
+    ///
+    /// let StreamIo::Read(Ok(output)) = io else {
+    ///     unreachable!();
+    /// };
+    ///
+    /// assert_eq!(output.bytes(), b"hello");
+    /// ```
+    pub fn fulfill_read(self, bytes_count: usize) -> StreamIo {
+        match self {
+            Self::Read(Err(buffer)) => Self::Read(Ok(StreamOutput {
+                buffer,
+                bytes_count,
+            })),
+            io => io,
+        }
+    }
+
+    /// Returns the kind of I/O as string.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Read(Ok(_)) => "read output",
+            Self::Read(Err(_)) => "read input",
+
+            Self::Write(Ok(_)) => "write output",
+            Self::Write(Err(_)) => "write input",
+
+            Self::WriteVectored(Ok(_)) => "write vectored output",
+            Self::WriteVectored(Err(_)) => "write vectored input",
+
+            Self::Flush(true) => "flush output",
+            Self::Flush(false) => "flush input",
+
+            Self::Shutdown(true) => "shutdown output",
+            Self::Shutdown(false) => "shutdown input",
+        }
+    }
+}
+
+/// Returns the given read buffer as a slice to read bytes into.
+///
+/// An empty buffer is zeroed up to its capacity first, so that
+/// runtimes reading into slices can process the empty buffers handed
+/// out with the `nightly` cfg, see [`StreamIo::Read`]. A non-empty
+/// buffer is returned as is.
+///
+/// ```
+/// use io_stream::io::init_read_buffer;
+///
+/// let mut buffer = Vec::with_capacity(4);
+/// assert!(init_read_buffer(&mut buffer).len() >= 4);
+///
+/// let mut buffer = vec![0; 2];
+/// assert_eq!(init_read_buffer(&mut buffer).len(), 2);
+/// ```
+pub fn init_read_buffer(buffer: &mut Vec<u8>) -> &mut [u8] {
+    if buffer.is_empty() {
+        buffer.resize(buffer.capacity(), 0);
+    }
+
+    buffer
+}
+
 /// Output returned by both read and write coroutines.
+///
+/// Cloning is expensive: the inner buffer gets copied.
+///
+/// With the `serde` feature, the output can be serialized in order to
+/// persist an in-flight I/O, the buffer being serialized as bytes.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct StreamOutput {
     /// The inner buffer.
+    #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
     pub buffer: Vec<u8>,
 
     /// The amount of bytes that have been read/written.
@@ -54,4 +309,166 @@ impl StreamOutput {
     pub fn bytes(&self) -> &[u8] {
         &self.buffer[..self.bytes_count]
     }
+
+    /// Returns the amount of bytes the inner buffer can hold past the
+    /// read/written bytes, without reallocating.
+    pub fn remaining_capacity(&self) -> usize {
+        self.buffer.capacity() - self.bytes_count
+    }
+
+    /// Returns the full inner buffer, including its unused tail.
+    ///
+    /// Useful to give the allocation back to a coroutine, see
+    /// [`ReadStream::replace`].
+    ///
+    /// [`ReadStream::replace`]: crate::coroutines::read::ReadStream::replace
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+/// Output returned by vectored write coroutines.
+///
+/// Cloning is expensive: the inner buffers get copied.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct StreamVectoredOutput {
+    /// The inner buffers.
+    pub buffers: Vec<Vec<u8>>,
+
+    /// The amount of bytes that have been written, starting from the
+    /// first buffer.
+    pub bytes_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::{StreamIo, StreamOutput};
+
+    #[test]
+    fn stream_io_fmt() {
+        let output = StreamOutput {
+            buffer: vec![0x61; 2048],
+            bytes_count: 1448,
+        };
+
+        let read = StreamIo::Read(Ok(output));
+        assert_eq!(format!("{read:?}"), "read output");
+        assert_eq!(format!("{read}"), "read output (1448 bytes)");
+        assert_eq!(
+            format!("{read:#?}"),
+            "read output (1448 bytes): 61 61 61 61 61 61 61 61 61 61 61 61 61 61 61 61 …"
+        );
+
+        let read = StreamIo::Read(Err(vec![0; 4096]));
+        assert_eq!(format!("{read}"), "read input (4096 bytes buffer)");
+        assert_eq!(format!("{read:#?}"), "read input (4096 bytes buffer)");
+
+        let write = StreamIo::Write(Err(vec![0x2a; 512]));
+        assert_eq!(format!("{write:?}"), "write input");
+        assert_eq!(format!("{write}"), "write input (512 bytes pending)");
+
+        let output = StreamOutput {
+            buffer: b"ab\r\n".to_vec(),
+            bytes_count: 4,
+        };
+
+        let write = StreamIo::Write(Ok(output));
+        assert_eq!(format!("{write}"), "write output (4 bytes)");
+        assert_eq!(format!("{write:#?}"), "write output (4 bytes): 61 62 0d 0a");
+    }
+
+    #[test]
+    fn stream_io_accessors() {
+        let mut read = StreamIo::Read(Err(vec![0; 4]));
+        assert!(read.is_read());
+        assert!(!read.is_write());
+        assert!(read.as_write_bytes().is_none());
+
+        read.as_read_buffer().unwrap()[..3].copy_from_slice(b"abc");
+
+        let mut read = read.fulfill_read(3);
+        assert!(read.is_read());
+        assert!(read.as_read_buffer().is_none());
+
+        match &read {
+            StreamIo::Read(Ok(output)) => assert_eq!(output.bytes(), b"abc"),
+            other => unreachable!("Unexpected I/O: {other:?}"),
+        }
+
+        let mut write = StreamIo::Write(Err(b"abc".to_vec()));
+        assert!(write.is_write());
+        assert!(!write.is_read());
+        assert!(write.as_read_buffer().is_none());
+        assert_eq!(write.as_write_bytes(), Some(&b"abc"[..]));
+
+        // only read requests are fulfilled
+        let mut write = write.fulfill_read(3);
+        assert_eq!(write, StreamIo::Write(Err(b"abc".to_vec())));
+
+        write = StreamIo::Write(Ok(StreamOutput {
+            buffer: b"abc".to_vec(),
+            bytes_count: 3,
+        }));
+        assert!(write.is_write());
+        assert!(write.as_write_bytes().is_none());
+        assert!(write.as_read_buffer().is_none());
+
+        assert!(!StreamIo::Flush(false).is_read());
+        assert!(!StreamIo::WriteVectored(Err(vec![])).is_write());
+    }
+
+    #[test]
+    fn stream_output_into_buffer() {
+        let mut buffer = Vec::with_capacity(16);
+        buffer.extend(b"abcd");
+        let ptr = buffer.as_ptr();
+
+        let output = StreamOutput {
+            buffer,
+            bytes_count: 3,
+        };
+
+        assert_eq!(output.bytes(), b"abc");
+        assert_eq!(output.remaining_capacity(), 13);
+
+        let buffer = output.into_buffer();
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert_eq!(buffer.capacity(), 16);
+        assert_eq!(buffer, b"abcd");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stream_output_serde() {
+        let output = StreamOutput {
+            buffer: b"abc\0\0".to_vec(),
+            bytes_count: 3,
+        };
+
+        let json = serde_json::to_string(&output).unwrap();
+        let deserialized: StreamOutput = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, output);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stream_io_serde() {
+        let ios = [
+            StreamIo::Read(Err(vec![0; 4])),
+            StreamIo::Write(Ok(StreamOutput {
+                buffer: b"abc".to_vec(),
+                bytes_count: 2,
+            })),
+            StreamIo::Flush(true),
+        ];
+
+        let json = serde_json::to_string(&ios).unwrap();
+        let deserialized: Vec<StreamIo> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, ios);
+    }
 }
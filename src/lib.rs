@@ -1,6 +1,11 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![cfg_attr(nightly, feature(read_buf, core_io_borrowed_buf))]
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 pub mod coroutines;
 pub mod io;
+pub mod prelude;
 pub mod runtimes;
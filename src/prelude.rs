@@ -0,0 +1,53 @@
+//! Everyday API, to be glob-imported.
+//!
+//! ```
+//! use io_stream::prelude::*;
+//! ```
+//!
+//! Brings in the I/O types, the [`Coroutine`] trait alongside the
+//! most common coroutines, and the standard runtime functions when
+//! the `std` feature is enabled. Async runtimes are not part of the
+//! prelude, since their functions share the same names.
+
+pub use crate::{
+    coroutines::{
+        cancel::Cancel,
+        read::{ReadBudget, ReadStream, ReadStreamError, ReadStreamResult},
+        read_exact::{ReadStreamExact, ReadStreamExactError, ReadStreamExactResult},
+        read_to_end::{ReadStreamToEnd, ReadStreamToEndError, ReadStreamToEndResult},
+        write::{WriteStream, WriteStreamError, WriteStreamResult},
+        Coroutine, CoroutineResult,
+    },
+    io::{StreamIo, StreamOutput},
+};
+
+#[cfg(feature = "std")]
+pub use crate::runtimes::std::{drive, handle};
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn drive_with_prelude() {
+        let _ = env_logger::try_init();
+
+        let mut stream = Cursor::new(Vec::new());
+
+        let mut write = WriteStream::new(b"hello world".to_vec());
+        let output = drive(&mut stream, &mut write).unwrap().unwrap();
+        assert_eq!(output.bytes_count, 11);
+
+        stream.set_position(0);
+
+        let mut read = ReadStreamExact::new(5);
+        let hello = drive(&mut stream, &mut read).unwrap().unwrap();
+        assert_eq!(hello, b"hello");
+
+        let mut read = ReadStreamToEnd::new();
+        let world = drive(&mut stream, &mut read).unwrap().unwrap();
+        assert_eq!(world, b" world");
+    }
+}
@@ -0,0 +1,55 @@
+//! The async-std-based, async stream runtime.
+//!
+//! async-std streams implement the [`futures_io`] traits, so this
+//! runtime re-exports the executor-agnostic handlers of the
+//! [`futures`](super::futures) runtime.
+
+pub use super::futures::{flush, handle, read, shutdown, write, write_vectored};
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, io::Cursor};
+
+    use crate::coroutines::{
+        read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+        write::{WriteStream, WriteStreamResult},
+    };
+
+    use super::handle;
+
+    #[test]
+    fn handle_read_write() {
+        let _ = env_logger::try_init();
+
+        block_on(async {
+            let mut stream = Cursor::new(Vec::new());
+
+            let mut write = WriteStream::new(b"hello".to_vec());
+            let mut arg = None;
+
+            loop {
+                match write.resume(arg.take()) {
+                    WriteStreamResult::Ok(_) => break,
+                    WriteStreamResult::Io(io) => arg = Some(handle(&mut stream, io).await.unwrap()),
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            }
+
+            stream.set_position(0);
+
+            let mut read = ReadStreamToEnd::with_capacity(2);
+
+            let bytes = loop {
+                match read.resume(arg.take()) {
+                    ReadStreamToEndResult::Ok(bytes) => break bytes,
+                    ReadStreamToEndResult::Io(io) => {
+                        arg = Some(handle(&mut stream, io).await.unwrap())
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            };
+
+            assert_eq!(bytes, b"hello");
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! The embedded-io-based, blocking stream runtime.
+//!
+//! Unlike the [`std`] runtime, this runtime does not require the
+//! standard library, which makes it usable on `no_std` targets.
+//!
+//! [`std`]: https://doc.rust-lang.org/std/
+
+use alloc::vec::Vec;
+
+use embedded_io::{ErrorType, Read, Write};
+use log::trace;
+
+use crate::io::{init_read_buffer, StreamIo, StreamOutput, StreamVectoredOutput};
+
+/// The embedded-io-based, blocking stream runtime handler.
+///
+/// This handler makes use of [`embedded_io`] traits to process
+/// [`StreamIo`].
+pub fn handle<S: Read + Write>(stream: S, io: StreamIo) -> Result<StreamIo, S::Error> {
+    match io {
+        StreamIo::Read(io) => read(stream, io),
+        StreamIo::Write(io) => write(stream, io),
+        StreamIo::WriteVectored(io) => write_vectored(stream, io),
+        StreamIo::Flush(flushed) => flush(stream, flushed),
+        StreamIo::Shutdown(done) => shutdown(stream, done),
+    }
+}
+
+pub fn read<S: Read>(
+    mut stream: S,
+    input: Result<StreamOutput, Vec<u8>>,
+) -> Result<StreamIo, <S as ErrorType>::Error> {
+    let mut buffer = match input {
+        Ok(output) => return Ok(StreamIo::Read(Ok(output))),
+        Err(buffer) => buffer,
+    };
+
+    trace!("reading bytes synchronously");
+    let bytes_count = stream.read(init_read_buffer(&mut buffer))?;
+
+    let output = StreamOutput {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(StreamIo::Read(Ok(output)))
+}
+
+pub fn write<S: Write>(
+    mut stream: S,
+    input: Result<StreamOutput, Vec<u8>>,
+) -> Result<StreamIo, <S as ErrorType>::Error> {
+    let bytes = match input {
+        Ok(output) => return Ok(StreamIo::Write(Ok(output))),
+        Err(bytes) => bytes,
+    };
+
+    trace!("writing bytes synchronously");
+    let bytes_count = stream.write(&bytes)?;
+
+    let output = StreamOutput {
+        buffer: bytes,
+        bytes_count,
+    };
+
+    Ok(StreamIo::Write(Ok(output)))
+}
+
+/// Writes the first non-empty buffer of the given ones.
+///
+/// Since [`Write`] has no vectored write, buffers are written one at
+/// a time, which coroutines see as a short write.
+pub fn write_vectored<S: Write>(
+    mut stream: S,
+    input: Result<StreamVectoredOutput, Vec<Vec<u8>>>,
+) -> Result<StreamIo, <S as ErrorType>::Error> {
+    let buffers = match input {
+        Ok(output) => return Ok(StreamIo::WriteVectored(Ok(output))),
+        Err(buffers) => buffers,
+    };
+
+    trace!("writing first of {} buffers synchronously", buffers.len());
+    let bytes_count = match buffers.iter().find(|buffer| !buffer.is_empty()) {
+        Some(buffer) => stream.write(buffer)?,
+        None => 0,
+    };
+
+    let output = StreamVectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(StreamIo::WriteVectored(Ok(output)))
+}
+
+/// Flushes the stream using [`Write::flush`].
+pub fn flush<S: Write>(mut stream: S, flushed: bool) -> Result<StreamIo, <S as ErrorType>::Error> {
+    if flushed {
+        return Ok(StreamIo::Flush(true));
+    }
+
+    trace!("flushing stream synchronously");
+    stream.flush()?;
+
+    Ok(StreamIo::Flush(true))
+}
+
+/// Flushes the stream in place of shutting it down.
+///
+/// Since [`Write`] has no shutdown, the request is acknowledged once
+/// the stream is flushed: the peer does not reach the End Of File
+/// until the stream is dropped or closed by other means.
+pub fn shutdown<S: Write>(stream: S, done: bool) -> Result<StreamIo, <S as ErrorType>::Error> {
+    if done {
+        return Ok(StreamIo::Shutdown(true));
+    }
+
+    trace!("flushing stream in place of shutdown");
+    flush(stream, false)?;
+
+    Ok(StreamIo::Shutdown(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{
+            read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+            write::{WriteStream, WriteStreamResult},
+        },
+        io::StreamIo,
+    };
+
+    #[test]
+    fn read_write() {
+        let _ = env_logger::try_init();
+
+        let mut buffer = [0; 8];
+        let mut stream = &mut buffer[..];
+
+        let mut write = WriteStream::new(b"hello".to_vec());
+        let mut arg = None;
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(_) => break,
+                WriteStreamResult::Io(io) => {
+                    let StreamIo::Write(io) = io else {
+                        unreachable!("Unexpected I/O: {io:?}")
+                    };
+                    arg = Some(super::write(&mut stream, io).unwrap())
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        let mut stream = &buffer[..5];
+        let mut read = ReadStreamToEnd::with_capacity(2);
+
+        let bytes = loop {
+            match read.resume(arg.take()) {
+                ReadStreamToEndResult::Ok(bytes) => break bytes,
+                ReadStreamToEndResult::Io(StreamIo::Read(io)) => {
+                    arg = Some(super::read(&mut stream, io).unwrap())
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(bytes, b"hello");
+    }
+}
@@ -0,0 +1,615 @@
+//! The futures-based, async stream runtime.
+//!
+//! This runtime is executor-agnostic: it relies on the
+//! [`futures_io`] traits, which makes it compatible with any stream
+//! implementing them, like the ones of async-std or smol.
+//!
+//! Prefer the `tokio` runtime for streams implementing the
+//! native Tokio traits, it avoids going through a compatibility
+//! layer and it offers Tokio-specific helpers like timeouts.
+
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::{AsyncReadExt, AsyncWriteExt};
+use log::trace;
+
+use crate::{
+    coroutines::{
+        read::{ReadStream, ReadStreamResult},
+        Coroutine, CoroutineResult,
+    },
+    io::{init_read_buffer, StreamIo, StreamOutput, StreamVectoredOutput},
+};
+
+/// The futures-based, async stream runtime handler.
+///
+/// This handler makes use of standard module [`std::io`] and
+/// [`futures_io`] traits to process [`StreamIo`].
+pub async fn handle(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    io: StreamIo,
+) -> io::Result<StreamIo> {
+    match io {
+        StreamIo::Read(io) => read(stream, io).await,
+        StreamIo::Write(io) => write(stream, io).await,
+        StreamIo::WriteVectored(io) => write_vectored(stream, io).await,
+        StreamIo::Flush(flushed) => flush(stream, flushed).await,
+        StreamIo::Shutdown(done) => shutdown(stream, done).await,
+    }
+}
+
+pub async fn read(
+    mut stream: impl AsyncRead + Unpin,
+    input: Result<StreamOutput, Vec<u8>>,
+) -> io::Result<StreamIo> {
+    let mut buffer = match input {
+        Ok(output) => return Ok(StreamIo::Read(Ok(output))),
+        Err(buffer) => buffer,
+    };
+
+    trace!("reading bytes asynchronously");
+    let bytes_count = stream.read(init_read_buffer(&mut buffer)).await?;
+
+    let output = StreamOutput {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(StreamIo::Read(Ok(output)))
+}
+
+pub async fn write(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<StreamOutput, Vec<u8>>,
+) -> io::Result<StreamIo> {
+    let bytes = match input {
+        Ok(output) => return Ok(StreamIo::Write(Ok(output))),
+        Err(bytes) => bytes,
+    };
+
+    trace!("writing bytes asynchronously");
+    let bytes_count = stream.write(&bytes).await?;
+
+    let output = StreamOutput {
+        buffer: bytes,
+        bytes_count,
+    };
+
+    Ok(StreamIo::Write(Ok(output)))
+}
+
+/// Writes the given buffers at once using
+/// [`AsyncWriteExt::write_vectored`].
+pub async fn write_vectored(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<StreamVectoredOutput, Vec<Vec<u8>>>,
+) -> io::Result<StreamIo> {
+    let buffers = match input {
+        Ok(output) => return Ok(StreamIo::WriteVectored(Ok(output))),
+        Err(buffers) => buffers,
+    };
+
+    trace!("writing {} buffers asynchronously", buffers.len());
+    let slices: Vec<_> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+    let bytes_count = stream.write_vectored(&slices).await?;
+
+    let output = StreamVectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(StreamIo::WriteVectored(Ok(output)))
+}
+
+/// Flushes the stream using [`AsyncWriteExt::flush`].
+pub async fn flush(mut stream: impl AsyncWrite + Unpin, flushed: bool) -> io::Result<StreamIo> {
+    if flushed {
+        return Ok(StreamIo::Flush(true));
+    }
+
+    trace!("flushing stream asynchronously");
+    stream.flush().await?;
+
+    Ok(StreamIo::Flush(true))
+}
+
+/// Shuts down the write half of the stream using
+/// [`AsyncWriteExt::close`].
+pub async fn shutdown(mut stream: impl AsyncWrite + Unpin, done: bool) -> io::Result<StreamIo> {
+    if done {
+        return Ok(StreamIo::Shutdown(true));
+    }
+
+    trace!("closing stream asynchronously");
+    stream.close().await?;
+
+    Ok(StreamIo::Shutdown(true))
+}
+
+/// A [`Stream`] of chunks read from an [`AsyncRead`] stream.
+///
+/// The stream drives a [`ReadStream`] coroutine against the inner
+/// stream, and yields each chunk of read bytes until EOF. The read
+/// buffer is given back to the coroutine after each chunk, see
+/// [`ReadStream::recycle`]. See [`CoroutineOutputs`] to drive any
+/// other coroutine.
+#[derive(Debug)]
+pub struct CoroutineStream<S> {
+    /// The inner stream.
+    stream: S,
+
+    /// The read coroutine.
+    read: ReadStream,
+
+    /// The buffer of a read request not yet fulfilled.
+    pending: Option<Vec<u8>>,
+
+    /// Whether the stream reached EOF or failed.
+    done: bool,
+}
+
+impl<S: AsyncRead + Unpin> CoroutineStream<S> {
+    /// Creates a new stream of chunks using a read buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(stream: S) -> Self {
+        Self::with_capacity(stream, ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new stream of chunks using a read buffer with the
+    /// given capacity.
+    pub fn with_capacity(stream: S, capacity: usize) -> Self {
+        Self {
+            stream,
+            read: ReadStream::with_capacity(capacity),
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Consumes the stream and returns the inner one.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncRead + Unpin> Stream for CoroutineStream<S> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut arg = None;
+
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            if let Some(mut buffer) = this.pending.take() {
+                trace!("polling bytes asynchronously");
+
+                let bytes_count =
+                    match Pin::new(&mut this.stream).poll_read(cx, init_read_buffer(&mut buffer)) {
+                        Poll::Ready(Ok(n)) => n,
+                        Poll::Ready(Err(err)) => {
+                            this.done = true;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        Poll::Pending => {
+                            this.pending = Some(buffer);
+                            return Poll::Pending;
+                        }
+                    };
+
+                let output = StreamOutput {
+                    buffer,
+                    bytes_count,
+                };
+
+                arg = Some(StreamIo::Read(Ok(output)));
+            }
+
+            match this.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => {
+                    let chunk = this.read.recycle(output);
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+                ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => {
+                    this.pending = Some(buffer);
+                }
+                ReadStreamResult::Io(io) => {
+                    this.done = true;
+                    let err = format!("unexpected I/O request {io:?}");
+                    return Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::Other, err))));
+                }
+                ReadStreamResult::Eof => {
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                ReadStreamResult::Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::Other, err))));
+                }
+            }
+        }
+    }
+}
+
+/// A [`Stream`] of coroutine outputs, driven against an [`AsyncRead`]
+/// stream.
+///
+/// The stream drives the coroutine against the inner stream, and
+/// yields each of its outputs. The coroutine is resumed again after
+/// each output, so it needs to restart on its own once terminated,
+/// like framing coroutines reading one frame after another. See
+/// [`CoroutineStream`] for a stream of chunks of read bytes.
+///
+/// The stream ends once the inner stream reaches EOF between two
+/// outputs, or after the first error. Reaching EOF in the middle of
+/// an output is up to the coroutine, which usually fails.
+#[derive(Debug)]
+pub struct CoroutineOutputs<S, C> {
+    /// The inner stream.
+    stream: S,
+
+    /// The driven coroutine.
+    coroutine: C,
+
+    /// The buffer of a read request not yet fulfilled.
+    pending: Option<Vec<u8>>,
+
+    /// Whether bytes have been read since the last output.
+    started: bool,
+
+    /// Whether the stream reached EOF or failed.
+    done: bool,
+}
+
+impl<S: AsyncRead + Unpin, C: Coroutine> CoroutineOutputs<S, C> {
+    /// Creates a new stream of outputs of the given coroutine.
+    pub fn new(stream: S, coroutine: C) -> Self {
+        Self {
+            stream,
+            coroutine,
+            pending: None,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Consumes the stream and returns the inner one.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S, C> Stream for CoroutineOutputs<S, C>
+where
+    S: AsyncRead + Unpin,
+    C: Coroutine + Unpin,
+    C::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Item = io::Result<C::Output>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut arg = None;
+
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            if let Some(mut buffer) = this.pending.take() {
+                trace!("polling bytes asynchronously");
+
+                let bytes_count =
+                    match Pin::new(&mut this.stream).poll_read(cx, init_read_buffer(&mut buffer)) {
+                        Poll::Ready(Ok(n)) => n,
+                        Poll::Ready(Err(err)) => {
+                            this.done = true;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        Poll::Pending => {
+                            this.pending = Some(buffer);
+                            return Poll::Pending;
+                        }
+                    };
+
+                if bytes_count == 0 && !this.started {
+                    trace!("reached EOF between two outputs");
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+
+                this.started = true;
+
+                let output = StreamOutput {
+                    buffer,
+                    bytes_count,
+                };
+
+                arg = Some(StreamIo::Read(Ok(output)));
+            }
+
+            match this.coroutine.resume(arg.take()) {
+                CoroutineResult::Ok(output) => {
+                    this.started = false;
+                    return Poll::Ready(Some(Ok(output)));
+                }
+                CoroutineResult::Io(StreamIo::Read(Err(buffer))) => {
+                    this.pending = Some(buffer);
+                }
+                CoroutineResult::Io(io) => {
+                    this.done = true;
+                    let err = format!("unexpected I/O request {io:?}");
+                    return Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::Other, err))));
+                }
+                CoroutineResult::Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::Other, err))));
+                }
+            }
+        }
+    }
+}
+
+/// An [`AsyncRead`] adapter driving a [`ReadStream`] coroutine
+/// against an inner [`AsyncRead`] stream.
+///
+/// This makes coroutines composable with code expecting a standard
+/// [`AsyncRead`]. Bytes of a chunk that do not fit in the buffer
+/// given to [`AsyncRead::poll_read`] are kept for the next polls.
+#[derive(Debug)]
+pub struct ReadStreamReader<S> {
+    /// The inner stream.
+    stream: S,
+
+    /// The read coroutine.
+    read: ReadStream,
+
+    /// The buffer of a read request not yet fulfilled.
+    pending: Option<Vec<u8>>,
+
+    /// The last read chunk, alongside the amount of its bytes already
+    /// copied to the caller.
+    chunk: Option<(StreamOutput, usize)>,
+
+    /// Whether the stream reached EOF.
+    eof: bool,
+}
+
+impl<S: AsyncRead + Unpin> ReadStreamReader<S> {
+    /// Creates a new reader using a read buffer with
+    /// [`ReadStream::DEFAULT_CAPACITY`] capacity.
+    ///
+    /// See [`Self::with_capacity`] for a custom buffer capacity.
+    pub fn new(stream: S) -> Self {
+        Self::with_capacity(stream, ReadStream::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new reader using a read buffer with the given
+    /// capacity.
+    pub fn with_capacity(stream: S, capacity: usize) -> Self {
+        Self {
+            stream,
+            read: ReadStream::with_capacity(capacity),
+            pending: None,
+            chunk: None,
+            eof: false,
+        }
+    }
+
+    /// Consumes the reader and returns the inner stream.
+    ///
+    /// Bytes read but not yet copied to the caller are lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Copies the bytes of the current chunk into the given buffer.
+    ///
+    /// The chunk buffer is given back to the coroutine once all its
+    /// bytes have been copied.
+    fn copy_chunk(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let (output, offset) = self.chunk.as_mut()?;
+        let bytes = &output.bytes()[*offset..];
+        let n = bytes.len().min(buf.len());
+        buf[..n].copy_from_slice(&bytes[..n]);
+        *offset += n;
+
+        if *offset >= output.bytes_count {
+            if let Some((output, _)) = self.chunk.take() {
+                self.read.replace(output.buffer);
+            }
+        }
+
+        Some(n)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ReadStreamReader<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let mut arg = None;
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if let Some(n) = this.copy_chunk(buf) {
+            return Poll::Ready(Ok(n));
+        }
+
+        loop {
+            if this.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            if let Some(mut buffer) = this.pending.take() {
+                trace!("polling bytes asynchronously");
+
+                let bytes_count =
+                    match Pin::new(&mut this.stream).poll_read(cx, init_read_buffer(&mut buffer)) {
+                        Poll::Ready(Ok(n)) => n,
+                        Poll::Ready(Err(err)) => {
+                            // gives the buffer back, so that the coroutine
+                            // reuses it on retry
+                            this.read.replace(buffer);
+                            return Poll::Ready(Err(err));
+                        }
+                        Poll::Pending => {
+                            this.pending = Some(buffer);
+                            return Poll::Pending;
+                        }
+                    };
+
+                let output = StreamOutput {
+                    buffer,
+                    bytes_count,
+                };
+
+                arg = Some(StreamIo::Read(Ok(output)));
+            }
+
+            match this.read.resume(arg.take()) {
+                ReadStreamResult::Ok(output) => {
+                    this.chunk = Some((output, 0));
+                    let n = this.copy_chunk(buf).unwrap_or_default();
+                    return Poll::Ready(Ok(n));
+                }
+                ReadStreamResult::Io(StreamIo::Read(Err(buffer))) => {
+                    this.pending = Some(buffer);
+                }
+                ReadStreamResult::Io(io) => {
+                    let err = format!("unexpected I/O request {io:?}");
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, err)));
+                }
+                ReadStreamResult::Eof => {
+                    this.eof = true;
+                    return Poll::Ready(Ok(0));
+                }
+                ReadStreamResult::Err(err) => {
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, io::Cursor, AsyncReadExt, StreamExt};
+
+    use crate::coroutines::{
+        read_line::ReadStreamLine,
+        read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+        write::{WriteStream, WriteStreamResult},
+    };
+
+    use super::{handle, CoroutineOutputs, CoroutineStream, ReadStreamReader};
+
+    #[test]
+    fn handle_cursor() {
+        let _ = env_logger::try_init();
+
+        block_on(async {
+            let mut stream = Cursor::new(Vec::new());
+
+            let mut write = WriteStream::new(b"hello world".to_vec());
+            let mut arg = None;
+
+            loop {
+                match write.resume(arg.take()) {
+                    WriteStreamResult::Ok(_) => break,
+                    WriteStreamResult::Io(io) => arg = Some(handle(&mut stream, io).await.unwrap()),
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            }
+
+            stream.set_position(0);
+
+            let mut read = ReadStreamToEnd::with_capacity(4);
+
+            let bytes = loop {
+                match read.resume(arg.take()) {
+                    ReadStreamToEndResult::Ok(bytes) => break bytes,
+                    ReadStreamToEndResult::Io(io) => {
+                        arg = Some(handle(&mut stream, io).await.unwrap())
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            };
+
+            assert_eq!(bytes, b"hello world");
+        })
+    }
+
+    #[test]
+    fn collect_chunks() {
+        let _ = env_logger::try_init();
+
+        let stream = Cursor::new(b"abcdefghij".to_vec());
+        let chunks = CoroutineStream::with_capacity(stream, 4);
+        let chunks: Vec<_> = block_on(chunks.map(Result::unwrap).collect());
+
+        assert_eq!(chunks, [&b"abcd"[..], b"efgh", b"ij"]);
+        assert_eq!(chunks.concat(), b"abcdefghij");
+    }
+
+    #[test]
+    fn collect_lines() {
+        let _ = env_logger::try_init();
+
+        let stream = Cursor::new(b"ab\r\ncd\r\nef\r\n".to_vec());
+        let lines = CoroutineOutputs::new(stream, ReadStreamLine::with_capacity(4));
+        let lines: Vec<_> = block_on(lines.map(Result::unwrap).collect());
+
+        assert_eq!(lines, ["ab", "cd", "ef"]);
+
+        // reaching EOF in the middle of a line fails
+        let stream = Cursor::new(b"ab\r\ncd".to_vec());
+        let mut lines = CoroutineOutputs::new(stream, ReadStreamLine::with_capacity(4));
+
+        assert_eq!(block_on(lines.next()).unwrap().unwrap(), "ab");
+        assert!(block_on(lines.next()).unwrap().is_err());
+        assert!(block_on(lines.next()).is_none());
+    }
+
+    #[test]
+    fn read_to_end_through_reader() {
+        let _ = env_logger::try_init();
+
+        let stream = Cursor::new(b"abcdefghij".to_vec());
+        let mut reader = ReadStreamReader::with_capacity(stream, 4);
+        let mut bytes = Vec::new();
+
+        let n = block_on(reader.read_to_end(&mut bytes)).unwrap();
+        assert_eq!(n, 10);
+        assert_eq!(bytes, b"abcdefghij");
+
+        // chunks bigger than the caller's buffer are kept for the
+        // next polls
+        let stream = Cursor::new(b"abcdefghij".to_vec());
+        let mut reader = ReadStreamReader::with_capacity(stream, 8);
+        let mut buf = [0; 3];
+
+        let n = block_on(reader.read(&mut buf)).unwrap();
+        assert_eq!(&buf[..n], b"abc");
+
+        let mut rest = Vec::new();
+        block_on(reader.read_to_end(&mut rest)).unwrap();
+        assert_eq!(rest, b"defghij");
+    }
+}
@@ -10,7 +10,17 @@
 //! [I/O]: crate::io::Io
 //! [coroutines]: crate::coroutines
 
+#[cfg(feature = "async-std")]
+#[path = "async-std.rs"]
+pub mod async_std;
+#[cfg(feature = "embedded-io")]
+#[path = "embedded-io.rs"]
+pub mod embedded_io;
+#[cfg(feature = "futures")]
+pub mod futures;
 #[cfg(feature = "std")]
+pub mod replay;
+#[cfg(any(feature = "std", test))]
 pub mod std;
 #[cfg(feature = "tokio")]
 pub mod tokio;
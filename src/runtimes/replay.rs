@@ -0,0 +1,213 @@
+//! The record and replay stream runtime.
+//!
+//! [`Recorder`] wraps any runtime handler and keeps track of every
+//! I/O request emitted by a coroutine alongside the response it
+//! received. [`ReplayRuntime`] then feeds those recorded responses
+//! back, which allows a session to be reproduced deterministically
+//! without the original stream.
+//!
+//! With the `serde` feature, records can be serialized in order to
+//! replay a session across runs.
+
+use std::{collections::VecDeque, io};
+
+use log::trace;
+
+use crate::io::StreamIo;
+
+/// A recorded I/O request alongside its response.
+pub type StreamIoRecord = (StreamIo, StreamIo);
+
+/// Runtime decorator recording I/O requests and responses.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    records: Vec<StreamIoRecord>,
+}
+
+impl Recorder {
+    /// Creates a new, empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes the given I/O request using the given runtime
+    /// handler, then records both the request and the response.
+    ///
+    /// Failed I/O are not recorded.
+    pub fn handle(
+        &mut self,
+        io: StreamIo,
+        handle: impl FnOnce(StreamIo) -> io::Result<StreamIo>,
+    ) -> io::Result<StreamIo> {
+        let request = io.clone();
+        let response = handle(io)?;
+        self.record(request, response.clone());
+        Ok(response)
+    }
+
+    /// Records the given I/O request and response.
+    ///
+    /// This is useful for runtimes that cannot be wrapped by
+    /// [`Self::handle`], like async ones.
+    pub fn record(&mut self, request: StreamIo, response: StreamIo) {
+        trace!("record {request:?} → {response:?}");
+        self.records.push((request, response));
+    }
+
+    /// Returns the recorded I/O as slice.
+    pub fn records(&self) -> &[StreamIoRecord] {
+        &self.records
+    }
+
+    /// Consumes the recorder and returns the recorded I/O.
+    pub fn into_records(self) -> Vec<StreamIoRecord> {
+        self.records
+    }
+}
+
+/// Runtime replaying previously recorded I/O responses.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayRuntime {
+    records: VecDeque<StreamIoRecord>,
+}
+
+impl ReplayRuntime {
+    /// Creates a new runtime replaying the given records, in order.
+    pub fn new(records: impl IntoIterator<Item = StreamIoRecord>) -> Self {
+        let records = records.into_iter().collect();
+        Self { records }
+    }
+
+    /// Returns `true` if all records have been replayed.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Processes the given I/O request by returning the next
+    /// recorded response.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the given request
+    /// diverges from the recorded one, and with
+    /// [`io::ErrorKind::UnexpectedEof`] if there is no more record to
+    /// replay.
+    pub fn handle(&mut self, io: StreamIo) -> io::Result<StreamIo> {
+        let Some((request, response)) = self.records.pop_front() else {
+            let err = format!("no more record to replay {io:?}");
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, err));
+        };
+
+        if !same_request(&request, &io) {
+            let err = format!("request diverged: expected {request:?}, got {io:?}");
+            return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+
+        trace!("replay {request:?} → {response:?}");
+        Ok(response)
+    }
+}
+
+/// Returns `true` if the given requests are the same.
+///
+/// Read buffers may contain stale bytes from previous reads, so read
+/// requests only compare their buffer length.
+fn same_request(recorded: &StreamIo, io: &StreamIo) -> bool {
+    match (recorded, io) {
+        (StreamIo::Read(Err(recorded)), StreamIo::Read(Err(io))) => recorded.len() == io.len(),
+        (recorded, io) => recorded == io,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufReader};
+
+    use crate::{
+        coroutines::read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+        io::{init_read_buffer, StreamIo, StreamOutput},
+    };
+
+    use super::{Recorder, ReplayRuntime};
+
+    fn read(reader: &mut impl io::Read, io: StreamIo) -> io::Result<StreamIo> {
+        let StreamIo::Read(Err(mut buffer)) = io else {
+            unreachable!("Unexpected I/O: {io:?}");
+        };
+
+        let bytes_count = reader.read(init_read_buffer(&mut buffer))?;
+        let output = StreamOutput {
+            buffer,
+            bytes_count,
+        };
+
+        Ok(StreamIo::Read(Ok(output)))
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let _ = env_logger::try_init();
+
+        let mut reader = BufReader::new("abcdef".as_bytes());
+        let mut recorder = Recorder::new();
+
+        let mut read_to_end = ReadStreamToEnd::with_capacity(4);
+        let mut arg = None;
+
+        let recorded = loop {
+            match read_to_end.resume(arg.take()) {
+                ReadStreamToEndResult::Ok(output) => break output,
+                ReadStreamToEndResult::Io(io) => {
+                    let io = recorder.handle(io, |io| read(&mut reader, io));
+                    arg = Some(io.unwrap());
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(recorded, b"abcdef");
+        assert_eq!(recorder.records().len(), 3);
+
+        let mut replay = ReplayRuntime::new(recorder.into_records());
+
+        let mut read_to_end = ReadStreamToEnd::with_capacity(4);
+        let mut arg = None;
+
+        let replayed = loop {
+            match read_to_end.resume(arg.take()) {
+                ReadStreamToEndResult::Ok(output) => break output,
+                ReadStreamToEndResult::Io(io) => arg = Some(replay.handle(io).unwrap()),
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(replayed, recorded);
+        assert!(replay.is_empty());
+    }
+
+    #[test]
+    fn replay_diverged() {
+        let _ = env_logger::try_init();
+
+        let mut reader = BufReader::new("abcdef".as_bytes());
+        let mut recorder = Recorder::new();
+
+        let io = StreamIo::Read(Err(vec![0; 4]));
+        recorder.handle(io, |io| read(&mut reader, io)).unwrap();
+
+        let mut replay = ReplayRuntime::new(recorder.into_records());
+
+        let io = StreamIo::Read(Err(vec![0; 8]));
+        let err = replay.handle(io).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // stale bytes of read buffers do not diverge
+        let mut replay = ReplayRuntime::new([(
+            StreamIo::Read(Err(vec![0; 4])),
+            StreamIo::Read(Err(vec![0; 4])),
+        )]);
+
+        replay
+            .handle(StreamIo::Read(Err(b"abcd".to_vec())))
+            .unwrap();
+    }
+}
@@ -1,19 +1,232 @@
 //! The standard, blocking stream runtime.
+//!
+//! With a nightly toolchain and the `nightly` cfg, [`handle`] reads
+//! with [`read_buf`] into uninitialized buffers. This is a cfg rather
+//! than a cargo feature because it enables the unstable `read_buf`
+//! library feature: `#![feature]` fails to compile on stable with
+//! E0554, which would break `--all-features` builds there.
 
-use std::io::{self, Read, Write};
+use std::{
+    cmp, fmt,
+    io::{self, IoSlice, Read, Write},
+    mem,
+    net::{self, TcpStream},
+};
 
-use log::trace;
+use log::{debug, trace};
 
-use crate::io::{StreamIo, StreamOutput};
+use crate::{
+    coroutines::{
+        flush::{FlushStream, FlushStreamResult},
+        write::{WriteStream, WriteStreamResult},
+        Coroutine, CoroutineResult,
+    },
+    io::{init_read_buffer, StreamIo, StreamOutput, StreamVectoredOutput},
+};
 
 /// The standard, blocking filesystem runtime handler.
 ///
 /// This handler makes use of standard modules [`std::io`] to process
 /// [`StreamIo`].
+///
+/// Since [`Write`] cannot shut down a stream, shutdown requests fail
+/// with [`io::ErrorKind::Unsupported`], see [`handle_with_shutdown`].
 pub fn handle(stream: impl Read + Write, io: StreamIo) -> io::Result<StreamIo> {
     match io {
+        #[cfg(not(nightly))]
         StreamIo::Read(io) => read(stream, io),
+        #[cfg(nightly)]
+        StreamIo::Read(io) => read_buf(stream, io),
         StreamIo::Write(io) => write(stream, io),
+        StreamIo::WriteVectored(io) => write_vectored(stream, io),
+        StreamIo::Flush(flushed) => flush(stream, flushed),
+        StreamIo::Shutdown(true) => Ok(StreamIo::Shutdown(true)),
+        StreamIo::Shutdown(false) => {
+            let err = "cannot shut down stream, see handle_with_shutdown";
+            Err(io::Error::new(io::ErrorKind::Unsupported, err))
+        }
+    }
+}
+
+/// Streams whose write half can be shut down.
+pub trait ShutdownWrite {
+    /// Shuts down the write half of the stream.
+    fn shutdown_write(&self) -> io::Result<()>;
+}
+
+impl ShutdownWrite for TcpStream {
+    fn shutdown_write(&self) -> io::Result<()> {
+        self.shutdown(net::Shutdown::Write)
+    }
+}
+
+#[cfg(unix)]
+impl ShutdownWrite for std::os::unix::net::UnixStream {
+    fn shutdown_write(&self) -> io::Result<()> {
+        self.shutdown(net::Shutdown::Write)
+    }
+}
+
+impl<T: ShutdownWrite + ?Sized> ShutdownWrite for &T {
+    fn shutdown_write(&self) -> io::Result<()> {
+        (**self).shutdown_write()
+    }
+}
+
+impl<T: ShutdownWrite + ?Sized> ShutdownWrite for &mut T {
+    fn shutdown_write(&self) -> io::Result<()> {
+        (**self).shutdown_write()
+    }
+}
+
+/// The standard, blocking stream runtime handler, supporting
+/// shutdown.
+///
+/// Same as [`handle`], except that shutdown requests shut down the
+/// write half of the stream, see [`ShutdownWrite`].
+pub fn handle_with_shutdown(
+    stream: impl Read + Write + ShutdownWrite,
+    io: StreamIo,
+) -> io::Result<StreamIo> {
+    match io {
+        StreamIo::Shutdown(done) => shutdown(stream, done),
+        io => handle(stream, io),
+    }
+}
+
+/// The standard, non-blocking stream runtime handler.
+///
+/// Same as [`handle`], except that an I/O failing with
+/// [`io::ErrorKind::WouldBlock`] does not fail: the original request
+/// is returned unchanged instead, so that its buffer is not lost.
+/// Callers can then wait for the stream to be ready, using `poll` or
+/// `select` for example, and process the same request again.
+///
+/// This is meant for streams set in non-blocking mode, like
+/// [`std::net::TcpStream::set_nonblocking`]. Reads do not make use of
+/// `read_buf`, even on nightly: empty buffers are initialized with
+/// [`init_read_buffer`] instead.
+pub fn handle_nonblocking(mut stream: impl Read + Write, io: StreamIo) -> io::Result<StreamIo> {
+    let result = match io {
+        StreamIo::Read(Err(mut buffer)) => match stream.read(init_read_buffer(&mut buffer)) {
+            Ok(bytes_count) => Ok(StreamIo::Read(Ok(StreamOutput {
+                buffer,
+                bytes_count,
+            }))),
+            Err(err) => Err((err, StreamIo::Read(Err(buffer)))),
+        },
+        StreamIo::Write(Err(bytes)) => match stream.write(&bytes) {
+            Ok(bytes_count) => Ok(StreamIo::Write(Ok(StreamOutput {
+                buffer: bytes,
+                bytes_count,
+            }))),
+            Err(err) => Err((err, StreamIo::Write(Err(bytes)))),
+        },
+        StreamIo::WriteVectored(Err(buffers)) => {
+            let slices: Vec<_> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+
+            match stream.write_vectored(&slices) {
+                Ok(bytes_count) => Ok(StreamIo::WriteVectored(Ok(StreamVectoredOutput {
+                    buffers,
+                    bytes_count,
+                }))),
+                Err(err) => Err((err, StreamIo::WriteVectored(Err(buffers)))),
+            }
+        }
+        StreamIo::Flush(false) => match stream.flush() {
+            Ok(()) => Ok(StreamIo::Flush(true)),
+            Err(err) => Err((err, StreamIo::Flush(false))),
+        },
+        io => return handle(stream, io),
+    };
+
+    match result {
+        Ok(io) => Ok(io),
+        Err((err, io)) if err.kind() == io::ErrorKind::WouldBlock => {
+            trace!("stream not ready, giving back the I/O request");
+            Ok(io)
+        }
+        Err((err, _)) => Err(err),
+    }
+}
+
+/// Drives the given coroutine until it terminates, processing its
+/// [`StreamIo`] requests with [`handle`].
+///
+/// The outer result contains the runtime I/O error, whereas the inner
+/// one contains the coroutine output or error.
+pub fn drive<C: Coroutine>(
+    mut stream: impl Read + Write,
+    coroutine: &mut C,
+) -> io::Result<Result<C::Output, C::Error>> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            CoroutineResult::Ok(output) => break Ok(Ok(output)),
+            CoroutineResult::Err(err) => break Ok(Err(err)),
+            CoroutineResult::Io(io) => arg = Some(handle(&mut stream, io)?),
+        }
+    }
+}
+
+/// Result of a coroutine resume that may contain a [`StreamIo`].
+///
+/// Allows tests to drive coroutines whose result is not a
+/// [`CoroutineResult`], see [`resume_until_done`].
+#[cfg(test)]
+pub(crate) trait IoResult: Sized {
+    /// Returns the I/O of the result, or the result itself if it does
+    /// not contain any.
+    fn into_io(self) -> Result<StreamIo, Self>;
+}
+
+#[cfg(test)]
+impl<O, E> IoResult for CoroutineResult<O, E> {
+    fn into_io(self) -> Result<StreamIo, Self> {
+        match self {
+            Self::Io(io) => Ok(io),
+            result => Err(result),
+        }
+    }
+}
+
+/// Implements [`IoResult`] for the given result enum, which must
+/// contain an `Io(StreamIo)` variant.
+#[cfg(test)]
+macro_rules! impl_io_result {
+    ($name:ident $(<$($param:ident),*>)?) => {
+        impl $(<$($param),*>)? $crate::runtimes::std::IoResult for $name $(<$($param),*>)? {
+            fn into_io(self) -> Result<$crate::io::StreamIo, Self> {
+                match self {
+                    Self::Io(io) => Ok(io),
+                    result => Err(result),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+pub(crate) use impl_io_result;
+
+/// Resumes a coroutine with the given function until its result does
+/// not contain any I/O, processing the I/O with [`handle`].
+///
+/// Unlike [`drive`], the last result is returned as is, which allows
+/// tests to match on its terminal variants.
+#[cfg(test)]
+pub(crate) fn resume_until_done<R: IoResult>(
+    mut stream: impl Read + Write,
+    mut resume: impl FnMut(Option<StreamIo>) -> R,
+) -> R {
+    let mut arg = None;
+
+    loop {
+        match resume(arg.take()).into_io() {
+            Ok(io) => arg = Some(handle(&mut stream, io).unwrap()),
+            Err(result) => break result,
+        }
     }
 }
 
@@ -24,7 +237,68 @@ pub fn read(mut stream: impl Read, input: Result<StreamOutput, Vec<u8>>) -> io::
     };
 
     trace!("reading bytes synchronously");
-    let bytes_count = stream.read(&mut buffer)?;
+    let bytes_count = stream.read(init_read_buffer(&mut buffer))?;
+
+    let output = StreamOutput {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(StreamIo::Read(Ok(output)))
+}
+
+/// Reads bytes into the spare capacity of the given buffer.
+///
+/// Unlike [`read`], the buffer does not need to be initialized: an
+/// empty buffer is read straight into its whole capacity using
+/// [`Read::read_buf`]. It is then given back with its length set to
+/// the amount of bytes read, the rest of its capacity being left
+/// uninitialized.
+///
+/// Like [`read`], a non-empty buffer limits the read to its length,
+/// and is given back without being resized.
+///
+/// Requires a nightly toolchain and the `nightly` cfg, which
+/// [`handle`] then uses for reads:
+///
+/// ```text
+/// RUSTFLAGS="--cfg nightly" cargo +nightly build
+/// ```
+#[cfg(nightly)]
+#[allow(clippy::incompatible_msrv)]
+pub fn read_buf(
+    mut stream: impl Read,
+    input: Result<StreamOutput, Vec<u8>>,
+) -> io::Result<StreamIo> {
+    use std::io::BorrowedBuf;
+
+    let mut buffer = match input {
+        Ok(output) => return Ok(StreamIo::Read(Ok(output))),
+        Err(buffer) => buffer,
+    };
+
+    if !buffer.is_empty() {
+        trace!("reading bytes synchronously into initialized buffer");
+        let mut buf: BorrowedBuf<'_> = buffer.as_mut_slice().into();
+        stream.read_buf(buf.unfilled())?;
+        let bytes_count = buf.len();
+
+        let output = StreamOutput {
+            buffer,
+            bytes_count,
+        };
+
+        return Ok(StreamIo::Read(Ok(output)));
+    }
+
+    trace!("reading bytes synchronously into uninitialized buffer");
+    let mut buf: BorrowedBuf<'_> = buffer.spare_capacity_mut().into();
+    stream.read_buf(buf.unfilled())?;
+    let bytes_count = buf.len();
+
+    // SAFETY: the first `bytes_count` bytes of the spare capacity
+    // have been initialized by `read_buf`
+    unsafe { buffer.set_len(bytes_count) };
 
     let output = StreamOutput {
         buffer,
@@ -50,3 +324,903 @@ pub fn write(mut stream: impl Write, input: Result<StreamOutput, Vec<u8>>) -> io
 
     Ok(StreamIo::Write(Ok(output)))
 }
+
+/// Writes the given buffers at once using [`Write::write_vectored`].
+pub fn write_vectored(
+    mut stream: impl Write,
+    input: Result<StreamVectoredOutput, Vec<Vec<u8>>>,
+) -> io::Result<StreamIo> {
+    let buffers = match input {
+        Ok(output) => return Ok(StreamIo::WriteVectored(Ok(output))),
+        Err(buffers) => buffers,
+    };
+
+    trace!("writing {} buffers synchronously", buffers.len());
+    let slices: Vec<_> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+    let bytes_count = stream.write_vectored(&slices)?;
+
+    let output = StreamVectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(StreamIo::WriteVectored(Ok(output)))
+}
+
+/// Flushes the stream using [`Write::flush`].
+pub fn flush(mut stream: impl Write, flushed: bool) -> io::Result<StreamIo> {
+    if flushed {
+        return Ok(StreamIo::Flush(true));
+    }
+
+    trace!("flushing stream synchronously");
+    stream.flush()?;
+
+    Ok(StreamIo::Flush(true))
+}
+
+/// Flushes the stream, then shuts down its write half using
+/// [`ShutdownWrite::shutdown_write`].
+pub fn shutdown(mut stream: impl Write + ShutdownWrite, done: bool) -> io::Result<StreamIo> {
+    if done {
+        return Ok(StreamIo::Shutdown(true));
+    }
+
+    trace!("shutting down stream synchronously");
+    stream.flush()?;
+    stream.shutdown_write()?;
+
+    Ok(StreamIo::Shutdown(true))
+}
+
+/// A [`Write`] sink driving a [`WriteStream`] coroutine against the
+/// inner stream.
+///
+/// This bridges the coroutine write path into any API expecting a
+/// [`Write`], like [`write!`]. Each write drives the same coroutine
+/// until the whole given buffer is written, or until the inner stream
+/// reaches the End Of File, so that its buffers are reused across
+/// writes.
+///
+/// Likewise, each flush drives a [`FlushStream`] coroutine, whose
+/// [`StreamIo::Flush`] request flushes the inner stream.
+#[derive(Debug)]
+pub struct CoroutineWriter<S> {
+    /// The inner stream.
+    stream: S,
+
+    /// The write coroutine, reused across writes.
+    write: WriteStream,
+}
+
+impl<S: Write> CoroutineWriter<S> {
+    /// Creates a new writer over the given stream.
+    pub fn new(stream: S) -> Self {
+        let write = WriteStream::new(Vec::new());
+        Self { stream, write }
+    }
+
+    /// Returns a reference to the inner stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consumes the writer and returns the inner stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Write> Write for CoroutineWriter<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write.replace(buf.iter().copied());
+        let mut arg = None;
+
+        loop {
+            match self.write.resume(arg.take()) {
+                WriteStreamResult::Ok(output) => {
+                    let bytes_count = output.bytes_count;
+                    self.write.replace_buffer(output.buffer);
+                    break Ok(bytes_count);
+                }
+                WriteStreamResult::Eof | WriteStreamResult::Backpressure(_) => {
+                    break Ok(self.write.written())
+                }
+                WriteStreamResult::Io(StreamIo::Write(io)) => {
+                    arg = Some(write(&mut self.stream, io)?);
+                }
+                WriteStreamResult::Io(io) => {
+                    let err = format!("unexpected I/O while writing: {io:?}");
+                    break Err(io::Error::new(io::ErrorKind::InvalidInput, err));
+                }
+                WriteStreamResult::Err(err) => {
+                    break Err(io::Error::new(io::ErrorKind::Other, err));
+                }
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut coroutine = FlushStream::new();
+        let mut arg = None;
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                FlushStreamResult::Ok(()) => break Ok(()),
+                FlushStreamResult::Io(StreamIo::Flush(flushed)) => {
+                    arg = Some(flush(&mut self.stream, flushed)?);
+                }
+                FlushStreamResult::Io(io) => {
+                    let err = format!("unexpected I/O while flushing: {io:?}");
+                    break Err(io::Error::new(io::ErrorKind::InvalidInput, err));
+                }
+                FlushStreamResult::Err(err) => {
+                    break Err(io::Error::new(io::ErrorKind::Other, err));
+                }
+            }
+        }
+    }
+}
+
+/// In-memory stream returning bytes in chunks of configurable sizes.
+///
+/// Each read returns at most the next chunk size of the pattern, which
+/// repeats once exhausted. This simulates data arriving in arbitrary
+/// chunks, which is useful to test how coroutines behave across chunk
+/// boundaries.
+///
+/// Read bytes come from the inner data, whereas written bytes are
+/// appended to a separate buffer, see [`Self::written`]. Writes are
+/// not limited by the pattern, see [`Self::with_max_write`] to
+/// simulate partial writes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChunkedCursor {
+    data: Vec<u8>,
+    pos: usize,
+    pattern: Vec<usize>,
+    index: usize,
+    written: Vec<u8>,
+    max_write: Option<usize>,
+}
+
+impl ChunkedCursor {
+    /// Creates a new cursor over the given data, returning at most
+    /// `chunk_size` bytes per read.
+    pub fn new(data: impl Into<Vec<u8>>, chunk_size: usize) -> Self {
+        Self::with_pattern(data, [chunk_size])
+    }
+
+    /// Creates a new cursor over the given data, returning at most
+    /// the next chunk size of the given repeating pattern per read.
+    ///
+    /// Zero chunk sizes are treated as 1, since an empty read would
+    /// signal the End Of File. An empty pattern does not split reads.
+    pub fn with_pattern(
+        data: impl Into<Vec<u8>>,
+        pattern: impl IntoIterator<Item = usize>,
+    ) -> Self {
+        Self {
+            data: data.into(),
+            pos: 0,
+            pattern: pattern.into_iter().map(|size| size.max(1)).collect(),
+            index: 0,
+            written: Vec::new(),
+            max_write: None,
+        }
+    }
+
+    /// Writes at most the given amount of bytes per write.
+    ///
+    /// A zero maximum is treated as 1, since an empty write would
+    /// signal the End Of File.
+    pub fn with_max_write(mut self, max: usize) -> Self {
+        self.max_write = Some(max.max(1));
+        self
+    }
+
+    /// Returns the position of the next byte to read.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the bytes not read yet.
+    pub fn remaining(&self) -> &[u8] {
+        &self.data[self.pos..]
+    }
+
+    /// Returns the bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Takes the bytes written so far.
+    pub fn take_written(&mut self) -> Vec<u8> {
+        mem::take(&mut self.written)
+    }
+
+    /// Returns the next read chunk size, bounded by the given length.
+    fn next_chunk_size(&mut self, len: usize) -> usize {
+        let Some(size) = self.pattern.get(self.index) else {
+            return len;
+        };
+
+        self.index = (self.index + 1) % self.pattern.len();
+        cmp::min(*size, len)
+    }
+}
+
+impl Read for ChunkedCursor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.data.len() - self.pos;
+
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let n = self.next_chunk_size(cmp::min(buf.len(), remaining));
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Write for ChunkedCursor {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = cmp::min(buf.len(), self.max_write.unwrap_or(buf.len()));
+        self.written.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The direction of the bytes recorded by a [`TracingHandle`].
+#[cfg(feature = "trace")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// Bytes read from the stream.
+    Read,
+
+    /// Bytes written into the stream.
+    Write,
+}
+
+/// The standard, blocking stream runtime handler, recording the bytes
+/// read and written.
+///
+/// Each [`StreamIo`] is processed with [`handle`], then the bytes it
+/// actually read or wrote are appended to the trace, in order. Reads
+/// reaching the End Of File are recorded as empty. This gives a
+/// replayable log of a real session, for example to debug a protocol
+/// or to write regression tests.
+///
+/// Requires the `trace` feature, so that production builds do not pay
+/// for the copies.
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub struct TracingHandle<S> {
+    /// The inner stream.
+    stream: S,
+
+    /// The bytes read and written so far.
+    trace: Vec<(Direction, Vec<u8>)>,
+}
+
+#[cfg(feature = "trace")]
+impl<S: Read + Write> TracingHandle<S> {
+    /// Creates a new tracing handler over the given stream.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Processes the given I/O with [`handle`], recording its bytes.
+    pub fn handle(&mut self, io: StreamIo) -> io::Result<StreamIo> {
+        // responses are given back as is, hence not recorded
+        let request = matches!(
+            io,
+            StreamIo::Read(Err(_)) | StreamIo::Write(Err(_)) | StreamIo::WriteVectored(Err(_))
+        );
+
+        let io = handle(&mut self.stream, io)?;
+
+        if !request {
+            return Ok(io);
+        }
+
+        let record = match &io {
+            StreamIo::Read(Ok(output)) => (Direction::Read, output.bytes().to_vec()),
+            StreamIo::Write(Ok(output)) => (Direction::Write, output.bytes().to_vec()),
+            StreamIo::WriteVectored(Ok(output)) => {
+                let bytes = output.buffers.iter().flatten();
+                let bytes = bytes.take(output.bytes_count).copied().collect();
+                (Direction::Write, bytes)
+            }
+            _ => return Ok(io),
+        };
+
+        trace!("record {} bytes {:?}", record.1.len(), record.0);
+        self.trace.push(record);
+        Ok(io)
+    }
+
+    /// Returns the bytes read and written so far, in order.
+    pub fn trace(&self) -> &[(Direction, Vec<u8>)] {
+        &self.trace
+    }
+
+    /// Takes the bytes read and written so far, leaving the trace
+    /// empty.
+    pub fn take_trace(&mut self) -> Vec<(Direction, Vec<u8>)> {
+        mem::take(&mut self.trace)
+    }
+
+    /// Consumes the handler and returns the inner stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+/// A single step of a coroutine driven by a [`Scheduler`].
+#[derive(Debug)]
+pub enum Step<T> {
+    /// The coroutine needs the given I/O to be processed.
+    Io(StreamIo),
+
+    /// The coroutine terminated with the given output.
+    Done(io::Result<T>),
+}
+
+/// A coroutine task registered in a [`Scheduler`].
+struct Task<S, T> {
+    /// The task identifier.
+    id: usize,
+
+    /// The stream the coroutine progresses over.
+    stream: S,
+
+    /// The coroutine resume function.
+    resume: Box<dyn FnMut(Option<StreamIo>) -> Step<T>>,
+
+    /// The I/O response to resume the coroutine with.
+    arg: Option<StreamIo>,
+
+    /// The I/O request given back because the stream was not ready.
+    pending: Option<StreamIo>,
+}
+
+/// Minimal cooperative scheduler driving multiple coroutines
+/// round-robin, each one over its own stream.
+///
+/// Each call to [`Self::poll_once`] makes every task progress by one
+/// I/O step, which gives every coroutine a fair share of progress.
+///
+/// I/O requests are processed with [`handle_nonblocking`], so that
+/// streams can be set in non-blocking mode: a task whose stream is
+/// not ready keeps its request, which is processed again on the next
+/// poll.
+pub struct Scheduler<S, T> {
+    /// The tasks not terminated yet.
+    tasks: Vec<Task<S, T>>,
+
+    /// The identifier of the next spawned task.
+    next_id: usize,
+}
+
+impl<S: Read + Write, T> Scheduler<S, T> {
+    /// Creates a new scheduler without task.
+    pub fn new() -> Self {
+        Self {
+            tasks: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Registers a new task, made of a stream and of the resume
+    /// function of its coroutine, and returns its identifier.
+    ///
+    /// The resume function usually owns the coroutine and maps its
+    /// result to a [`Step`].
+    pub fn spawn(
+        &mut self,
+        stream: S,
+        resume: impl FnMut(Option<StreamIo>) -> Step<T> + 'static,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        trace!("spawn task {id}");
+
+        self.tasks.push(Task {
+            id,
+            stream,
+            resume: Box::new(resume),
+            arg: None,
+            pending: None,
+        });
+
+        id
+    }
+
+    /// Returns the amount of tasks not terminated yet.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if all the tasks terminated.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Makes each task progress by one I/O step.
+    ///
+    /// Terminated tasks are removed from the scheduler, and their
+    /// outputs are returned alongside their identifier. Tasks whose
+    /// stream is not ready are kept as is.
+    pub fn poll_once(&mut self) -> Vec<(usize, io::Result<T>)> {
+        let mut outputs = Vec::new();
+
+        for mut task in mem::take(&mut self.tasks) {
+            let io = match task.pending.take() {
+                Some(io) => io,
+                None => match (task.resume)(task.arg.take()) {
+                    Step::Io(io) => io,
+                    Step::Done(result) => {
+                        debug!("task {} terminated", task.id);
+                        outputs.push((task.id, result));
+                        continue;
+                    }
+                },
+            };
+
+            match handle_nonblocking(&mut task.stream, io) {
+                Ok(io) if is_request(&io) => {
+                    trace!("task {} not ready", task.id);
+                    task.pending = Some(io);
+                }
+                Ok(io) => task.arg = Some(io),
+                Err(err) => {
+                    debug!("task {} failed", task.id);
+                    outputs.push((task.id, Err(err)));
+                    continue;
+                }
+            }
+
+            self.tasks.push(task);
+        }
+
+        outputs
+    }
+}
+
+/// Returns `true` if the given I/O is a request, not processed yet.
+fn is_request(io: &StreamIo) -> bool {
+    matches!(
+        io,
+        StreamIo::Read(Err(_))
+            | StreamIo::Write(Err(_))
+            | StreamIo::WriteVectored(Err(_))
+            | StreamIo::Flush(false)
+            | StreamIo::Shutdown(false)
+    )
+}
+
+impl<S: Read + Write, T> Default for Scheduler<S, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, T> fmt::Debug for Scheduler<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<_> = self.tasks.iter().map(|task| task.id).collect();
+
+        f.debug_struct("Scheduler")
+            .field("tasks", &ids)
+            .field("next_id", &self.next_id)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read as _, Write as _};
+
+    use crate::coroutines::{
+        read_balanced::{ReadStreamBalanced, ReadStreamBalancedResult},
+        read_exact::{ReadStreamExact, ReadStreamExactResult},
+        read_to_end::{ReadStreamToEnd, ReadStreamToEndResult},
+        read_until::{ReadStreamUntil, ReadStreamUntilResult},
+        write::{WriteStream, WriteStreamResult},
+    };
+
+    use super::{ChunkedCursor, CoroutineWriter, Scheduler, Step};
+
+    #[cfg(unix)]
+    #[test]
+    fn handle_nonblocking_would_block() {
+        use std::os::unix::net::UnixStream;
+
+        use crate::io::StreamIo;
+
+        let _ = env_logger::try_init();
+
+        let (mut local, mut remote) = UnixStream::pair().unwrap();
+        local.set_nonblocking(true).unwrap();
+
+        // no data available yet, the request is given back
+        let buffer = vec![0; 8];
+        let ptr = buffer.as_ptr();
+
+        let buffer = match super::handle_nonblocking(&mut local, StreamIo::Read(Err(buffer))) {
+            Ok(StreamIo::Read(Err(buffer))) => buffer,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert_eq!(buffer.len(), 8);
+
+        // the same request can be processed again once data arrived
+        remote.write_all(b"hello").unwrap();
+
+        match super::handle_nonblocking(&mut local, StreamIo::Read(Err(buffer))) {
+            Ok(StreamIo::Read(Ok(output))) => assert_eq!(output.bytes(), b"hello"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn chunked_cursor_pattern() {
+        let _ = env_logger::try_init();
+
+        let mut cursor = ChunkedCursor::with_pattern(*b"abcdefghij", [1, 3, 2]);
+        let mut buffer = [0; 8];
+        let mut chunks = Vec::new();
+
+        loop {
+            match cursor.read(&mut buffer).unwrap() {
+                0 => break,
+                n => chunks.push(buffer[..n].to_vec()),
+            }
+        }
+
+        assert_eq!(chunks, [&b"a"[..], b"bcd", b"ef", b"g", b"hij"]);
+        assert!(cursor.remaining().is_empty());
+    }
+
+    #[test]
+    fn chunked_cursor_single_bytes() {
+        let _ = env_logger::try_init();
+
+        let input = br#"{"a": ["}", {"b": 1}]} tail"#;
+        let mut cursor = ChunkedCursor::new(*input, 1);
+
+        let mut read = ReadStreamBalanced::new();
+        let mut arg = None;
+        let mut reads = 0;
+
+        let value = loop {
+            match read.resume(arg.take()) {
+                ReadStreamBalancedResult::Ok(value) => break value,
+                ReadStreamBalancedResult::Io(io) => {
+                    reads += 1;
+                    arg = Some(super::handle(&mut cursor, io).unwrap());
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(value, br#"{"a": ["}", {"b": 1}]}"#);
+        assert_eq!(reads, value.len());
+        assert_eq!(cursor.remaining(), b" tail");
+    }
+
+    #[test]
+    fn chunked_cursor_read_until() {
+        let _ = env_logger::try_init();
+
+        let mut cursor = ChunkedCursor::new(*b"first\nsecond\n", 1);
+        let mut lines = Vec::new();
+
+        for _ in 0..2 {
+            let mut read = ReadStreamUntil::new(b'\n');
+            let mut arg = None;
+
+            let line = loop {
+                match read.resume(arg.take()) {
+                    ReadStreamUntilResult::Ok(line) => break line,
+                    ReadStreamUntilResult::Io(io) => {
+                        arg = Some(super::handle(&mut cursor, io).unwrap());
+                    }
+                    other => unreachable!("Unexpected result: {other:?}"),
+                }
+            };
+
+            // single-byte chunks never over-read
+            assert!(read.leftover().is_empty());
+            lines.push(line);
+        }
+
+        assert_eq!(lines, [&b"first\n"[..], b"second\n"]);
+    }
+
+    #[test]
+    fn chunked_cursor_read_exact() {
+        let _ = env_logger::try_init();
+
+        // simulates short reads of 3 bytes max
+        let mut cursor = ChunkedCursor::new(*b"0123456789tail", 3);
+
+        let mut read = ReadStreamExact::new(10);
+        let mut arg = None;
+        let mut reads = 0;
+
+        let bytes = loop {
+            match read.resume(arg.take()) {
+                ReadStreamExactResult::Ok(bytes) => break bytes,
+                ReadStreamExactResult::Io(io) => {
+                    reads += 1;
+                    arg = Some(super::handle(&mut cursor, io).unwrap());
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        };
+
+        assert_eq!(bytes, b"0123456789");
+        assert_eq!(reads, 4);
+        assert_eq!(cursor.remaining(), b"tail");
+
+        // writes are not limited by the read pattern
+        let mut write = WriteStream::new(b"hello".to_vec());
+
+        match write.resume(None) {
+            WriteStreamResult::Io(io) => {
+                let io = super::handle(&mut cursor, io).unwrap();
+                assert!(matches!(write.resume(Some(io)), WriteStreamResult::Ok(_)));
+            }
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+
+        assert_eq!(cursor.take_written(), b"hello");
+        assert!(cursor.written().is_empty());
+    }
+
+    #[test]
+    fn chunked_cursor_partial_writes() {
+        let _ = env_logger::try_init();
+
+        let mut cursor = ChunkedCursor::default().with_max_write(2);
+
+        let mut write = WriteStream::new(b"hello".to_vec());
+        let mut arg = None;
+        let mut writes = 0;
+
+        loop {
+            match write.resume(arg.take()) {
+                WriteStreamResult::Ok(_) => break,
+                WriteStreamResult::Io(io) => {
+                    writes += 1;
+                    arg = Some(super::handle(&mut cursor, io).unwrap());
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        assert_eq!(cursor.written(), b"hello");
+        assert_eq!(writes, 3);
+    }
+
+    #[test]
+    fn coroutine_writer() {
+        let _ = env_logger::try_init();
+
+        // simulates partial writes of 3 bytes max
+        let mut writer = CoroutineWriter::new(ChunkedCursor::default().with_max_write(3));
+
+        let name = "world";
+        write!(writer, "hello {name}!").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(writer.get_ref().written(), b"hello world!");
+
+        // flushing emits a flush request for the inner stream
+        let mut writer = CoroutineWriter::new(io::BufWriter::new(Vec::new()));
+
+        writer.write_all(b"buffered").unwrap();
+        assert!(writer.get_ref().get_ref().is_empty());
+
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref().get_ref(), b"buffered");
+    }
+
+    #[test]
+    fn scheduler() {
+        let _ = env_logger::try_init();
+
+        let mut scheduler = Scheduler::new();
+
+        for (data, chunk_size) in [(&b"abcdef"[..], 2), (b"gh", 1), (b"ijklmnop", 4)] {
+            let mut read = ReadStreamToEnd::new();
+
+            scheduler.spawn(
+                ChunkedCursor::new(data, chunk_size),
+                move |arg| match read.resume(arg) {
+                    ReadStreamToEndResult::Ok(bytes) => Step::Done(Ok(bytes)),
+                    ReadStreamToEndResult::Io(io) => Step::Io(io),
+                    ReadStreamToEndResult::Err(err) => {
+                        Step::Done(Err(io::Error::new(io::ErrorKind::Other, err)))
+                    }
+                },
+            );
+        }
+
+        let mut outputs = Vec::new();
+        let mut polls = 0;
+
+        while !scheduler.is_empty() {
+            polls += 1;
+
+            for (id, output) in scheduler.poll_once() {
+                outputs.push((polls, id, output.unwrap()));
+            }
+        }
+
+        // each task needs one poll per chunk, one poll to reach EOF
+        // and one last poll to terminate
+        let expected = [
+            (4, 1, b"gh".to_vec()),
+            (4, 2, b"ijklmnop".to_vec()),
+            (5, 0, b"abcdef".to_vec()),
+        ];
+
+        assert_eq!(outputs, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scheduler_not_ready() {
+        use std::os::unix::net::UnixStream;
+
+        let _ = env_logger::try_init();
+
+        let mut scheduler = Scheduler::new();
+        let mut remotes = Vec::new();
+
+        for _ in 0..2 {
+            let (local, remote) = UnixStream::pair().unwrap();
+            local.set_nonblocking(true).unwrap();
+            remotes.push(remote);
+
+            let mut read = ReadStreamExact::new(5);
+
+            scheduler.spawn(local, move |arg| match read.resume(arg) {
+                ReadStreamExactResult::Ok(bytes) => Step::Done(Ok(bytes)),
+                ReadStreamExactResult::Io(io) => Step::Io(io),
+                ReadStreamExactResult::Err(err) => {
+                    Step::Done(Err(io::Error::new(io::ErrorKind::Other, err)))
+                }
+            });
+        }
+
+        remotes[0].write_all(b"hello").unwrap();
+
+        let mut outputs = Vec::new();
+
+        for _ in 0..4 {
+            outputs.extend(scheduler.poll_once());
+        }
+
+        // the task whose stream is not ready is kept
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].0, 0);
+        assert_eq!(outputs.remove(0).1.unwrap(), b"hello");
+        assert_eq!(scheduler.len(), 1);
+
+        remotes[1].write_all(b"world").unwrap();
+
+        while !scheduler.is_empty() {
+            outputs.extend(scheduler.poll_once());
+        }
+
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].0, 1);
+        assert_eq!(outputs.remove(0).1.unwrap(), b"world");
+    }
+
+    #[cfg(nightly)]
+    #[test]
+    fn read_buf() {
+        use std::io::BufReader;
+
+        use crate::io::{StreamIo, StreamOutput};
+
+        let _ = env_logger::try_init();
+
+        let mut reader = BufReader::new("abcdef".as_bytes());
+
+        // a buffer with capacity but no initialized byte
+        let buffer = Vec::with_capacity(4);
+
+        let output = match super::read_buf(&mut reader, Err(buffer)).unwrap() {
+            StreamIo::Read(Ok(output)) => output,
+            other => unreachable!("Unexpected I/O: {other:?}"),
+        };
+
+        // only the bytes read have been initialized
+        assert_eq!(output.bytes(), b"abcd");
+        assert_eq!(output.buffer.len(), 4);
+        let capacity = output.buffer.capacity();
+
+        let StreamOutput { mut buffer, .. } = output;
+        buffer.clear();
+
+        // a shorter read does not shrink the buffer
+        let output = match super::read_buf(&mut reader, Err(buffer)).unwrap() {
+            StreamIo::Read(Ok(output)) => output,
+            other => unreachable!("Unexpected I/O: {other:?}"),
+        };
+
+        assert_eq!(output.bytes(), b"ef");
+        assert_eq!(output.buffer.len(), 2);
+        assert_eq!(output.buffer.capacity(), capacity);
+
+        // the length of a non-empty buffer limits the read
+        let mut reader = BufReader::new("abcdef".as_bytes());
+        let mut buffer = Vec::with_capacity(8);
+        buffer.resize(2, 0);
+
+        let output = match super::read_buf(&mut reader, Err(buffer)).unwrap() {
+            StreamIo::Read(Ok(output)) => output,
+            other => unreachable!("Unexpected I/O: {other:?}"),
+        };
+
+        assert_eq!(output.bytes(), b"ab");
+        assert_eq!(output.buffer.len(), 2);
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn tracing_handle() {
+        use crate::io::{StreamIo, StreamOutput};
+
+        use super::{Direction, TracingHandle};
+
+        let _ = env_logger::try_init();
+
+        let cursor = ChunkedCursor::new(*b"+OK ready\r\n", 4).with_max_write(4);
+        let mut handle = TracingHandle::new(cursor);
+
+        match handle.handle(StreamIo::Read(Err(vec![0; 16]))).unwrap() {
+            StreamIo::Read(Ok(output)) => assert_eq!(output.bytes(), b"+OK "),
+            other => unreachable!("Unexpected I/O: {other:?}"),
+        }
+
+        match handle
+            .handle(StreamIo::Write(Err(b"QUIT\r\n".to_vec())))
+            .unwrap()
+        {
+            StreamIo::Write(Ok(output)) => assert_eq!(output.bytes(), b"QUIT"),
+            other => unreachable!("Unexpected I/O: {other:?}"),
+        }
+
+        // responses and flushes carry no new bytes
+        let output = StreamOutput {
+            buffer: b"QUIT".to_vec(),
+            bytes_count: 4,
+        };
+
+        handle.handle(StreamIo::Write(Ok(output))).unwrap();
+        handle.handle(StreamIo::Flush(false)).unwrap();
+
+        let expected = [
+            (Direction::Read, b"+OK ".to_vec()),
+            (Direction::Write, b"QUIT".to_vec()),
+        ];
+
+        assert_eq!(handle.trace(), expected);
+        assert_eq!(handle.into_inner().written(), b"QUIT");
+    }
+}
@@ -1,11 +1,15 @@
 //! The Tokio-based, async stream runtime.
 
-use std::io;
+use std::{
+    io::{self, IoSlice},
+    time::{Duration, Instant},
+};
 
 use log::trace;
+use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-use crate::io::{StreamIo, StreamOutput};
+use crate::io::{init_read_buffer, StreamIo, StreamOutput, StreamVectoredOutput};
 
 /// The Tokio-based, async stream runtime handler.
 ///
@@ -18,9 +22,253 @@ pub async fn handle(
     match io {
         StreamIo::Read(io) => read(stream, io).await,
         StreamIo::Write(io) => write(stream, io).await,
+        StreamIo::WriteVectored(io) => write_vectored(stream, io).await,
+        StreamIo::Flush(flushed) => flush(stream, flushed).await,
+        StreamIo::Shutdown(done) => shutdown(stream, done).await,
     }
 }
 
+/// A [`StreamIo`] response alongside the time taken by the runtime
+/// to process it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimedStreamIo {
+    /// The I/O response.
+    pub io: StreamIo,
+
+    /// The wall-clock duration of the underlying read or write.
+    pub elapsed: Duration,
+}
+
+/// The Tokio-based, async stream runtime handler, measuring I/O
+/// latency.
+///
+/// Same as [`handle`], except that the duration of each underlying
+/// read or write is returned alongside the response, which can be
+/// used to build latency histograms.
+pub async fn handle_timed(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    io: StreamIo,
+) -> io::Result<TimedStreamIo> {
+    let start = Instant::now();
+    let io = handle(stream, io).await?;
+    let elapsed = start.elapsed();
+    trace!("processed I/O in {elapsed:?}");
+    Ok(TimedStreamIo { io, elapsed })
+}
+
+/// The Tokio-based, async stream runtime handler, bounded by a
+/// timeout.
+///
+/// Same as [`handle`], except that the I/O fails with
+/// [`StreamTimeoutError::TimedOut`] if it does not complete within
+/// the given duration. The timeout applies to this single I/O
+/// request, not to the whole coroutine progression.
+///
+/// Like [`handle_nonblocking`] for [`io::ErrorKind::WouldBlock`], the
+/// unfinished request is handed back unchanged in the error, so that
+/// its buffer is not lost. Since coroutines are resumable, the caller
+/// can process that same request again, then resume the coroutine
+/// with the response.
+///
+/// [`handle_nonblocking`]: super::std::handle_nonblocking
+pub async fn handle_with_timeout(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    io: StreamIo,
+    duration: Duration,
+) -> Result<StreamIo, StreamTimeoutError> {
+    timed_out("I/O", duration, stream, io).await
+}
+
+/// Errors that can occur when processing I/O bounded by timeouts.
+#[derive(Debug, Error)]
+pub enum StreamTimeoutError {
+    /// The I/O did not complete before the timeout elapsed.
+    ///
+    /// Contains the name of the timeout, its duration and the
+    /// unfinished request, unchanged.
+    #[error("{0} timeout elapsed after {1:?}")]
+    TimedOut(&'static str, Duration, StreamIo),
+
+    /// The I/O failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Converts the error into an [`io::Error`], timeouts being reported
+/// as [`io::ErrorKind::TimedOut`] and their request being dropped.
+impl From<StreamTimeoutError> for io::Error {
+    fn from(err: StreamTimeoutError) -> Self {
+        match err {
+            StreamTimeoutError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::TimedOut, err.to_string()),
+        }
+    }
+}
+
+/// Processes the given I/O request, failing with
+/// [`StreamTimeoutError::TimedOut`] once the given named timeout
+/// elapsed.
+///
+/// The request is processed in place, so that it is given back
+/// unchanged on timeout. Reads and writes are cancellation safe: no
+/// byte is read nor written when they time out.
+async fn timed_out(
+    name: &'static str,
+    timeout: Duration,
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    mut io: StreamIo,
+) -> Result<StreamIo, StreamTimeoutError> {
+    let bytes_count = match tokio::time::timeout(timeout, process(&mut stream, &mut io)).await {
+        Ok(bytes_count) => bytes_count?,
+        Err(_) => return Err(StreamTimeoutError::TimedOut(name, timeout, io)),
+    };
+
+    let io = match io {
+        StreamIo::Read(Err(buffer)) => StreamIo::Read(Ok(StreamOutput {
+            buffer,
+            bytes_count,
+        })),
+        StreamIo::Write(Err(buffer)) => StreamIo::Write(Ok(StreamOutput {
+            buffer,
+            bytes_count,
+        })),
+        StreamIo::WriteVectored(Err(buffers)) => {
+            StreamIo::WriteVectored(Ok(StreamVectoredOutput {
+                buffers,
+                bytes_count,
+            }))
+        }
+        StreamIo::Flush(_) => StreamIo::Flush(true),
+        StreamIo::Shutdown(_) => StreamIo::Shutdown(true),
+        io => io,
+    };
+
+    Ok(io)
+}
+
+/// Performs the I/O of the given request without consuming it, and
+/// returns the amount of bytes read or written.
+async fn process(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    io: &mut StreamIo,
+) -> io::Result<usize> {
+    match io {
+        StreamIo::Read(Err(buffer)) => {
+            trace!("reading bytes asynchronously");
+            stream.read(init_read_buffer(buffer)).await
+        }
+        StreamIo::Write(Err(bytes)) => {
+            trace!("writing bytes asynchronously");
+            stream.write(bytes).await
+        }
+        StreamIo::WriteVectored(Err(buffers)) => {
+            trace!("writing {} buffers asynchronously", buffers.len());
+            let slices: Vec<_> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+            stream.write_vectored(&slices).await
+        }
+        StreamIo::Flush(false) => {
+            trace!("flushing stream asynchronously");
+            stream.flush().await.map(|()| 0)
+        }
+        StreamIo::Shutdown(false) => {
+            trace!("shutting down stream asynchronously");
+            stream.shutdown().await.map(|()| 0)
+        }
+        // responses are given back as is
+        _ => Ok(0),
+    }
+}
+
+/// The timeouts applied by [`handle_with_timeouts`].
+///
+/// The first byte timeout bounds the time to receive the first byte
+/// of the stream, which helps to detect unresponsive peers quickly.
+/// Once a byte has been received, the idle timeout bounds every
+/// subsequent read or write instead.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StreamTimeouts {
+    /// The time to first byte timeout, if any.
+    first_byte: Option<Duration>,
+
+    /// The idle timeout, if any.
+    idle: Option<Duration>,
+
+    /// Whether the first byte has been received.
+    received: bool,
+}
+
+impl StreamTimeouts {
+    /// Creates new timeouts, with no bound at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails reads with [`StreamTimeoutError::TimedOut`] if no byte has
+    /// been received within the given duration.
+    pub fn with_first_byte_timeout(mut self, timeout: Duration) -> Self {
+        self.first_byte = Some(timeout);
+        self
+    }
+
+    /// Fails reads and writes with [`StreamTimeoutError::TimedOut`] if
+    /// they do not complete within the given duration.
+    ///
+    /// Until the first byte is received, reads are bounded by the
+    /// first byte timeout instead, if defined.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle = Some(timeout);
+        self
+    }
+
+    /// Returns `true` if the first byte has been received.
+    pub fn received(&self) -> bool {
+        self.received
+    }
+
+    /// Returns the timeout applying to the next read, with its name.
+    fn read_timeout(&self) -> Option<(&'static str, Duration)> {
+        match self.first_byte {
+            Some(timeout) if !self.received => Some(("first byte", timeout)),
+            _ => self.idle.map(|timeout| ("idle", timeout)),
+        }
+    }
+}
+
+/// The Tokio-based, async stream runtime handler, bounded by
+/// timeouts.
+///
+/// Same as [`handle`], except that I/O fails with
+/// [`StreamTimeoutError::TimedOut`] when the given timeouts elapse,
+/// see [`StreamTimeouts`]. Like [`handle_with_timeout`], the
+/// unfinished request is handed back in the error.
+pub async fn handle_with_timeouts(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    io: StreamIo,
+    timeouts: &mut StreamTimeouts,
+) -> Result<StreamIo, StreamTimeoutError> {
+    let timeout = match &io {
+        StreamIo::Read(_) => timeouts.read_timeout(),
+        StreamIo::Write(_)
+        | StreamIo::WriteVectored(_)
+        | StreamIo::Flush(_)
+        | StreamIo::Shutdown(_) => timeouts.idle.map(|timeout| ("idle", timeout)),
+    };
+
+    let io = match timeout {
+        None => handle(stream, io).await?,
+        Some((name, timeout)) => timed_out(name, timeout, stream, io).await?,
+    };
+
+    if let StreamIo::Read(Ok(output)) = &io {
+        if output.bytes_count > 0 && !timeouts.received {
+            trace!("received first byte");
+            timeouts.received = true;
+        }
+    }
+
+    Ok(io)
+}
+
 pub async fn read(
     mut stream: impl AsyncRead + Unpin,
     input: Result<StreamOutput, Vec<u8>>,
@@ -31,7 +279,7 @@ pub async fn read(
     };
 
     trace!("reading bytes asynchronously");
-    let bytes_count = stream.read(&mut buffer).await?;
+    let bytes_count = stream.read(init_read_buffer(&mut buffer)).await?;
 
     let output = StreamOutput {
         buffer,
@@ -60,3 +308,227 @@ pub async fn write(
 
     Ok(StreamIo::Write(Ok(output)))
 }
+
+/// Writes the given buffers at once using
+/// [`AsyncWriteExt::write_vectored`].
+pub async fn write_vectored(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<StreamVectoredOutput, Vec<Vec<u8>>>,
+) -> io::Result<StreamIo> {
+    let buffers = match input {
+        Ok(output) => return Ok(StreamIo::WriteVectored(Ok(output))),
+        Err(buffers) => buffers,
+    };
+
+    trace!("writing {} buffers asynchronously", buffers.len());
+    let slices: Vec<_> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+    let bytes_count = stream.write_vectored(&slices).await?;
+
+    let output = StreamVectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(StreamIo::WriteVectored(Ok(output)))
+}
+
+/// Flushes the stream using [`AsyncWriteExt::flush`].
+pub async fn flush(mut stream: impl AsyncWrite + Unpin, flushed: bool) -> io::Result<StreamIo> {
+    if flushed {
+        return Ok(StreamIo::Flush(true));
+    }
+
+    trace!("flushing stream asynchronously");
+    stream.flush().await?;
+
+    Ok(StreamIo::Flush(true))
+}
+
+/// Shuts down the write half of the stream using
+/// [`AsyncWriteExt::shutdown`].
+pub async fn shutdown(mut stream: impl AsyncWrite + Unpin, done: bool) -> io::Result<StreamIo> {
+    if done {
+        return Ok(StreamIo::Shutdown(true));
+    }
+
+    trace!("shutting down stream asynchronously");
+    stream.shutdown().await?;
+
+    Ok(StreamIo::Shutdown(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        time::{Duration, Instant},
+    };
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::{
+        coroutines::{
+            read::{ReadStream, ReadStreamResult},
+            shutdown::{ShutdownStream, ShutdownStreamResult},
+        },
+        io::StreamIo,
+    };
+
+    use super::{StreamTimeoutError, StreamTimeouts};
+
+    #[tokio::test]
+    async fn handle_shutdown() {
+        let _ = env_logger::try_init();
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(b"request").await.unwrap();
+
+        let mut shutdown = ShutdownStream::new();
+        let mut arg = None;
+
+        loop {
+            match shutdown.resume(arg.take()) {
+                ShutdownStreamResult::Ok(()) => break,
+                ShutdownStreamResult::Io(io) => {
+                    arg = Some(super::handle(&mut client, io).await.unwrap())
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        // the peer reaches EOF after the written bytes
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).await.unwrap();
+        assert_eq!(request, b"request");
+    }
+
+    #[tokio::test]
+    async fn handle_timed() {
+        let _ = env_logger::try_init();
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            server.write_all(b"delayed").await.unwrap();
+        });
+
+        let mut read = ReadStream::new();
+
+        let io = match read.resume(None) {
+            ReadStreamResult::Io(io) => io,
+            other => unreachable!("Unexpected result: {other:?}"),
+        };
+
+        let timed = super::handle_timed(&mut client, io).await.unwrap();
+
+        assert!(timed.elapsed >= Duration::from_millis(40));
+        assert!(timed.elapsed < Duration::from_secs(5));
+
+        match read.resume(Some(timed.io)) {
+            ReadStreamResult::Ok(output) => assert_eq!(output.bytes(), b"delayed"),
+            other => unreachable!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn handle_with_timeout() {
+        let _ = env_logger::try_init();
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+
+        let mut read = Reader::new();
+        let io = read.next();
+        let err = super::handle_with_timeout(&mut client, io, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+
+        // the unfinished request is handed back, so it can be retried
+        let io = match err {
+            StreamTimeoutError::TimedOut("I/O", _, io @ StreamIo::Read(Err(_))) => io,
+            other => unreachable!("Unexpected error: {other:?}"),
+        };
+
+        server.write_all(b"late").await.unwrap();
+
+        let io = super::handle_with_timeout(&mut client, io, Duration::from_secs(5))
+            .await
+            .unwrap();
+        read.check(io, b"late");
+    }
+
+    #[tokio::test]
+    async fn handle_with_first_byte_timeout() {
+        let _ = env_logger::try_init();
+
+        let (mut client, mut server) = tokio::io::duplex(64);
+
+        let mut timeouts = StreamTimeouts::new()
+            .with_first_byte_timeout(Duration::from_millis(50))
+            .with_idle_timeout(Duration::from_secs(10));
+
+        let mut read = Reader::new();
+
+        // the server stays silent
+        let start = Instant::now();
+        let io = read.next();
+        let err = super::handle_with_timeouts(&mut client, io, &mut timeouts)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            StreamTimeoutError::TimedOut("first byte", ..)
+        ));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(!timeouts.received());
+
+        // once the first byte is received, the longer idle timeout
+        // takes over
+        tokio::spawn(async move {
+            server.write_all(b"a").await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            server.write_all(b"b").await.unwrap();
+        });
+
+        let io = read.next();
+        let io = super::handle_with_timeouts(&mut client, io, &mut timeouts)
+            .await
+            .unwrap();
+        assert!(timeouts.received());
+        read.check(io, b"a");
+
+        let io = read.next();
+        let io = super::handle_with_timeouts(&mut client, io, &mut timeouts)
+            .await
+            .unwrap();
+        read.check(io, b"b");
+    }
+
+    /// Drives a [`ReadStream`] one read at a time.
+    struct Reader(ReadStream);
+
+    impl Reader {
+        fn new() -> Self {
+            Self(ReadStream::new())
+        }
+
+        fn next(&mut self) -> StreamIo {
+            match self.0.resume(None) {
+                ReadStreamResult::Io(io) => io,
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+
+        fn check(&mut self, io: StreamIo, expected: &[u8]) {
+            match self.0.resume(Some(io)) {
+                ReadStreamResult::Ok(output) => {
+                    assert_eq!(output.bytes(), expected);
+                    self.0.replace(output.buffer);
+                }
+                other => unreachable!("Unexpected result: {other:?}"),
+            }
+        }
+    }
+}